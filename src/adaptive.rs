@@ -0,0 +1,321 @@
+//! Adaptive Cuckoo Filter, which learns from reported false positives
+//!
+//! Based on the adaptive variant of the cuckoo filter (Mitzenmacher et. al., _Adaptive Cuckoo Filters_): when a query turns out to be a false positive, the filter changes the colliding slot so that the same query stops matching, while the item really stored there still does.
+//!
+//! The paper re-derives the stored fingerprint from the original item, which needs a copy of every item. Instead, each slot here also records 8 more bits of its item's hash when it's inserted (the extension), plus an adaptivity bit. Lookups ignore the extension until a false positive is reported against the slot; after that, the slot only matches queries whose extension agrees too.
+
+use crate::filter::{
+    alternate_bucket, buckets_for, BucketIndex, CuckooFilterError, Fingerprint, SplitMix64,
+    BUCKET_SIZE, ITEM_LIMIT,
+};
+use crate::kick::KickTable;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// One fingerprint plus its adaptivity state. A zero fingerprint marks an empty slot
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Slot {
+    fingerprint: Fingerprint,
+    /// More bits of the item's hash, only compared once `adapted` is set
+    extension: u8,
+    adapted: bool,
+}
+
+const EMPTY: Slot = Slot {
+    fingerprint: 0,
+    extension: 0,
+    adapted: false,
+};
+
+impl Slot {
+    fn matches(&self, fingerprint: Fingerprint, extension: u8) -> bool {
+        self.fingerprint == fingerprint && (!self.adapted || self.extension == extension)
+    }
+}
+
+/// A Cuckoo Filter that can be told about false positives, so it stops repeating them
+///
+/// Lookups and placement are the same as in `CuckooFilter`, at the cost of 3 bytes per slot rather than 1.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = AdaptiveCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug)]
+pub struct AdaptiveCuckooFilter<H: Hasher + Default> {
+    eviction_cache: Option<(BucketIndex, Slot)>,
+    data: Vec<[Slot; BUCKET_SIZE]>,
+    length_u32: u32,
+    slot_rng: SplitMix64,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> AdaptiveCuckooFilter<H> {
+    /// Try to create a new Adaptive Cuckoo Filter, sized the same way as `CuckooFilter::new`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new(max_items: usize) -> Result<AdaptiveCuckooFilter<H>, CuckooFilterError> {
+        if max_items > ITEM_LIMIT {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let number_of_buckets: usize = (max_items / BUCKET_SIZE).next_power_of_two();
+        Ok(AdaptiveCuckooFilter {
+            eviction_cache: None,
+            data: vec![[EMPTY; BUCKET_SIZE]; number_of_buckets],
+            length_u32: number_of_buckets as u32,
+            slot_rng: SplitMix64::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Is the filter full of items (practically speaking)? See `CuckooFilter::is_full`
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_some()
+    }
+
+    /// Add item to filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint, extension) = self.buckets_from_item(item);
+        let entry = Slot {
+            fingerprint,
+            extension,
+            adapted: false,
+        };
+        self.place(candidate_1, candidate_2, entry)
+    }
+
+    /// Check if item is in filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint, extension) = self.buckets_from_item(item);
+        self.stored_matches(candidate_1, candidate_2)
+            .any(|slot| slot.matches(fingerprint, extension))
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Delete an item from the filter
+    ///
+    /// When several slots match, one whose extension agrees with the item's is removed first, so an adapted slot isn't left behind for an item that's gone.
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint, extension) = self.buckets_from_item(item);
+        // An unadapted slot with this fingerprint may belong to another item, while one whose extension agrees is almost surely ours, so look for that first
+        let same_extension =
+            |slot: &Slot| slot.fingerprint == fingerprint && slot.extension == extension;
+        let any_match = |slot: &Slot| slot.matches(fingerprint, extension);
+        for wanted in [&same_extension as &dyn Fn(&Slot) -> bool, &any_match] {
+            if let Some((index, slot)) = self.eviction_cache {
+                if (index == candidate_1 || index == candidate_2) && wanted(&slot) {
+                    self.eviction_cache = None;
+                    return Ok(());
+                }
+            }
+            let found = [candidate_1, candidate_2].iter().find_map(|&bucket_index| {
+                let bucket = &self.data[bucket_index as usize];
+                let slot = bucket.iter().position(wanted)?;
+                Some((bucket_index, slot))
+            });
+            if let Some((bucket_index, slot)) = found {
+                self.data[bucket_index as usize][slot] = EMPTY;
+                return Ok(());
+            }
+        }
+        Err(CuckooFilterError::ItemDoesNotExist)
+    }
+
+    /// Tell the filter that `contains(item)` was a false positive (the item was never inserted), so the same query stops matching
+    ///
+    /// Every slot the item collides with is switched to also compare its extension bits. Returns whether the item now looks absent: it won't if a colliding slot happens to share its extension too (a 1 in 256 chance per slot), in which case nothing more can be done for that query. Genuine members keep matching, since their extensions came from their own hashes.
+    ///
+    /// Don't report items that really were inserted: their own slot can't be adapted away, so the call will return `false`, but it may still adapt slots of other items that share the fingerprint (which stay findable).
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = AdaptiveCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// // Nothing to fix for an item that isn't matching anyway
+    /// assert!(filter.report_false_positive(&"never inserted"));
+    /// ```
+    pub fn report_false_positive<T: Hash>(&mut self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint, extension) = self.buckets_from_item(item);
+        let mut still_matches = false;
+        let mut adapt = |slot: &mut Slot| {
+            if slot.matches(fingerprint, extension) {
+                slot.adapted = true;
+                still_matches |= slot.extension == extension;
+            }
+        };
+        if let Some((index, slot)) = &mut self.eviction_cache {
+            if *index == candidate_1 || *index == candidate_2 {
+                adapt(slot);
+            }
+        }
+        self.data[candidate_1 as usize]
+            .iter_mut()
+            .for_each(&mut adapt);
+        if candidate_2 != candidate_1 {
+            self.data[candidate_2 as usize]
+                .iter_mut()
+                .for_each(&mut adapt);
+        }
+        !still_matches
+    }
+
+    /// Calculate the buckets, fingerprint, and extension given a `Hash`able item
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex, Fingerprint, u8) {
+        let mut hasher = H::default();
+        item.hash(&mut hasher);
+        let hash_value = hasher.finish();
+        let (candidate_1, candidate_2, fingerprint) = buckets_for(hash_value, self.length_u32);
+        // The fingerprint uses bits 32-39, so take the next 8
+        (
+            candidate_1,
+            candidate_2,
+            fingerprint,
+            (hash_value >> 40) as u8,
+        )
+    }
+
+    /// Every slot a query for these buckets is compared against (both buckets and a matching eviction cache)
+    fn stored_matches(
+        &self,
+        candidate_1: u32,
+        candidate_2: u32,
+    ) -> impl Iterator<Item = &Slot> + '_ {
+        let cached = self
+            .eviction_cache
+            .iter()
+            .filter(move |(index, _)| *index == candidate_1 || *index == candidate_2)
+            .map(|(_, slot)| slot);
+        cached
+            .chain(self.data[candidate_1 as usize].iter())
+            .chain(self.data[candidate_2 as usize].iter())
+    }
+}
+
+impl<H: Hasher + Default> KickTable for AdaptiveCuckooFilter<H> {
+    type Index = BucketIndex;
+    type Entry = Slot;
+
+    fn try_insert_at_bucket(&mut self, bucket_index: BucketIndex, entry: Slot) -> bool {
+        match self.data[bucket_index as usize]
+            .iter_mut()
+            .find(|slot| slot.fingerprint == 0)
+        {
+            Some(slot) => {
+                *slot = entry;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The adaptivity state moves with its fingerprint
+    fn swap_slot(&mut self, bucket_index: BucketIndex, slot: usize, entry: Slot) -> Slot {
+        core::mem::replace(&mut self.data[bucket_index as usize][slot], entry)
+    }
+
+    fn other_bucket(&self, bucket_index: BucketIndex, entry: &Slot) -> BucketIndex {
+        alternate_bucket(bucket_index, entry.fingerprint, self.length_u32)
+    }
+
+    fn kicks_from_first(entry: &Slot) -> bool {
+        entry.fingerprint.is_multiple_of(2)
+    }
+
+    fn eviction_cache(&mut self) -> &mut Option<(BucketIndex, Slot)> {
+        &mut self.eviction_cache
+    }
+
+    fn slot_rng(&mut self) -> &mut SplitMix64 {
+        &mut self.slot_rng
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn reported_false_positives_stop_matching() {
+        let mut cf = AdaptiveCuckooFilter::<Murmur3Hasher>::new(1024).unwrap();
+        for i in 0..900u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let false_positives: Vec<u64> = (1_000_000..1_100_000u64)
+            .filter(|i| cf.contains(i))
+            .collect();
+        assert!(!false_positives.is_empty());
+        let fixed = false_positives
+            .iter()
+            .filter(|i| cf.report_false_positive(*i))
+            .count();
+        // Each one can only stay stuck if its extension collides as well
+        assert!(fixed * 10 > false_positives.len() * 9);
+        for i in false_positives.iter() {
+            assert_eq!(cf.contains(i), !cf.report_false_positive(i));
+        }
+        // Members are untouched, even after evictions moved adapted slots around
+        assert!((0..900u64).all(|i| cf.contains(&i)));
+        for i in 900..980u64 {
+            let _ = cf.insert(&i);
+        }
+        assert!((0..900u64).all(|i| cf.contains(&i)));
+        // Reporting a member can't make it disappear
+        assert!(!cf.report_false_positive(&7u64));
+        assert!(cf.contains(&7u64));
+        assert!(cf.delete(&7u64).is_ok());
+    }
+
+    // Deleting an item must take its own adapted slot, not an unadapted one that belongs to another item
+    #[test]
+    fn delete_prefers_the_matching_extension() {
+        let mut cf = AdaptiveCuckooFilter::<Murmur3Hasher>::new(8).unwrap();
+        // Items sharing buckets and a fingerprint, with different extensions
+        let (candidate_1, _, fingerprint, _) = cf.buckets_from_item(&0u64);
+        let mut group = vec![0u64];
+        for i in 1.. {
+            let (bucket_index, _, other_fingerprint, extension) = cf.buckets_from_item(&i);
+            if bucket_index == candidate_1
+                && other_fingerprint == fingerprint
+                && group.iter().all(|j| cf.buckets_from_item(j).3 != extension)
+            {
+                group.push(i);
+                if group.len() == 4 {
+                    break;
+                }
+            }
+        }
+        let [x, a, b, c] = [group[0], group[1], group[2], group[3]];
+        assert!(cf.insert(&x).is_ok());
+        assert!(cf.insert(&a).is_ok());
+        assert!(cf.report_false_positive(&c));
+        assert!(cf.delete(&x).is_ok());
+        // Takes the slot x left, ahead of a's
+        assert!(cf.insert(&b).is_ok());
+        assert!(cf.delete(&a).is_ok());
+        assert!(cf.contains(&b));
+    }
+}
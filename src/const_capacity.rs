@@ -0,0 +1,111 @@
+//! A Cuckoo Filter whose size is part of its type
+//!
+//! Only available with the `const_capacity` feature.
+
+use crate::filter::{CuckooFilter, CuckooFilterError, BUCKET_SIZE, ITEM_LIMIT};
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+/// A heap-backed Cuckoo Filter with `BUCKETS` buckets, fixed at compile time
+///
+/// `BUCKETS` must be a power of two within the item limit. That's checked when the type is instantiated, so a filter that compiles can always be built, and `new` can't fail. Operations that would change the size (like `shrink_to_fit`) aren't available, so the type stays honest. Everything that only reads the filter is available through `Deref`.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = ConstCuckooFilter::<Murmur3Hasher, 256>::new();
+/// assert_eq!(filter.capacity(), 1024);
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+///
+/// A bucket count that isn't a power of two doesn't build:
+///
+/// ```compile_fail
+/// use cuckoo_filter::*;
+///
+/// let filter = ConstCuckooFilter::<Murmur3Hasher, 100>::new();
+/// ```
+#[derive(Debug)]
+pub struct ConstCuckooFilter<H: Hasher + Default, const BUCKETS: usize> {
+    filter: CuckooFilter<H>,
+}
+
+impl<H: Hasher + Default, const BUCKETS: usize> ConstCuckooFilter<H, BUCKETS> {
+    /// Create a new, empty filter with `BUCKETS` buckets
+    pub fn new() -> ConstCuckooFilter<H, BUCKETS> {
+        const {
+            assert!(
+                BUCKETS.is_power_of_two() && BUCKETS <= ITEM_LIMIT / BUCKET_SIZE,
+                "ConstCuckooFilter needs a power of two number of buckets within the item limit"
+            );
+        }
+        let filter = CuckooFilter::<H>::new(BUCKETS * BUCKET_SIZE, false)
+            .expect("bucket count was checked at compile time");
+        ConstCuckooFilter { filter }
+    }
+
+    /// Add item to filter. See `CuckooFilter::insert`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        self.filter.insert(item)
+    }
+
+    /// Check if item is in filter. See `CuckooFilter::contains`
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.filter.contains(item)
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Delete item from filter. See `CuckooFilter::delete`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        self.filter.delete(item)
+    }
+}
+
+impl<H: Hasher + Default, const BUCKETS: usize> Default for ConstCuckooFilter<H, BUCKETS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher + Default, const BUCKETS: usize> Deref for ConstCuckooFilter<H, BUCKETS> {
+    type Target = CuckooFilter<H>;
+
+    fn deref(&self) -> &CuckooFilter<H> {
+        &self.filter
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn const_filter_operations() {
+        let mut cf = ConstCuckooFilter::<Murmur3Hasher, 256>::new();
+        assert_eq!(cf.num_buckets(), 256);
+        for i in 0..500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((0..500u64).all(|i| cf.contains(&i)));
+        assert!(cf.delete(&0u64).is_ok());
+        assert!(!cf.contains(&0u64));
+        assert_eq!(cf.len(), 499);
+    }
+}
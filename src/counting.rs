@@ -0,0 +1,368 @@
+//! Counting Cuckoo Filter, for multisets
+//!
+//! Each slot holds a fingerprint plus a small counter, so inserting an item twice and deleting it once leaves it present (the plain filter would either hold a stale duplicate or lose the item). Evictions move the fingerprint and its counter together.
+
+use crate::filter::{
+    alternate_bucket, buckets_for, BucketIndex, CuckooFilterError, Fingerprint, SplitMix64,
+    BUCKET_SIZE, ITEM_LIMIT,
+};
+use crate::kick::KickTable;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// A fingerprint and how many times it has been inserted. `(0, 0)` marks an empty slot
+type Slot = (Fingerprint, u8);
+
+/// A Cuckoo Filter that counts how many times each item was inserted
+///
+/// Counters saturate at 255. Fingerprint collisions work the same as in `CuckooFilter`: two items that share buckets and a fingerprint share a counter.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert_eq!(filter.count(&"hello, I am some data"), 2);
+/// let _ = filter.delete(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug)]
+pub struct CountingCuckooFilter<H: Hasher + Default> {
+    eviction_cache: Option<(BucketIndex, Slot)>,
+    data: Vec<[Slot; BUCKET_SIZE]>,
+    length_u32: u32,
+    slot_rng: SplitMix64,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> CountingCuckooFilter<H> {
+    /// Try to create a new Counting Cuckoo Filter, sized the same way as `CuckooFilter::new`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new(max_items: usize) -> Result<CountingCuckooFilter<H>, CuckooFilterError> {
+        if max_items > ITEM_LIMIT {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let number_of_buckets: usize = (max_items / BUCKET_SIZE).next_power_of_two();
+        Ok(CountingCuckooFilter {
+            eviction_cache: None,
+            data: vec![[(0, 0); BUCKET_SIZE]; number_of_buckets],
+            length_u32: number_of_buckets as u32,
+            slot_rng: SplitMix64::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Is the filter full of items (practically speaking)? See `CuckooFilter::is_full`
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_some()
+    }
+
+    /// Add one to an item's count, placing it if it's new
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the item is new and the filter is "practically" full
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if let Some(slot) = self.find_mut(candidate_1, candidate_2, fingerprint) {
+            slot.1 = slot.1.saturating_add(1);
+            return Ok(());
+        }
+        self.place(candidate_1, candidate_2, (fingerprint, 1))
+    }
+
+    /// How many times the item has been inserted (minus deletes), or 0 if it's absent
+    pub fn count<T: Hash>(&self, item: &T) -> u8 {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.find(candidate_1, candidate_2, fingerprint)
+            .map_or(0, |(_, count)| count)
+    }
+
+    /// Check if item is in filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.count(item) > 0
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Subtract one from an item's count, freeing its slot when the count reaches zero
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        let slot = self
+            .find_mut(candidate_1, candidate_2, fingerprint)
+            .ok_or(CuckooFilterError::ItemDoesNotExist)?;
+        slot.1 -= 1;
+        if slot.1 == 0 {
+            *slot = (0, 0);
+        }
+        if matches!(self.eviction_cache, Some((_, (0, 0)))) {
+            self.eviction_cache = None;
+        }
+        Ok(())
+    }
+
+    /// Add everything in `other` into this filter, summing the counts of items both filters hold
+    ///
+    /// This is multiset union: an item inserted twice into each filter has a count of 4 afterwards (saturating at 255). Both filters must have the same number of buckets and use the same `Hasher`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut ours = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+    /// let mut theirs = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+    /// let _ = ours.insert(&"hello, I am some data");
+    /// let _ = theirs.insert(&"hello, I am some data");
+    /// assert!(ours.merge(&theirs).is_ok());
+    /// assert_eq!(ours.count(&"hello, I am some data"), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::LengthMismatch`: the filters have different numbers of buckets
+    /// - `CuckooFilterError::OutOfSpace`: this filter filled up part way through (what was merged before that stays merged)
+    pub fn merge(&mut self, other: &CountingCuckooFilter<H>) -> Result<(), CuckooFilterError> {
+        if self.length_u32 != other.length_u32 {
+            return Err(CuckooFilterError::LengthMismatch);
+        }
+        for (bucket_index, (fingerprint, count)) in other.stored_slots() {
+            let alternate_index = alternate_bucket(bucket_index, fingerprint, self.length_u32);
+            if let Some(slot) = self.find_mut(bucket_index, alternate_index, fingerprint) {
+                slot.1 = slot.1.saturating_add(count);
+            } else {
+                self.place(bucket_index, alternate_index, (fingerprint, count))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Find slots whose counters have saturated at 255, returning how many there are, and free them if `clear` is set
+    ///
+    /// A saturated counter has lost track of how many inserts it's seen, so deletes can never bring it back to zero: the item becomes permanent. Call this with `clear: false` to detect counter overflow in skewed workloads, or `clear: true` to drop those items (they'll then look absent until reinserted).
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+    /// for _ in 0..300 {
+    ///     let _ = filter.insert(&"hello, I am some data");
+    /// }
+    /// assert_eq!(filter.prune_saturated(false), 1);
+    /// ```
+    pub fn prune_saturated(&mut self, clear: bool) -> usize {
+        let mut saturated = 0;
+        for slot in self.data.iter_mut().flatten() {
+            if slot.1 == u8::MAX {
+                saturated += 1;
+                if clear {
+                    *slot = (0, 0);
+                }
+            }
+        }
+        if let Some((_, (_, u8::MAX))) = self.eviction_cache {
+            saturated += 1;
+            if clear {
+                self.eviction_cache = None;
+            }
+        }
+        saturated
+    }
+
+    /// Every occupied slot alongside the bucket it sits in, including the eviction cache
+    fn stored_slots(&self) -> impl Iterator<Item = (BucketIndex, Slot)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(bucket_index, bucket)| {
+                bucket
+                    .iter()
+                    .filter(|slot| slot.1 != 0)
+                    .map(move |&slot| (bucket_index as BucketIndex, slot))
+            })
+            .chain(self.eviction_cache)
+    }
+
+    /// Calculate the buckets given a `Hash`able item
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex, Fingerprint) {
+        let mut hasher = H::default();
+        item.hash(&mut hasher);
+        buckets_for(hasher.finish(), self.length_u32)
+    }
+
+    /// The slot holding this fingerprint in either bucket (or the eviction cache), if any
+    fn find(&self, candidate_1: u32, candidate_2: u32, fingerprint: u8) -> Option<Slot> {
+        let in_cache = self.eviction_cache.and_then(|(index, slot)| {
+            let matches = slot.0 == fingerprint && (index == candidate_1 || index == candidate_2);
+            matches.then_some(slot)
+        });
+        in_cache.or_else(|| {
+            [candidate_1, candidate_2]
+                .iter()
+                .flat_map(|&bucket_index| self.data[bucket_index as usize])
+                .find(|slot| slot.0 == fingerprint)
+        })
+    }
+
+    /// Like `find`, but hands back the slot itself so its counter can be changed
+    fn find_mut(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+    ) -> Option<&mut Slot> {
+        if let Some((index, slot)) = &mut self.eviction_cache {
+            if slot.0 == fingerprint && (*index == candidate_1 || *index == candidate_2) {
+                return Some(slot);
+            }
+        }
+        let (bucket_index, slot) = [candidate_1, candidate_2]
+            .iter()
+            .find_map(|&bucket_index| {
+                self.data[bucket_index as usize]
+                    .iter()
+                    .position(|slot| slot.0 == fingerprint)
+                    .map(|slot| (bucket_index, slot))
+            })?;
+        Some(&mut self.data[bucket_index as usize][slot])
+    }
+}
+
+impl<H: Hasher + Default> KickTable for CountingCuckooFilter<H> {
+    type Index = BucketIndex;
+    type Entry = Slot;
+
+    fn try_insert_at_bucket(&mut self, bucket_index: BucketIndex, entry: Slot) -> bool {
+        match self.data[bucket_index as usize]
+            .iter_mut()
+            .find(|slot| slot.1 == 0)
+        {
+            Some(slot) => {
+                *slot = entry;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The counter moves with its fingerprint
+    fn swap_slot(&mut self, bucket_index: BucketIndex, slot: usize, entry: Slot) -> Slot {
+        core::mem::replace(&mut self.data[bucket_index as usize][slot], entry)
+    }
+
+    fn other_bucket(&self, bucket_index: BucketIndex, entry: &Slot) -> BucketIndex {
+        alternate_bucket(bucket_index, entry.0, self.length_u32)
+    }
+
+    fn kicks_from_first(entry: &Slot) -> bool {
+        entry.0.is_multiple_of(2)
+    }
+
+    fn eviction_cache(&mut self) -> &mut Option<(BucketIndex, Slot)> {
+        &mut self.eviction_cache
+    }
+
+    fn slot_rng(&mut self) -> &mut SplitMix64 {
+        &mut self.slot_rng
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn counts_survive_evictions() {
+        let mut cf = CountingCuckooFilter::<Murmur3Hasher>::new(1024).unwrap();
+        for i in 0..800u64 {
+            assert!(cf.insert(&i).is_ok());
+            if i % 4 == 0 {
+                assert!(cf.insert(&i).is_ok());
+            }
+        }
+        assert!((0..800u64).all(|i| cf.count(&i) >= if i % 4 == 0 { 2 } else { 1 }));
+        assert_eq!(
+            cf.delete(&"never inserted"),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+    }
+
+    #[test]
+    fn deletes_undo_inserts_one_at_a_time() {
+        let mut cf = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+        for _ in 0..3 {
+            assert!(cf.insert(&"repeated").is_ok());
+        }
+        // One slot, counted three times
+        assert_eq!(cf.stored_slots().count(), 1);
+        assert!(cf.delete(&"repeated").is_ok());
+        assert!(cf.delete(&"repeated").is_ok());
+        assert!(cf.contains(&"repeated"));
+        assert_eq!(cf.count(&"repeated"), 1);
+        assert!(cf.delete(&"repeated").is_ok());
+        assert!(!cf.contains(&"repeated"));
+        assert_eq!(cf.stored_slots().count(), 0);
+        assert_eq!(
+            cf.delete(&"repeated"),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+    }
+
+    #[test]
+    fn prune_saturated_finds_overflowed_counters() {
+        let mut cf = CountingCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+        for _ in 0..300 {
+            assert!(cf.insert(&"hot").is_ok());
+        }
+        assert!(cf.insert(&"cold").is_ok());
+        assert_eq!(cf.count(&"hot"), u8::MAX);
+        // Detecting leaves it in place
+        assert_eq!(cf.prune_saturated(false), 1);
+        assert_eq!(cf.count(&"hot"), u8::MAX);
+        // Clearing removes only the saturated item
+        assert_eq!(cf.prune_saturated(true), 1);
+        assert!(!cf.contains(&"hot"));
+        assert!(cf.contains(&"cold"));
+        assert_eq!(cf.prune_saturated(false), 0);
+    }
+
+    #[test]
+    fn merge_sums_counts() {
+        let mut ours = CountingCuckooFilter::<Murmur3Hasher>::new(256).unwrap();
+        let mut theirs = CountingCuckooFilter::<Murmur3Hasher>::new(256).unwrap();
+        for _ in 0..3 {
+            assert!(ours.insert(&"shared").is_ok());
+        }
+        for _ in 0..4 {
+            assert!(theirs.insert(&"shared").is_ok());
+        }
+        assert!(ours.insert(&"ours only").is_ok());
+        assert!(theirs.insert(&"theirs only").is_ok());
+        assert!(ours.merge(&theirs).is_ok());
+        assert_eq!(ours.count(&"shared"), 7);
+        assert_eq!(ours.count(&"ours only"), 1);
+        assert_eq!(ours.count(&"theirs only"), 1);
+        // Counters saturate rather than wrapping
+        for _ in 0..100 {
+            assert!(ours.merge(&theirs).is_ok());
+        }
+        assert_eq!(ours.count(&"shared"), u8::MAX);
+        let small = CountingCuckooFilter::<Murmur3Hasher>::new(16).unwrap();
+        assert_eq!(ours.merge(&small), Err(CuckooFilterError::LengthMismatch));
+    }
+}
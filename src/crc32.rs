@@ -0,0 +1,54 @@
+//! CRC-32 (the IEEE 802.3 / zlib polynomial), used for block checksums
+//!
+//! This is a small table-driven implementation so the crate doesn't need a dependency for it.
+
+/// Reflected form of the IEEE polynomial
+const POLYNOMIAL: u32 = 0xedb8_8320;
+
+/// Lookup table for processing a byte at a time, built at compile time
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Extend a running CRC-32 with `bytes`, zlib style: start from 0, and `crc32_update(crc32_update(0, a), b)` equals the CRC of `a` followed by `b`
+pub(crate) fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in bytes {
+        crc = (crc >> 8) ^ TABLE[((crc ^ byte as u32) & 0xff) as usize];
+    }
+    !crc
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        // The standard check value for CRC-32/ISO-HDLC
+        assert_eq!(crc32_update(0, b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32_update(0, b""), 0);
+        assert_eq!(
+            crc32_update(crc32_update(0, b"1234"), b"56789"),
+            crc32_update(0, b"123456789")
+        );
+    }
+}
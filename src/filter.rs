@@ -2,57 +2,221 @@
 //!
 //! This implementation is based on the paper _Cuckoo Filter: Practically Better Than Bloom_, by Fan et. al.
 //!
-//! The paper recommends a (2, 4) CF (2 possible buckets for each item, and 4 fingerprints in each bucket) because it's space optimal for practical false positive rates. Assuming our CF will hold up to a few billion items, 6 bits per fingerprint is sufficient (24 bits per bucket), but we round up to one byte per fingerprint for the sake of practicality.
+//! The paper recommends a (2, 4) CF (2 possible buckets for each item, and 4 fingerprints in each bucket) because it's space optimal for practical false positive rates. Assuming our CF will hold up to a few billion items, 6 bits per fingerprint is sufficient (24 bits per bucket), but we round up to one byte per fingerprint for the sake of practicality. If memory matters more than speed and false positives, `PackedCuckooFilter` with `fp_bits` of 6 stores each bucket in 3 bytes instead of 4.
 //!
 //! The paper's authors have provided a reference C++ implementation in this repository: <https://github.com/efficient/cuckoofilter>
 
+use crate::crc32::crc32_update;
+use alloc::boxed::Box;
 use alloc::vec;
 use alloc::vec::Vec;
+use core::any::Any;
 use core::default::Default;
 use core::hash::{Hash, Hasher};
 use core::marker::PhantomData;
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+mod builder;
+#[cfg(feature = "std")]
+mod dot;
+#[cfg(feature = "rand")]
+mod fill;
+#[cfg(feature = "std")]
+mod io;
+mod mmap;
+mod serialize;
+
+pub use builder::CuckooFilterBuilder;
+pub use mmap::CuckooFilterRef;
 
 pub type BucketIndex = u32;
 pub type Fingerprint = u8;
 
-const MAX_EVICTIONS: u16 = 500;
-/// Each bucket holds 4 fingerprints
-const BUCKET_SIZE: usize = 4;
+pub(crate) const MAX_EVICTIONS: u16 = 500;
+/// Odd constant the fingerprint is multiplied by before being XORed into a bucket index (from the reference implementation)
+pub(crate) const FINGERPRINT_MULTIPLIER: u32 = 0x5bd1e995;
+/// Each bucket holds 4 fingerprints, unless a `CuckooFilter` picks another size
+pub(crate) const BUCKET_SIZE: usize = 4;
 /// With 32 bit hash functions, we can hold (address) up to 32 bits worth of buckets
 const MAX_BUCKETS: usize = u32::MAX as usize;
 /// The item limit needs to respect the POW(2) rounding we do
-const ITEM_LIMIT: usize = (MAX_BUCKETS.next_power_of_two() >> 1) * BUCKET_SIZE;
+pub(crate) const ITEM_LIMIT: usize = (MAX_BUCKETS.next_power_of_two() >> 1) * BUCKET_SIZE;
+/// The slot `delete_locating` reports when it cleared the eviction cache rather than a bucket (one past the last real slot). For filters with another bucket size `B`, that's `B` instead
+pub const EVICTION_CACHE_SLOT: usize = BUCKET_SIZE;
 
-/// An eviction cache holds an item that we couldn't reinsert
+/// How many fingerprints the eviction cache holds before the filter counts as full
+pub(crate) const EVICTION_CACHE_CAPACITY: usize = 8;
+
+/// The eviction cache holds fingerprints that we couldn't reinsert
 ///
-/// An item being here means that the filter is "probabilistically full". It may not be technically 100% saturated, but we ran into so many hash collisions that we had to stop. (Using a bad hash function may result in being "full" early)
+/// A fingerprint lands here when an insert runs out of kicks. One long eviction chain can just be bad luck, so there's room for a few. Once every slot is taken, the filter is "probabilistically full". It may not be technically 100% saturated, but we ran into so many hash collisions that we had to stop. (Using a bad hash function may result in being "full" early)
 #[derive(Debug)]
-struct EvictionVictim {
-    index: u32,
-    fingerprint: Fingerprint,
-    used: bool,
+struct EvictionCache {
+    // Each victim's bucket index (one of its two candidates) and fingerprint. Only the first `len` are in use
+    victims: [(BucketIndex, Fingerprint); EVICTION_CACHE_CAPACITY],
+    len: usize,
 }
 
-impl EvictionVictim {
-    fn new() -> EvictionVictim {
-        EvictionVictim {
-            index: 0,
-            fingerprint: 0,
-            used: false,
+impl EvictionCache {
+    fn new() -> EvictionCache {
+        EvictionCache {
+            victims: [(0, 0); EVICTION_CACHE_CAPACITY],
+            len: 0,
+        }
+    }
+
+    /// The victims currently held, oldest first (until one is removed from the middle)
+    fn victims(&self) -> &[(BucketIndex, Fingerprint)] {
+        &self.victims[..self.len]
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == EVICTION_CACHE_CAPACITY
+    }
+
+    /// Park a fingerprint. The cache must not be full
+    fn push(&mut self, bucket_index: BucketIndex, fingerprint: Fingerprint) {
+        self.victims[self.len] = (bucket_index, fingerprint);
+        self.len += 1;
+    }
+
+    /// Take out the victim at `position`, moving the newest one into its place
+    fn remove(&mut self, position: usize) -> (BucketIndex, Fingerprint) {
+        let victim = self.victims[position];
+        self.len -= 1;
+        self.victims[position] = self.victims[self.len];
+        self.victims[self.len] = (0, 0);
+        victim
+    }
+
+    /// Take out the newest victim, if there is one
+    fn pop(&mut self) -> Option<(BucketIndex, Fingerprint)> {
+        (!self.is_empty()).then(|| self.remove(self.len - 1))
+    }
+
+    /// Drop every victim `keep` says no to
+    fn retain(&mut self, keep: impl Fn(BucketIndex, Fingerprint) -> bool) {
+        let mut position = 0;
+        while position < self.len {
+            let (bucket_index, fingerprint) = self.victims[position];
+            if keep(bucket_index, fingerprint) {
+                position += 1;
+            } else {
+                self.remove(position);
+            }
         }
     }
 
+    /// Where a fingerprint for this pair of buckets sits in the cache, if it does
+    fn position(
+        &self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: Fingerprint,
+    ) -> Option<usize> {
+        self.victims().iter().position(|&(index, cached)| {
+            cached == fingerprint && (index == candidate_1 || index == candidate_2)
+        })
+    }
+
+    /// How many copies of a fingerprint for this pair of buckets are cached
+    fn copies(&self, candidate_1: u32, candidate_2: u32, fingerprint: Fingerprint) -> usize {
+        self.victims()
+            .iter()
+            .filter(|&&(index, cached)| {
+                cached == fingerprint && (index == candidate_1 || index == candidate_2)
+            })
+            .count()
+    }
+
     fn reset(&mut self) {
-        self.index = 0;
-        self.fingerprint = 0;
-        self.used = false;
+        *self = EvictionCache::new();
+    }
+}
+
+/// A tiny SplitMix64 generator for picking which slot to evict from
+///
+/// The paper calls for a random choice, and this keeps the crate free of `std`/`rand` while providing one. It's seeded from the filter's seed, so evictions (and therefore the exact layout) are reproducible.
+#[derive(Debug)]
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// The last item passed to `lookup_cached`, and the buckets and fingerprint it hashed to
+///
+/// The triple only depends on the item, the number of buckets, the seed (which never changes for a given filter), and the hasher key, so it stays valid through inserts and deletes. It goes stale if the filter is resized, which `length_u32` catches, or if `set_hasher_key` or `set_seeded_hasher` changes how items are hashed, which drops it.
+#[derive(Debug)]
+struct LastLookup {
+    item: Box<dyn Any + Send + Sync>,
+    length_u32: u32,
+    triple: (BucketIndex, BucketIndex, Fingerprint),
+}
+
+/// How many `record_sample` samples a filter keeps
+const SAMPLE_HISTORY: usize = 64;
+
+/// Fixed-size ring buffer of `(len, load_factor)` samples, backing `record_sample`
+///
+/// Each sample is written twice, `SAMPLE_HISTORY` slots apart, so the most recent samples are always one contiguous oldest-first run and `recent_samples` can hand out a plain slice.
+#[derive(Debug)]
+struct SampleRing {
+    samples: [(usize, f32); 2 * SAMPLE_HISTORY],
+    next: usize,
+    recorded: usize,
+}
+
+impl SampleRing {
+    fn new() -> SampleRing {
+        SampleRing {
+            samples: [(0, 0.0); 2 * SAMPLE_HISTORY],
+            next: 0,
+            recorded: 0,
+        }
+    }
+
+    fn push(&mut self, sample: (usize, f32)) {
+        self.samples[self.next] = sample;
+        self.samples[self.next + SAMPLE_HISTORY] = sample;
+        self.next = (self.next + 1) % SAMPLE_HISTORY;
+        self.recorded = (self.recorded + 1).min(SAMPLE_HISTORY);
+    }
+
+    fn as_slice(&self) -> &[(usize, f32)] {
+        if self.recorded < SAMPLE_HISTORY {
+            &self.samples[..self.recorded]
+        } else {
+            &self.samples[self.next..self.next + SAMPLE_HISTORY]
+        }
     }
 }
 
 /// Possible errors for the Cuckoo Filter
 #[derive(Debug, Eq, PartialEq)]
 pub enum CuckooFilterError {
-    /// Requested capacity at initialization exceeds item limit
+    /// Requested capacity at initialization exceeds item limit, or for `from_bytes_with_limit`, the serialized filter has more buckets than allowed
     CapacityExceedsItemLimit,
     /// Model had too many collisions and ran out of effective space
     OutOfSpace,
@@ -60,27 +224,297 @@ pub enum CuckooFilterError {
     ItemAlreadyExists,
     /// For `delete`, when item doesn't exist
     ItemDoesNotExist,
+    /// For `from_bytes` (or `from_raw_parts`), when the input isn't a valid serialized filter
+    InvalidEncoding,
+    /// For operations across two filters (such as `drain_into`) that need both to have the same number of buckets
+    LengthMismatch,
+    /// For `strict_delete`, when more than one stored fingerprint matches the item, so it's unclear which copy to remove
+    AmbiguousDelete,
+    /// For `PackedCuckooFilter::new_with_fp_bits`, when the fingerprint width is outside the supported 4 to 16 bits
+    UnsupportedFingerprintBits,
+    /// For `grow` with `error_on_resize_loss` set, when a fingerprint couldn't be placed in the bigger filter
+    ResizeLoss,
+    /// When the item's buckets already hold `max_bucket_collisions` copies of its fingerprint (see `set_max_bucket_collisions`)
+    TooManyCollisions,
+    /// For `from_bytes`, when the input was written by a newer (or unknown) version of the binary format
+    UnsupportedFormatVersion,
+    /// For `new_with_params`, when `max_evictions` is 0 (every insert into two full buckets would fail)
+    ZeroMaxEvictions,
+}
+
+impl core::fmt::Display for CuckooFilterError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let message = match self {
+            CuckooFilterError::CapacityExceedsItemLimit => {
+                "requested capacity exceeds the filter's item limit"
+            }
+            CuckooFilterError::OutOfSpace => "the filter is out of space",
+            CuckooFilterError::ItemAlreadyExists => "the item is already in the filter",
+            CuckooFilterError::ItemDoesNotExist => "the item isn't in the filter",
+            CuckooFilterError::InvalidEncoding => "the bytes aren't a valid serialized filter",
+            CuckooFilterError::LengthMismatch => "the filters have different numbers of buckets",
+            CuckooFilterError::AmbiguousDelete => {
+                "more than one stored fingerprint matches the item"
+            }
+            CuckooFilterError::UnsupportedFingerprintBits => {
+                "fingerprints must be between 4 and 16 bits wide"
+            }
+            CuckooFilterError::ResizeLoss => "a fingerprint was lost while resizing the filter",
+            CuckooFilterError::TooManyCollisions => {
+                "the item's buckets already hold too many copies of its fingerprint"
+            }
+            CuckooFilterError::UnsupportedFormatVersion => {
+                "the serialized filter uses an unsupported format version"
+            }
+            CuckooFilterError::ZeroMaxEvictions => "the eviction limit must be at least 1",
+        };
+        f.write_str(message)
+    }
+}
+
+impl core::error::Error for CuckooFilterError {}
+
+/// Given a hash value (digest) and a number of buckets, compute an item's two candidate buckets and its fingerprint
+///
+/// This is the pure math behind every filter operation, without needing a filter: handy for precomputing placements or checking another implementation against this one. `length_u32` is the number of buckets (`num_buckets()`), and must not be zero.
+///
+/// We modulo the bucket indices because the hash may output a value larger than the true length of the backing data array.
+///
+/// This is (mostly) Equation 1 in section 3.1 of the paper. However, unlike Equation 1, we follow the reference implementation from the authors and instead compute bucket 2 by XORing with a magic constant.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// const PLACEMENT: (BucketIndex, BucketIndex, Fingerprint) = buckets_for(0xdead_beef_0000_0011, 16);
+/// assert_eq!(PLACEMENT.0, 1);
+/// ```
+pub const fn buckets_for(
+    hash_value: u64,
+    length_u32: u32,
+) -> (BucketIndex, BucketIndex, Fingerprint) {
+    let upper_bits: u32 = (hash_value >> 32) as u32;
+    let mut fingerprint_u32: u32 = upper_bits & ((1 << 8) - 1);
+    // A zero fingerprint is indistinguishable from an empty slot, so bump it to 1 (as the reference implementation does)
+    if fingerprint_u32 == 0 {
+        fingerprint_u32 = 1;
+    }
+    let bucket_1 = hash_value as u32 % length_u32; // lower bits
+    let bucket_2 = (bucket_1 ^ fingerprint_u32.wrapping_mul(FINGERPRINT_MULTIPLIER)) % length_u32;
+    (bucket_1, bucket_2, fingerprint_u32 as u8)
+}
+
+/// The other candidate bucket for a fingerprint sitting in `old_bucket`, out of `length_u32` buckets
+///
+/// Applying this twice gets you back where you started, which is what lets evictions move fingerprints without the original item.
+pub(crate) const fn alternate_bucket(
+    old_bucket: BucketIndex,
+    fingerprint: Fingerprint,
+    length_u32: u32,
+) -> BucketIndex {
+    (old_bucket ^ (fingerprint as u32).wrapping_mul(FINGERPRINT_MULTIPLIER)) % length_u32
+}
+
+/// A fresh `H` with `seed` written in ahead of any item (a seed of 0 writes nothing)
+pub(crate) fn seeded_hasher<H: Hasher + Default>(seed: u32) -> H {
+    let mut hasher = H::default();
+    if seed != 0 {
+        hasher.write_u32(seed);
+    }
+    hasher
+}
+
+/// Does a bucket hold this fingerprint?
+///
+/// With the `simd` feature on x86_64, a 4-slot bucket is checked with a single SSE2 compare (`bucket_contains_sse2`). Any other bucket size or target takes the scalar loop, and both give the same answer.
+#[inline(always)]
+fn bucket_contains<const B: usize>(bucket: &[Fingerprint; B], fingerprint: Fingerprint) -> bool {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if let Ok(bucket) = <&[Fingerprint; 4]>::try_from(bucket.as_slice()) {
+        return bucket_contains_sse2(bucket, fingerprint);
+    }
+    bucket_contains_scalar(bucket, fingerprint)
+}
+
+/// Check a bucket one slot at a time
+#[inline(always)]
+fn bucket_contains_scalar(bucket: &[Fingerprint], fingerprint: Fingerprint) -> bool {
+    bucket.contains(&fingerprint)
+}
+
+/// Check all four slots of a bucket at once: broadcast the fingerprint to every byte lane, compare lane by lane, and test the mask bits of the four lanes holding the bucket
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[inline(always)]
+fn bucket_contains_sse2(bucket: &[Fingerprint; 4], fingerprint: Fingerprint) -> bool {
+    use core::arch::x86_64::{_mm_cmpeq_epi8, _mm_cvtsi32_si128, _mm_movemask_epi8, _mm_set1_epi8};
+    // SAFETY: SSE2 is part of the x86_64 baseline, so these instructions are always available
+    let mask = unsafe {
+        let slots = _mm_cvtsi32_si128(i32::from_le_bytes(*bucket));
+        _mm_movemask_epi8(_mm_cmpeq_epi8(slots, _mm_set1_epi8(fingerprint as i8)))
+    };
+    // Only the low four lanes came from the bucket (the rest are zero)
+    mask & 0b1111 != 0
+}
+
+/// A `Hasher` that can start from a secret key, for `CuckooFilter::new_with_keyed_hasher`
+pub trait KeyedHasher: Hasher + Default {
+    /// A hasher in its starting state, keyed with `key`
+    fn with_key(key: u128) -> Self;
+}
+
+/// Builds a hasher from a key (`KeyedHasher::with_key`, stored so the filter doesn't need the `KeyedHasher` bound everywhere)
+type KeyedHasherFn<H> = fn(u128) -> H;
+
+/// A `Hasher` with its own way of taking a seed, for `CuckooFilter::new_with_seeded_hasher`
+///
+/// Hashers without one are seeded by writing the seed in ahead of each item (see `new_with_seed`).
+pub trait SeededHasher: Hasher + Default {
+    /// A hasher in its starting state, seeded with `seed`
+    fn with_seed(seed: u32) -> Self;
+}
+
+/// Builds a hasher from a seed (`SeededHasher::with_seed`, stored for the same reason as `KeyedHasherFn`)
+type SeededHasherFn<H> = fn(u32) -> H;
+
+/// Which filter matched in a `tiered_lookup`
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TierResult {
+    /// Neither filter has the item
+    NotFound,
+    /// The hot (primary) filter has the item
+    HotHit,
+    /// Only the cold (secondary) filter has the item, so it may be worth promoting
+    ColdHit,
+}
+
+/// What a `membership` check can actually promise
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Membership {
+    /// The item was never inserted (or has been deleted). No false negatives, so this is certain
+    DefinitelyAbsent,
+    /// The item was probably inserted, but this may be a false positive
+    ProbablyPresent,
+}
+
+/// Per-call eviction settings for `insert_with_policy`
+///
+/// `InsertPolicy::default()` matches what `insert` does on a filter built with `new` (one built with `new_with_params` uses its own kick limit instead).
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct InsertPolicy {
+    /// How many fingerprints an insert may kick along the eviction chain before giving up
+    pub max_kicks: u16,
+    /// Put the item in whichever candidate bucket has more free slots, instead of always trying the first bucket first
+    pub balanced: bool,
+    /// When the chain gives up, park the homeless fingerprint in the eviction cache (marking the filter full). If `false`, the chain is rolled back instead, so the filter is left exactly as it was and can keep taking other items.
+    pub use_cache: bool,
+}
+
+impl Default for InsertPolicy {
+    fn default() -> Self {
+        InsertPolicy {
+            max_kicks: MAX_EVICTIONS,
+            balanced: false,
+            use_cache: true,
+        }
+    }
+}
+
+/// How a successful `insert_outcome` placed its item
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InsertOutcome {
+    /// One of the item's buckets had a free slot (the fast path)
+    DirectPlacement,
+    /// Both buckets were full, so `kicks` fingerprints were moved along an eviction chain to make room
+    Evicted {
+        /// How many fingerprints were kicked out of their slots
+        kicks: u16,
+    },
+}
+
+/// Running totals of the work inserts have done, from `stats`
+///
+/// Unlike the per-insert log kept with the `diagnostics` feature, these are a few counters that don't grow with the filter, so they're always on. `reset_stats` zeroes the totals.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct FilterStats {
+    /// Inserts that went looking for a slot: every one that stored a fingerprint, plus any that gave up under a policy that doesn't use the eviction cache. Inserts refused up front (by a full filter, or `max_bucket_collisions`) aren't counted
+    pub total_inserts: usize,
+    /// How many of those inserts found both buckets full and had to start an eviction chain
+    pub total_evictions: usize,
+    /// Fingerprints kicked out of their slots, across every eviction chain
+    pub total_swaps: usize,
+    /// The longest eviction chain any insert has needed, in kicks. Reaching the kick limit means an insert gave up and used the eviction cache
+    pub max_eviction_chain: u16,
+    /// Fingerprints in the eviction cache right now (this one isn't a running total, so `reset_stats` leaves it alone)
+    pub items_in_cache: usize,
+}
+
+/// What `insert_if_new` did with its item
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InsertIfNewResult {
+    /// The item was already in the filter, so nothing changed
+    AlreadyPresent,
+    /// The item was new, and fit
+    Inserted,
+    /// The item was new but the filter was full, so this fingerprint (and one of its candidate buckets) was pushed out to make room
+    Displaced((BucketIndex, Fingerprint)),
+    /// The item was new, but its buckets already hold `max_bucket_collisions` copies of its fingerprint (all lazily deleted, say), so nothing changed
+    Refused,
 }
 
 /// A Cuckoo Filter that holds up to 8.5 billion items
 ///
+/// `B` is the number of fingerprints per bucket. The default of 4 is what the paper found space-optimal for typical false positive rates: smaller buckets fill up earlier (a load factor around 84% for 2), while bigger ones fill further (around 98% for 8) but compare more fingerprints per lookup, so the false positive rate rises with `B`. The serialized layout records `B`, and only the default size can be written for memory mapping.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilter::<Murmur3Hasher, 8>::new(128, false).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// assert_eq!(filter.num_buckets(), 16);
+/// ```
+///
 /// ### Implementation Notes
 ///
-/// - The eviction cache holds an item that we couldn't reinsert, and represents when the data structure is effectively/probabilistically full (as opposed to mechanically full)
+/// - The eviction cache holds up to 8 items that we couldn't reinsert, and once it's full represents when the data structure is effectively/probabilistically full (as opposed to mechanically full)
 /// - The `length_u32` parameter lets us wrap around (modulo) bucket indices that would be too large
 #[derive(Debug)]
-pub struct CuckooFilter<H: Hasher + Default> {
-    eviction_cache: EvictionVictim,
+pub struct CuckooFilter<H: Hasher + Default, const B: usize = BUCKET_SIZE> {
+    eviction_cache: EvictionCache,
+    #[cfg(feature = "diagnostics")]
     eviction_counts: Vec<u16>,
+    #[cfg(feature = "diagnostics")]
     swap_counts: Vec<u16>,
+    #[cfg(feature = "diagnostics")]
     data_trace: Vec<(BucketIndex, BucketIndex, Fingerprint)>,
-    data: Vec<[Fingerprint; BUCKET_SIZE]>,
+    // Running totals for `stats`. `items_in_cache` isn't kept up to date here
+    stats: FilterStats,
+    pending_deletes: Vec<(BucketIndex, BucketIndex, Fingerprint)>,
+    // Atomic so lookups can keep taking `&self`. Needs atomic read-modify-write, which some microcontrollers (thumbv6m, riscv32imc) lack
+    #[cfg(target_has_atomic = "ptr")]
+    cache_hit_lookups: AtomicUsize,
+    last_lookup: Option<LastLookup>,
+    // Only allocated once `record_sample` is first called
+    samples: Option<Box<SampleRing>>,
+    data: Vec<[Fingerprint; B]>,
+    // Stored fingerprints, including the eviction cache, so `len` doesn't have to scan
+    count: usize,
     length_u32: u32,
-    hasher: H,
+    seed: u32,
+    hash_function: Option<fn(&[u8]) -> u64>,
+    // The key and how to build a hasher from it, for keyed hashers
+    hasher_key: Option<(u128, KeyedHasherFn<H>)>,
+    // How to build a hasher that takes the seed itself, for seeded hashers (otherwise the seed is written in ahead of each item)
+    native_seed: Option<SeededHasherFn<H>>,
+    max_bucket_collisions: Option<usize>,
+    max_evictions: u16,
+    slot_rng: SplitMix64,
     phantom: PhantomData<H>,
 }
 
-impl<H: Hasher + Default> CuckooFilter<H> {
+impl<H: Hasher + Default, const B: usize> CuckooFilter<H, B> {
+    /// `ITEM_LIMIT`, for this bucket size (the cap comes from the number of buckets)
+    const MAX_ITEMS: usize = (MAX_BUCKETS.next_power_of_two() >> 1) * B;
+
     /// Try to create a new Cuckoo Filter
     ///
     /// This can fail if the desired filter would be too large. This evaluation can optionally be performed at compile time. To do that, `max_items` must be a `const` variable!
@@ -105,487 +539,4580 @@ impl<H: Hasher + Default> CuckooFilter<H> {
     pub fn new(
         max_items: usize,
         compile_time_check: bool,
-    ) -> Result<CuckooFilter<H>, CuckooFilterError> {
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        CuckooFilterBuilder::<H, B>::new()
+            .capacity(max_items)
+            .compile_time_check(compile_time_check)
+            .build()
+    }
+
+    /// Try to create a new Cuckoo Filter that kicks at most `max_evictions` fingerprints per insert, rather than the default 500
+    ///
+    /// More kicks let a heavily loaded filter fill further before it reports `OutOfSpace`, while fewer put a tighter bound on how long a single insert can take (at the cost of filling up sooner). The limit applies to every insert except `insert_with_policy`, which brings its own, and is carried over to resized copies. It isn't serialized, so a filter rebuilt with `from_bytes` is back to the default.
+    ///
+    /// ```
+    /// use cuckoo_filter::CuckooFilter;
+    /// use cuckoo_filter::Murmur3Hasher;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new_with_params(128, 2000, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::ZeroMaxEvictions`: `max_evictions` is 0
+    pub fn new_with_params(
+        max_items: usize,
+        max_evictions: u16,
+        compile_time_check: bool,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        const { assert!(B > 0, "buckets need at least one slot") };
         // Check item limit
         if compile_time_check {
             assert!(
-                max_items < ITEM_LIMIT,
+                max_items < Self::MAX_ITEMS,
                 "cuckoo filter initialized with too many items"
             );
         }
-        if max_items > ITEM_LIMIT {
+        if max_items > Self::MAX_ITEMS {
             return Err(CuckooFilterError::CapacityExceedsItemLimit);
         }
+        if max_evictions == 0 {
+            return Err(CuckooFilterError::ZeroMaxEvictions);
+        }
         // If we didn't care about modulo bias, we could use this many buckets
-        let number_of_buckets_exact: usize = max_items / BUCKET_SIZE;
+        let number_of_buckets_exact: usize = max_items / B;
         // But to avoid hash collisions, we round up
         let number_of_buckets_actual: usize = number_of_buckets_exact.next_power_of_two();
         Ok(CuckooFilter {
-            eviction_cache: EvictionVictim::new(),
+            eviction_cache: EvictionCache::new(),
+            #[cfg(feature = "diagnostics")]
             eviction_counts: Vec::new(),
+            #[cfg(feature = "diagnostics")]
             swap_counts: Vec::new(),
+            #[cfg(feature = "diagnostics")]
             data_trace: Vec::new(),
-            data: vec![[0u8; BUCKET_SIZE]; number_of_buckets_actual],
+            stats: FilterStats::default(),
+            pending_deletes: Vec::new(),
+            #[cfg(target_has_atomic = "ptr")]
+            cache_hit_lookups: AtomicUsize::new(0),
+            last_lookup: None,
+            samples: None,
+            data: vec![[0u8; B]; number_of_buckets_actual],
+            count: 0,
             length_u32: number_of_buckets_actual as u32,
-            hasher: H::default(),
+            seed: 0,
+            hash_function: None,
+            hasher_key: None,
+            native_seed: None,
+            max_bucket_collisions: None,
+            max_evictions,
+            slot_rng: SplitMix64::new(0),
             phantom: PhantomData,
         })
     }
 
-    /// Approximately how many bytes is this CF using?
-    pub fn estimate_size(&self) -> usize {
-        self.data.len() * BUCKET_SIZE
-    }
-
-    /// Is the Cuckoo Filter full of items (practically speaking)?
-    ///
-    /// Criteria is that we have something left over in the Eviction cache after trying to move it for the max number of kicks
-    pub fn is_full(&self) -> bool {
-        self.eviction_cache.used
-    }
-
-    /// Given a hash value (digest), compute the buckets and fingerprint
+    /// Try to create a new Cuckoo Filter with room for `expected_items` plus a safety margin
     ///
-    /// We modulo the bucket indices because the hash may output a value larger than the true length of the backing data array. However, because our length is a power of 2, we can use bitwise AND.
+    /// Building with headroom is the easy way to avoid running out of space early (see the crate docs). This sizes the filter for `expected_items * (1 + headroom_fraction)` items before the usual power-of-two rounding, so `0.25` means 25% extra. `headroom_fraction` should not be negative.
     ///
-    /// This is (mostly) Equation 1 in section 3.1 of the paper
-    ///
-    /// However, unlike Equation 1, we follow the reference implementation from the authors and instead compute bucket 2 by XORing with a magic constant
-    fn digest_to_buckets(&self, hash_value: u64) -> (BucketIndex, BucketIndex, Fingerprint) {
-        let upper_bits: u32 = (hash_value >> 32) as u32;
-        let fingerprint_u32: u32 = upper_bits & ((1 << 8) - 1);
-        let bucket_1 = hash_value as u32 % self.length_u32; // lower bits
-        let bucket_2 = (bucket_1 ^ fingerprint_u32.wrapping_mul(0x5bd1e995)) % self.length_u32;
-        (bucket_1, bucket_2, fingerprint_u32 as u8)
-    }
-
-    /// Calculate the buckets given a `Hash`able item
-    fn buckets_from_item<T: Hash>(&mut self, item: &T) -> (BucketIndex, BucketIndex, Fingerprint) {
-        // To preserve idempotence, we need to reset the hasher's state every time
-        self.hasher = H::default();
-        item.hash(&mut self.hasher);
-        let hash_value: u64 = self.hasher.finish();
-        self.digest_to_buckets(hash_value)
-    }
-
-    ///Compute buckets from a provided hash function without touching the internal state. This doesn't use the `Hash` trait, so it requires having access to the bytes of the item.
+    /// ```
+    /// use cuckoo_filter::CuckooFilter;
+    /// use cuckoo_filter::Murmur3Hasher;
     ///
-    /// This has a theoretical performance benefit because we don't need to reset the hasher (call `H::default()`). Your mileage may vary.
-    fn buckets_from_item_stateless(
-        &self,
-        item: &[u8],
-        hasher: fn(&[u8]) -> u64,
-    ) -> (BucketIndex, BucketIndex, Fingerprint) {
-        let hash_value: u64 = hasher(item);
-        self.digest_to_buckets(hash_value)
-    }
-
-    /// We can calculate a new bucket for an evicted item despite only having that item's fingerprint
+    /// let filter = CuckooFilter::<Murmur3Hasher>::new_with_headroom(1000, 0.25).unwrap();
+    /// assert!(filter.capacity() >= 1250);
+    /// ```
     ///
-    /// This normally would be Equation 2 in Section 3.1 of the paper, but because we use the magic number optimization that no longer applies
-    // That code would have been (old_bucket ^ (fingerprint as u32)) & (self.length_u32 - 1)
-    fn bucket_from_evicted(
-        &self,
-        old_bucket: BucketIndex,
-        fingerprint: Fingerprint,
-    ) -> BucketIndex {
-        (old_bucket ^ (fingerprint as u32).wrapping_mul(0x5bd1e995)) % self.length_u32
-    }
-
-    /// Internal method to try inserting a fingerprint into a bucket.
+    /// # Errors
     ///
-    /// True means success, false means the bucket was full
-    fn try_insert_at_bucket(
-        &mut self,
-        bucket_index: BucketIndex,
-        fingerprint: Fingerprint,
-    ) -> bool {
-        let bucket = &mut self.data[bucket_index as usize];
-        for slot in bucket.iter_mut() {
-            if *slot == 0 {
-                *slot = fingerprint;
-                return true;
-            }
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` the padded capacity is larger than `ITEM_LIMIT`
+    pub fn new_with_headroom(
+        expected_items: usize,
+        headroom_fraction: f32,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let padded = expected_items as f64 * (1.0 + headroom_fraction as f64);
+        // Round up by hand, since `f64::ceil` needs `std`
+        let mut max_items = padded as usize;
+        if (max_items as f64) < padded {
+            max_items += 1;
         }
-        false
-    }
-
-    /// Internal method to swap an existing fingerprint for a new one (the Cuckoo mechanism)
-    fn swap_at_bucket(
-        &mut self,
-        bucket_index: BucketIndex,
-        fingerprint: Fingerprint,
-        slot: usize,
-    ) -> Fingerprint {
-        let bucket = &mut self.data[bucket_index as usize];
-        let evicted_fingerprint = bucket[slot];
-        bucket[slot] = fingerprint;
-        evicted_fingerprint
+        // `new` rounds down to whole buckets before rounding up to a power of two, so pad to a whole bucket first
+        CuckooFilter::<H, B>::new(max_items.div_ceil(B) * B, false)
     }
 
-    /// Tries to place an item into the filter
+    /// Try to create a new Cuckoo Filter whose hashes are seeded, so its bucket placement differs from an unseeded filter (or one with another seed)
     ///
-    /// Internal method, public APIs wrap this
-    fn internal_insert(
-        &mut self,
-        candidate_1: u32,
-        candidate_2: u32,
-        fingerprint: u8,
-    ) -> Result<(), CuckooFilterError> {
-        // If the cache is filled then we're (effectively) out of space
-        if self.eviction_cache.used {
-            return Err(CuckooFilterError::OutOfSpace);
-        }
-        // Try inserting into either bucket
-        for &bucket_index in &[candidate_1, candidate_2] {
-            if self.try_insert_at_bucket(bucket_index, fingerprint) {
-                self.eviction_counts.push(0);
-                self.data_trace
-                    .push((candidate_1, candidate_2, fingerprint));
-                self.swap_counts.push(0);
-                return Ok(());
-            }
-        }
-
-        // If both buckets are full, begin eviction process
-        let mut target_bucket_index = if fingerprint % 2 == 0 {
-            candidate_1
-        } else {
-            candidate_2
-        };
-        let mut evicted_fingerprint: u8 = 0;
-
-        let mut swaps: u16 = 0;
-
-        for kick in 0..MAX_EVICTIONS {
-            // If kick == 0, we already tried inserting into a bucket
-            if kick > 0 && self.try_insert_at_bucket(target_bucket_index, evicted_fingerprint) {
-                self.eviction_counts.push(kick as u16);
-                self.data_trace
-                    .push((candidate_1, candidate_2, fingerprint));
-                self.swap_counts.push(swaps);
-                return Ok(());
-            }
-
-            // Randomly choose a slot to evict from and swap
-            let slot = (target_bucket_index % BUCKET_SIZE as u32) as usize;
-            evicted_fingerprint = self.swap_at_bucket(target_bucket_index, fingerprint, slot);
-            swaps += 1;
-
-            // Recalculate the next target bucket based on the evicted fingerprint
-            target_bucket_index =
-                self.bucket_from_evicted(target_bucket_index, evicted_fingerprint);
-        }
-        // If MAX_EVICTIONS is reached, store the fingerprint in the eviction cache -- this avoids "missing" the item we couldn't insert so that lookups are still correct even when it's full
-        self.eviction_cache.index = target_bucket_index;
-        self.eviction_cache.fingerprint = evicted_fingerprint;
-        self.eviction_cache.used = true;
-        self.eviction_counts.push(MAX_EVICTIONS as u16);
-        self.swap_counts.push(swaps);
-        Err(CuckooFilterError::OutOfSpace)
-    }
-
-    /// Add item to filter. Returns Err if filter is full
+    /// The seed is written into the hasher ahead of every item, and also seeds the generator that picks which slot to evict from. A seed of 0 behaves exactly like `new`. Only the `Hash`-based methods use the seed: the `_stateless` and `_hash` methods take their hash from you.
     ///
     /// ```
     /// use cuckoo_filter::CuckooFilter;
     /// use cuckoo_filter::Murmur3Hasher;
     ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    /// let ins = filter.insert(&"hello, I am some data");
-    /// assert!(ins.is_ok());
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new_with_seed(128, 7).unwrap();
+    /// assert_eq!(filter.seed(), 7);
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.contains(&"hello, I am some data"));
     /// ```
     ///
     /// # Errors
     ///
-    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
-    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
-        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
-        self.internal_insert(candidate_1, candidate_2, fingerprint)
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new_with_seed(
+        max_items: usize,
+        seed: u32,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        CuckooFilterBuilder::<H, B>::new()
+            .capacity(max_items)
+            .seed(seed)
+            .build()
     }
 
-    /// Add item to filter, but use a provided stateless hash function. Requires the item to be passed as bytes (because we're bypassing the `Hash` Trait).
+    /// Try to create a new Cuckoo Filter that remembers the hash function its `_stateless` methods are meant to use
     ///
-    /// This allows items to be inserted that don't implement `Hash`, for whatever reason.
+    /// The `_stateless` methods still take the function on every call, but in debug builds they assert that it's the one stored here, which catches the silent bug of mixing up hash functions between calls. Release builds skip the check, so it's on you to pass the same function every time. (Function pointers aren't a perfect identity: the same function can occasionally end up with two addresses across codegen units, which would trip the assertion.)
     ///
-    /// Technically, this should be "faster" because it doesn't require resetting the internal Hasher state, but depending on compiler optimizations it may not pan out. Benchmark on your system first!
+    /// The stored function isn't serialized, so a filter rebuilt with `from_bytes` doesn't have one.
     ///
     /// ```
     /// use cuckoo_filter::*;
     ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    /// let ins = filter.insert_stateless(&"hello, I am some data".as_bytes(), murmur3_x86_64bit);
-    /// assert!(ins.is_ok());
+    /// let mut filter =
+    ///     CuckooFilter::<Murmur3Hasher>::new_with_hasher_fn(128, murmur3_x86_64bit).unwrap();
+    /// let _ = filter.insert_stateless(b"hello, I am some data", murmur3_x86_64bit);
+    /// assert!(filter.contains_stateless(b"hello, I am some data", murmur3_x86_64bit));
     /// ```
     ///
     /// # Errors
     ///
-    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
-    pub fn insert_stateless(
-        &mut self,
-        item: &[u8],
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new_with_hasher_fn(
+        max_items: usize,
         hash_function: fn(&[u8]) -> u64,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new(max_items, false)?;
+        filter.hash_function = Some(hash_function);
+        Ok(filter)
+    }
+
+    /// An empty CF of a new size that hashes the same way as this one (same seed and way of seeding, hasher key and stored hash function), with the same limits
+    fn empty_like(&self, max_items: usize) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new_with_seed(max_items, self.seed)?;
+        filter.hash_function = self.hash_function;
+        filter.hasher_key = self.hasher_key;
+        filter.native_seed = self.native_seed;
+        filter.max_bucket_collisions = self.max_bucket_collisions;
+        filter.max_evictions = self.max_evictions;
+        Ok(filter)
+    }
+
+    /// Refuse inserts whose two buckets already hold `limit` copies of the item's fingerprint (`None`, the default, turns this off)
+    ///
+    /// Lookups always check just two buckets, but every identical fingerprint in them is another way for an unrelated item to match, so piling copies up (by re-inserting popular items, say) pushes the false positive rate up for those buckets. Capping the copies keeps it bounded: an insert over the limit fails with `CuckooFilterError::TooManyCollisions` and leaves the filter unchanged. The limit applies to everything placed into this filter, including fingerprints moved in by `drain_into` or resizing, and is carried over to resized copies.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// filter.set_max_bucket_collisions(Some(1));
+    /// assert!(filter.insert(&"cat").is_ok());
+    /// assert_eq!(filter.insert(&"cat"), Err(CuckooFilterError::TooManyCollisions));
+    /// ```
+    pub fn set_max_bucket_collisions(&mut self, limit: Option<usize>) {
+        self.max_bucket_collisions = limit;
+    }
+
+    /// The seed this CF hashes items with (0 if it was built unseeded)
+    ///
+    /// Two filters only agree on where an item lives if they share a seed.
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Build a CF directly from 64-bit hashes you've already computed, skipping the hashing step entirely
+    ///
+    /// This is the bulk version of `insert_hash`. The hashes must be good quality 64-bit digests (the fingerprint comes from the upper 32 bits and the bucket from the lower 32), and later queries must use `lookup_hash` with the same hashes.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let hashes = ["cat", "dog"].map(|s| murmur3_x86_64bit(s.as_bytes()));
+    /// let filter = CuckooFilter::<Murmur3Hasher>::from_hashes(128, hashes.into_iter()).unwrap();
+    /// assert!(filter.lookup_hash(hashes[0]));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::OutOfSpace`: the hashes didn't all fit
+    pub fn from_hashes(
+        capacity: usize,
+        hashes: impl Iterator<Item = u64>,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new(capacity, false)?;
+        for hash_value in hashes {
+            filter.insert_hash(hash_value)?;
+        }
+        Ok(filter)
+    }
+
+    /// Build a CF holding every one of `items`, or fail
+    ///
+    /// Unlike inserting in a loop and ignoring errors, this never hands back a filter that silently lost part of the set: if any item doesn't fit, the whole build is an error. Size `capacity` with some headroom (see `new_with_headroom`).
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let filter = CuckooFilter::<Murmur3Hasher>::try_from_iter(["cat", "dog"], 128).unwrap();
+    /// assert!(filter.contains(&"dog"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::OutOfSpace`: the items didn't all fit
+    pub fn try_from_iter<T: Hash, I: IntoIterator<Item = T>>(
+        items: I,
+        capacity: usize,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new(capacity, false)?;
+        for item in items {
+            filter.insert(&item)?;
+        }
+        Ok(filter)
+    }
+
+    /// Approximately how many bytes is this CF using?
+    pub fn estimate_size(&self) -> usize {
+        self.data.len() * B
+    }
+
+    /// How many buckets back this CF (always a power of two)
+    pub fn num_buckets(&self) -> usize {
+        self.data.len()
+    }
+
+    /// How many fingerprints fit in each bucket
+    pub const fn bucket_size(&self) -> usize {
+        B
+    }
+
+    /// How many fingerprint slots this CF has in total (`num_buckets() * bucket_size()`)
+    ///
+    /// This can be more than the `max_items` passed to `new`, since the number of buckets is rounded up to a power of two.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let filter = CuckooFilter::<Murmur3Hasher>::new(100, false).unwrap();
+    /// assert_eq!(filter.capacity(), 128);
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.data.len() * B
+    }
+
+    /// How many fingerprints the CF holds, including the ones in the eviction cache
+    ///
+    /// Duplicate inserts are counted once per copy. This is kept up to date as items come and go, so it doesn't scan the buckets.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// How many fingerprints the CF holds, counted by scanning every slot and the eviction cache
+    ///
+    /// Doesn't trust the item count behind `len`, so it's right even when that count isn't: after edits through `raw_buckets_mut` that haven't been followed by `recompute_metadata`, say. Filters rebuilt with `from_bytes` or `from_raw_parts` are recounted the same way, so their `len` agrees with this.
+    ///
+    /// It's a count of fingerprints, not of distinct items: every duplicate insert stored its own copy and is counted again, so it's only the number of items if nothing was inserted twice. Takes time proportional to `capacity()`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(filter.estimate_count(), 2);
+    /// ```
+    pub fn estimate_count(&self) -> usize {
+        self.iter_occupied().count()
+    }
+
+    /// Does the CF hold nothing at all?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fraction of slots that are occupied (including the eviction cache), between 0 and 1
+    ///
+    /// Uses the running count behind `len`, so it's cheap to poll. Filters usually report full somewhere around 0.95, so this is the number to watch when tuning headroom.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// for i in 0..64 {
+    ///     let _ = filter.insert(&i);
+    /// }
+    /// assert_eq!(filter.load_factor(), 0.5);
+    /// ```
+    pub fn load_factor(&self) -> f32 {
+        self.load_after_grow(1)
+    }
+
+    /// Predict the load factor (occupied slots / total slots) if the capacity were multiplied by `growth_factor`, without actually growing
+    ///
+    /// Use this to decide whether doubling is enough before triggering a grow. `growth_factor` must be at least 1.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// for i in 0..64 {
+    ///     let _ = filter.insert(&i);
+    /// }
+    /// assert_eq!(filter.load_after_grow(2), 0.25);
+    /// ```
+    pub fn load_after_grow(&self, growth_factor: usize) -> f32 {
+        self.len() as f32 / (self.capacity() * growth_factor) as f32
+    }
+
+    /// Shrink the CF to the smallest size that still holds its contents, but never below `min_capacity` slots
+    ///
+    /// Shrinking is lossless: because the number of buckets is a power of two, an item's candidate buckets in a smaller filter are just its current buckets modulo the new length, so every fingerprint can be folded down without needing the original item. If the contents don't fit at some size (too many collisions) we try the next size up, and if nothing smaller works the filter is left as-is.
+    ///
+    /// The floor keeps a nearly empty filter from collapsing so far that the next batch of inserts immediately runs out of space. Pending `lazy_delete`s are flushed first, since their bucket indices only make sense at the current size.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+    /// filter.shrink_to_fit(256);
+    /// assert_eq!(filter.capacity(), 256);
+    /// ```
+    pub fn shrink_to_fit(&mut self, min_capacity: usize) {
+        self.flush_deletes();
+        let mut number_of_buckets = self.len().max(min_capacity).div_ceil(B).next_power_of_two();
+        while number_of_buckets < self.data.len() {
+            if let Some(folded) = self.fold_into(number_of_buckets) {
+                self.data = folded.data;
+                self.count = folded.count;
+                self.length_u32 = folded.length_u32;
+                self.eviction_cache = folded.eviction_cache;
+                return;
+            }
+            number_of_buckets *= 2;
+        }
+    }
+
+    /// Double the CF's capacity in place, without needing the original items
+    ///
+    /// Growing is the opposite of `shrink_to_fit`, but it can't be exact: an item in bucket `b` of the old filter belongs in either `b` or `b + old_length` of the new one, depending on a hash bit we never stored. So each bucket is split into both of its successors, keeping every item findable at the cost of storing each fingerprint twice (the load factor is unchanged, and a later `delete` only removes one copy, so the other lingers as a false positive).
+    ///
+    /// The one thing that can be lost is the eviction cache: the two copies of each cached fingerprint have to be inserted for real, and if the new filter is just as crowded around them, one may not fit. They get a far longer eviction chain than a normal insert, so a filter that had just reported `OutOfSpace` usually comes out of this with room to spare (`is_full` is false again), though very small filters may not. Returns how many fingerprints were dropped. With `error_on_resize_loss` set, any loss is an error instead and the filter is left untouched.
+    ///
+    /// Since every fingerprint is stored twice, the load factor doesn't go down, so a grown filter fills up again after relatively few inserts. Rebuilding from the original items (`resize_from_items`) is better whenever they're still around.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(filter.grow(true), Ok(0));
+    /// assert_eq!(filter.capacity(), 256);
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit`: doubling would go past `ITEM_LIMIT`
+    /// - `CuckooFilterError::ResizeLoss`: `error_on_resize_loss` is set and a fingerprint would have been dropped
+    pub fn grow(&mut self, error_on_resize_loss: bool) -> Result<usize, CuckooFilterError> {
+        let mut grown = self.empty_like(self.capacity() * 2)?;
+        self.flush_deletes();
+        let old_length = self.data.len();
+        grown.data[..old_length].copy_from_slice(&self.data);
+        grown.data[old_length..].copy_from_slice(&self.data);
+        grown.count = (self.len() - self.eviction_cache.len()) * 2;
+        // The grown filter is just as loaded as this one, so give the victims' copies a much longer eviction chain than a normal insert gets
+        let victim_policy = InsertPolicy {
+            max_kicks: u16::MAX,
+            ..grown.default_policy()
+        };
+        for &(cache_index, fingerprint) in self.eviction_cache.victims() {
+            for bucket_index in [cache_index, cache_index + self.length_u32] {
+                // A failure either parks the fingerprint in the new cache or drops it, which the count below picks up
+                let alternate_index = grown.bucket_from_evicted(bucket_index, fingerprint);
+                let _ = grown.internal_insert_with_policy(
+                    bucket_index,
+                    alternate_index,
+                    fingerprint,
+                    victim_policy,
+                );
+            }
+        }
+        let dropped = self.len() * 2 - grown.len();
+        if dropped > 0 && error_on_resize_loss {
+            return Err(CuckooFilterError::ResizeLoss);
+        }
+        self.data = grown.data;
+        self.count = grown.count;
+        self.length_u32 = grown.length_u32;
+        self.eviction_cache = grown.eviction_cache;
+        self.last_lookup = None;
+        Ok(dropped)
+    }
+
+    /// Build a smaller copy of the CF with `new_capacity` slots (rounded as in `new`), without needing the original items
+    ///
+    /// Meant for shipping a compact, read-only snapshot of a big filter: the copy uses less memory at the cost of a higher false positive rate. Fingerprints are folded down the same way as in `shrink_to_fit`, but where that gives up if anything doesn't fit, this is lossy: fingerprints that don't fit (once the copy is full) are dropped, so items that were in this filter may be missing from the copy. Keep the load well under the new capacity to avoid that. A `new_capacity` at or above the current capacity gives a same-sized copy. Lazily deleted items are left out of the copy, and this filter's pending deletes are left as they are.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let copy = filter.clone_shrunk(256).unwrap();
+    /// assert_eq!(copy.capacity(), 256);
+    /// assert!(copy.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn clone_shrunk(
+        &self,
+        new_capacity: usize,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut shrunk = self.empty_like(new_capacity)?;
+        if shrunk.length_u32 > self.length_u32 {
+            shrunk = self.empty_like(self.capacity())?;
+        }
+        // The pending deletes' bucket indices are for this size, so each one has to be matched against a stored copy here, before folding
+        let mut tombstones = self.pending_deletes.clone();
+        for (bucket_index, fingerprint) in self.stored_fingerprints() {
+            if let Some(position) =
+                tombstones
+                    .iter()
+                    .position(|&(pending_1, pending_2, pending_fingerprint)| {
+                        pending_fingerprint == fingerprint
+                            && (pending_1 == bucket_index || pending_2 == bucket_index)
+                    })
+            {
+                tombstones.swap_remove(position);
+                continue;
+            }
+            // Once the copy is full, everything else is dropped
+            let _ = shrunk.insert_fingerprint(bucket_index % shrunk.length_u32, fingerprint);
+        }
+        Ok(shrunk)
+    }
+
+    /// Try to place every fingerprint (including the eviction cache) into a fresh, smaller filter with `number_of_buckets` buckets
+    ///
+    /// Returns `None` if anything fails to fit
+    fn fold_into(&self, number_of_buckets: usize) -> Option<CuckooFilter<H, B>> {
+        let mut folded = self.empty_like(number_of_buckets * B).ok()?;
+        for (bucket_index, fingerprint) in self.stored_fingerprints() {
+            folded
+                .insert_fingerprint(bucket_index % folded.length_u32, fingerprint)
+                .ok()?;
+        }
+        Some(folded)
+    }
+
+    /// Rebuild the CF at a new size from the caller's authoritative list of items, replacing the old contents
+    ///
+    /// Unlike `shrink_to_fit` (which only has fingerprints to work with), this reinserts the original items, so it can grow as well as shrink and every item is guaranteed to be placed correctly. The catch is that you need the source data. The filter is only replaced if every item fits; on error, it's left untouched.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let items = ["cat", "dog", "bird"];
+    /// for item in items.iter() {
+    ///     let _ = filter.insert(item);
+    /// }
+    /// assert!(filter.resize_from_items(1024, items.iter()).is_ok());
+    /// assert_eq!(filter.capacity(), 1024);
+    /// assert!(filter.contains(&"dog"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::OutOfSpace`: the items didn't all fit at the new size
+    pub fn resize_from_items<T: Hash>(
+        &mut self,
+        new_capacity: usize,
+        items: impl Iterator<Item = T>,
     ) -> Result<(), CuckooFilterError> {
-        let (candidate_1, candidate_2, fingerprint) =
-            self.buckets_from_item_stateless(item, hash_function);
-        self.internal_insert(candidate_1, candidate_2, fingerprint)
+        let mut resized = self.empty_like(new_capacity)?;
+        for item in items {
+            resized.insert(&item)?;
+        }
+        *self = resized;
+        Ok(())
     }
 
-    /// Identifies if an item is in the filter
+    /// Move everything in this CF into `dest`, leaving this CF empty
     ///
-    /// This is an internal method that public APIs wrap around
-    fn internal_lookup(&self, candidate_1: u32, candidate_2: u32, fingerprint: u8) -> bool {
-        // Check cache
-        if self.eviction_cache.used
-            && fingerprint == self.eviction_cache.fingerprint
-            && (self.eviction_cache.index == candidate_1
-                || self.eviction_cache.index == candidate_2)
-        {
-            return true;
+    /// Only fingerprints are stored and bucket indices depend on the filter's size, so both filters must have the same number of buckets. Fingerprints (and the eviction cache) are placed directly into `dest` without rehashing anything.
+    ///
+    /// If `dest` runs out of space part way through, we stop: whatever was already moved lives in `dest` and the rest stays here, so nothing is lost or duplicated.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut source = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let mut dest = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = source.insert(&"hello, I am some data");
+    /// assert!(source.drain_into(&mut dest).is_ok());
+    /// assert!(!source.contains(&"hello, I am some data"));
+    /// assert!(dest.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::LengthMismatch`: the filters have different numbers of buckets
+    /// - `CuckooFilterError::OutOfSpace`: `dest` filled up before everything was moved
+    pub fn drain_into(&mut self, dest: &mut CuckooFilter<H, B>) -> Result<(), CuckooFilterError> {
+        if self.length_u32 != dest.length_u32 {
+            return Err(CuckooFilterError::LengthMismatch);
         }
-        // Check buckets
-        for &bucket_index in &[candidate_1, candidate_2] {
-            for entry in self.data[bucket_index as usize] {
-                if entry == fingerprint {
-                    return true;
+        if dest.is_full() {
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        // From here on, a failed insert means `dest` absorbed the fingerprint into its eviction cache, so it still counts as moved
+        while let Some((bucket_index, fingerprint)) = self.eviction_cache.pop() {
+            let result = dest.insert_fingerprint(bucket_index, fingerprint);
+            self.count -= 1;
+            result?;
+        }
+        for bucket_index in 0..self.length_u32 {
+            for slot in 0..B {
+                let fingerprint = self.data[bucket_index as usize][slot];
+                if fingerprint == 0 {
+                    continue;
                 }
+                let result = dest.insert_fingerprint(bucket_index, fingerprint);
+                self.data[bucket_index as usize][slot] = 0;
+                self.count -= 1;
+                result?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add every fingerprint stored in `other` (including its eviction cache) to this filter, leaving `other` untouched
+    ///
+    /// For combining filters built in parallel, such as one per worker. Both filters must have the same number of buckets, and should share a hasher and seed, since fingerprints are re-placed using the bucket they sat in within `other`. Items present in both filters end up stored twice, just as if they had been inserted twice.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut ours = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let mut theirs = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = ours.insert(&"hello, I am some data");
+    /// let _ = theirs.insert(&"hello, I am some other data");
+    /// assert!(ours.union(&theirs).is_ok());
+    /// assert!(ours.contains(&"hello, I am some data"));
+    /// assert!(ours.contains(&"hello, I am some other data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::LengthMismatch`: the filters have different numbers of buckets
+    /// - `CuckooFilterError::OutOfSpace`: this filter filled up before everything was merged (whatever was merged by then stays)
+    pub fn union(&mut self, other: &CuckooFilter<H, B>) -> Result<(), CuckooFilterError> {
+        if self.length_u32 != other.length_u32 {
+            return Err(CuckooFilterError::LengthMismatch);
+        }
+        if self.is_full() {
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        for &(bucket_index, fingerprint) in other.eviction_cache.victims() {
+            self.insert_fingerprint(bucket_index, fingerprint)?;
+        }
+        for (bucket_index, bucket) in other.data.iter().enumerate() {
+            for &fingerprint in bucket.iter().filter(|&&fingerprint| fingerprint != 0) {
+                self.insert_fingerprint(bucket_index as BucketIndex, fingerprint)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// CRC-32 checksums of each run of `block_size` consecutive buckets (the last block may be shorter)
+    ///
+    /// For rsync-style synchronization between two copies of a filter: compare checksum vectors, and only send the blocks whose checksums differ. Both copies need the same number of buckets for the blocks to line up. The eviction cache isn't covered, so compare `is_full` (or send it along) separately.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut ours = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+    /// let theirs = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+    /// let _ = ours.insert(&"hello, I am some data");
+    /// let changed = ours
+    ///     .block_checksums(16)
+    ///     .iter()
+    ///     .zip(theirs.block_checksums(16))
+    ///     .filter(|(a, b)| **a != *b)
+    ///     .count();
+    /// assert_eq!(changed, 1);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `block_size` is 0
+    pub fn block_checksums(&self, block_size: usize) -> Vec<u32> {
+        self.data
+            .chunks(block_size)
+            .map(|block| {
+                block
+                    .iter()
+                    .fold(0, |crc, bucket| crc32_update(crc, bucket))
+            })
+            .collect()
+    }
+
+    /// Estimate how many items this CF has that `other` doesn't (for set reconciliation between peers)
+    ///
+    /// For every fingerprint stored here, we check whether `other` has the same fingerprint in either of that item's candidate buckets, and count the ones it doesn't. Both filters must have the same number of buckets (and use the same `Hasher`) for the buckets to correspond.
+    ///
+    /// This is biased: an item missing from `other` is missed by the count whenever `other` happens to hold a matching fingerprint in the same buckets (a false positive), so the estimate tends low as `other` fills up. Duplicate inserts into this CF are counted once per copy, pushing the other way.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut ours = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let theirs = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = ours.insert(&"hello, I am some data");
+    /// assert_eq!(ours.estimate_difference(&theirs), Ok(1));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::LengthMismatch`: the filters have different numbers of buckets
+    pub fn estimate_difference(
+        &self,
+        other: &CuckooFilter<H, B>,
+    ) -> Result<usize, CuckooFilterError> {
+        if self.length_u32 != other.length_u32 {
+            return Err(CuckooFilterError::LengthMismatch);
+        }
+        let missing = self
+            .stored_fingerprints()
+            .filter(|&(bucket_index, fingerprint)| {
+                let alternate_index = self.bucket_from_evicted(bucket_index, fingerprint);
+                !other.internal_lookup(bucket_index, alternate_index, fingerprint)
+            })
+            .count();
+        Ok(missing)
+    }
+
+    /// Estimate how many items this CF and `other` have in common (for deduplicating across two sources)
+    ///
+    /// The counterpart to `estimate_difference`: for every fingerprint stored here, we check whether `other` has the same fingerprint in either of that item's candidate buckets, and count the ones it does. Both filters must have the same number of buckets (and use the same `Hasher`) for the buckets to correspond.
+    ///
+    /// An item only this CF has still matches whenever `other` happens to hold the same fingerprint in its buckets, which happens at `other`'s false positive rate `p` (see `false_positive_rate`). So of `m` matches out of `n` stored fingerprints, about `p * (n - shared)` are accidental, and the estimate is `(m - p * n) / (1 - p)`, clamped to what both filters could hold. This is rough: the accidental matches are random, so expect an error of a few times `sqrt(p * len())` either way (a handful of items at moderate loads, tens for big, full filters), on top of any error in the load-based `p`. Duplicate inserts count once per copy stored here.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut ours = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let mut theirs = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = ours.insert(&"hello, I am some data");
+    /// assert_eq!(ours.estimate_intersection(&theirs), Ok(0));
+    /// let _ = theirs.insert(&"hello, I am some data");
+    /// assert_eq!(ours.estimate_intersection(&theirs), Ok(1));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::LengthMismatch`: the filters have different numbers of buckets
+    pub fn estimate_intersection(
+        &self,
+        other: &CuckooFilter<H, B>,
+    ) -> Result<usize, CuckooFilterError> {
+        if self.length_u32 != other.length_u32 {
+            return Err(CuckooFilterError::LengthMismatch);
+        }
+        // Count from the same scan as the matches, since `len()` goes stale after `raw_buckets_mut`
+        let mut stored = 0;
+        let mut matches = 0;
+        for (bucket_index, fingerprint) in self.stored_fingerprints() {
+            let alternate_index = self.bucket_from_evicted(bucket_index, fingerprint);
+            stored += 1;
+            if other.internal_lookup(bucket_index, alternate_index, fingerprint) {
+                matches += 1;
+            }
+        }
+        let accidental_rate = other.false_positive_rate();
+        let estimate = (matches as f64 - accidental_rate * stored as f64) / (1.0 - accidental_rate);
+        // Negative estimates saturate to 0
+        let rounded = (estimate + 0.5) as usize;
+        Ok(rounded.min(matches).min(other.len()))
+    }
+
+    /// Count how many times each fingerprint value appears across the filter (including the eviction cache)
+    ///
+    /// With a good hasher, the counts should be roughly flat across 1-255. (Index 0 is always 0 because it marks an empty slot, and 1 is about twice as common as the rest because zero fingerprints are bumped to 1.) A spiky histogram means the fingerprint bits of your hash aren't uniform, which drives up the false positive rate.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(filter.fingerprint_histogram().iter().sum::<u32>(), 1);
+    /// ```
+    pub fn fingerprint_histogram(&self) -> [u32; 256] {
+        let mut histogram = [0u32; 256];
+        for (_, fingerprint) in self.stored_fingerprints() {
+            histogram[fingerprint as usize] += 1;
+        }
+        histogram
+    }
+
+    /// Measure how much information the fingerprint byte carries when `H` hashes `samples`, in bits (Shannon entropy)
+    ///
+    /// The fingerprint comes from a fixed byte of the 64-bit digest (bits 32-39), so a hasher that spreads buckets well can still make poor fingerprints if its entropy sits in the low bits. That failure mode doesn't show up in collision-rate tests. A good hasher scores close to the maximum of log2(255) ≈ 7.99 bits given a few thousand samples; anything under about 7 bits will noticeably raise the false positive rate.
+    ///
+    /// Requires the `std` feature (for `f64::log2`).
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let samples: Vec<u64> = (0..10_000).collect();
+    /// assert!(CuckooFilter::<Murmur3Hasher>::fingerprint_entropy(&samples) > 7.5);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn fingerprint_entropy<T: Hash>(samples: &[T]) -> f64 {
+        let mut histogram = [0u32; 256];
+        for item in samples {
+            let mut hasher = H::default();
+            item.hash(&mut hasher);
+            let (_, _, fingerprint) = buckets_for(hasher.finish(), 1);
+            histogram[fingerprint as usize] += 1;
+        }
+        let total = samples.len() as f64;
+        histogram
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let probability = count as f64 / total;
+                -probability * probability.log2()
+            })
+            .sum()
+    }
+
+    /// Direct mutable access to the buckets, for tooling that repairs or migrates filters
+    ///
+    /// Write `0` to empty a slot. Nothing is checked while you edit, so call `recompute_metadata` afterwards: until then, `len()` (which is a running count, not a scan of the buckets) is stale, and the eviction cache and any pending `lazy_delete`s may disagree with the buckets.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// filter.raw_buckets_mut()[0] = [7, 0, 0, 0];
+    /// filter.recompute_metadata();
+    /// assert_eq!(filter.len(), 1);
+    /// ```
+    pub fn raw_buckets_mut(&mut self) -> &mut [[Fingerprint; B]] {
+        &mut self.data
+    }
+
+    /// Bring the filter's bookkeeping back in line with its buckets after edits through `raw_buckets_mut`
+    ///
+    /// The item count behind `len` is recounted from the buckets. Cached victims that are no longer valid (pointing outside the filter, or holding the empty fingerprint) are dropped, and valid ones are moved into their buckets if the edits made room. Pending deletes whose fingerprint is no longer in their buckets are discarded.
+    pub fn recompute_metadata(&mut self) {
+        let length_u32 = self.length_u32;
+        self.eviction_cache
+            .retain(|bucket_index, fingerprint| bucket_index < length_u32 && fingerprint != 0);
+        self.recount();
+        self.reclaim_eviction_cache();
+        let mut pending = core::mem::take(&mut self.pending_deletes);
+        pending.retain(|&(candidate_1, candidate_2, fingerprint)| {
+            self.matching_copies(candidate_1, candidate_2, fingerprint) > 0
+        });
+        self.pending_deletes = pending;
+    }
+
+    /// Pack the occupancy of every slot into a bitmap (1 = occupied), for feeding external analysis tools
+    ///
+    /// Slot `i` is slot `i % bucket_size()` of bucket `i / bucket_size()`, and lives in bit `i % 8` (least significant first) of byte `i / 8`. That's `capacity() / 8` bytes rather than one per slot. The eviction cache isn't a slot, so it isn't included.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let bitmap = filter.fingerprint_occupancy_bitmap();
+    /// assert_eq!(bitmap.len(), 16);
+    /// assert_eq!(bitmap.iter().map(|byte| byte.count_ones()).sum::<u32>(), 1);
+    /// ```
+    pub fn fingerprint_occupancy_bitmap(&self) -> Vec<u8> {
+        let mut bitmap = vec![0u8; self.capacity().div_ceil(8)];
+        for (slot_index, &fingerprint) in self.data.iter().flatten().enumerate() {
+            if fingerprint != 0 {
+                bitmap[slot_index / 8] |= 1 << (slot_index % 8);
+            }
+        }
+        bitmap
+    }
+
+    /// Downsample the filter's occupancy into `cells` contiguous regions of buckets, giving each region's load (0 to 1)
+    ///
+    /// For rendering a heatmap of a huge filter: hot regions point at spatial clustering, usually from correlated keys or a weak hasher. Regions differ in size by at most one bucket when `cells` doesn't divide the bucket count, and `cells` is capped at the number of buckets. The eviction cache isn't included.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+    /// for i in 0..512 {
+    ///     let _ = filter.insert(&i);
+    /// }
+    /// let heatmap = filter.occupancy_heatmap(8);
+    /// assert_eq!(heatmap.len(), 8);
+    /// assert!(heatmap.iter().all(|&load| load > 0.25 && load < 0.75));
+    /// ```
+    pub fn occupancy_heatmap(&self, cells: usize) -> Vec<f32> {
+        let number_of_buckets = self.data.len();
+        let cells = cells.min(number_of_buckets);
+        (0..cells)
+            .map(|cell| {
+                let region = &self.data
+                    [cell * number_of_buckets / cells..(cell + 1) * number_of_buckets / cells];
+                let occupied = region.iter().flatten().filter(|&&fp| fp != 0).count();
+                occupied as f32 / (region.len() * B) as f32
+            })
+            .collect()
+    }
+
+    /// Count the buckets by how many of their slots are occupied: entry `k` is the number of buckets holding exactly `k` fingerprints
+    ///
+    /// There are `B + 1` entries (0 through `B` occupied slots), and they add up to `num_buckets()`. A filter close to saturation has most of its buckets in the last entry, which is what makes eviction chains long: an insert only needs to kick when both of its buckets are full. The eviction cache isn't a bucket, so it isn't included. One pass over the buckets.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(filter.occupancy_histogram(), vec![31, 1, 0, 0, 0]);
+    /// ```
+    pub fn occupancy_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; B + 1];
+        for bucket in self.data.iter() {
+            histogram[bucket.iter().filter(|&&fp| fp != 0).count()] += 1;
+        }
+        histogram
+    }
+
+    /// Walk every stored fingerprint as `(bucket index, slot, fingerprint)`, bucket by bucket, then the eviction cache
+    ///
+    /// For debugging and for tooling that moves contents around (the way `grow` and `union` do). Fingerprints in the eviction cache come last, with the bucket index the cache keeps for them (one of their two candidates) and the slot `EVICTION_CACHE_SLOT` (`B` with a non-default bucket size), as in `delete_locating`. The iterator borrows the filter and doesn't allocate.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let (bucket_index, slot) = filter.insert_locating(&"hello, I am some data").unwrap();
+    /// let occupied: Vec<_> = filter.iter_occupied().collect();
+    /// assert_eq!(occupied.len(), 1);
+    /// assert_eq!((occupied[0].0, occupied[0].1), (bucket_index, slot));
+    /// ```
+    pub fn iter_occupied(&self) -> impl Iterator<Item = (BucketIndex, usize, Fingerprint)> + '_ {
+        self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(bucket_index, bucket)| {
+                bucket
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, fp)| **fp != 0)
+                    .map(move |(slot, fp)| (bucket_index as BucketIndex, slot, *fp))
+            })
+            .chain(
+                self.eviction_cache
+                    .victims()
+                    .iter()
+                    .map(|&(bucket_index, fingerprint)| (bucket_index, B, fingerprint)),
+            )
+    }
+
+    /// Every fingerprint in the CF alongside the bucket it sits in, including the eviction cache (whose indices are one of their candidate buckets)
+    fn stored_fingerprints(&self) -> impl Iterator<Item = (BucketIndex, Fingerprint)> + '_ {
+        self.iter_occupied()
+            .map(|(bucket_index, _, fingerprint)| (bucket_index, fingerprint))
+    }
+
+    /// Estimate the chance that a lookup of an item that was never inserted returns `true`, given how full the filter is right now
+    ///
+    /// A lookup compares the item's fingerprint against the `2 * B` slots of its two buckets. Assuming a good hash, each slot is occupied with probability `load_factor()`, and an occupied slot holds any one of the 255 non-zero fingerprints equally often (0 marks an empty slot, so `2^8 - 1` rather than the paper's `2^f`), which gives `1 - (1 - load_factor() / 255)^(2 * B)`. An empty filter has no false positives, and a full one with 4 slots per bucket is at about 3%. Duplicate inserts count towards the load like any other fingerprint. Use `measure_fp_rate` to check the estimate against real data.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert_eq!(filter.false_positive_rate(), 0.0);
+    /// for i in 0..64 {
+    ///     let _ = filter.insert(&i);
+    /// }
+    /// assert!(filter.false_positive_rate() > 0.01 && filter.false_positive_rate() < 0.02);
+    /// ```
+    pub fn false_positive_rate(&self) -> f64 {
+        let slot_miss = 1.0 - self.load_factor() as f64 / Fingerprint::MAX as f64;
+        // An integer power by hand, since `f64::powi` needs `std`
+        let all_miss = (0..2 * B).fold(1.0, |product, _| product * slot_miss);
+        1.0 - all_miss
+    }
+
+    /// Measure the false positive rate on your own data: the fraction of `negatives` (items you know were never inserted) that the filter claims to have seen
+    ///
+    /// This is an empirical check to compare with the analytic estimate. It only reads the filter. An empty `negatives` gives a rate of 0.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert_eq!(filter.measure_fp_rate(&["never", "inserted"]), 0.0);
+    /// ```
+    pub fn measure_fp_rate<T: Hash>(&self, negatives: &[T]) -> f64 {
+        if negatives.is_empty() {
+            return 0.0;
+        }
+        let false_positives = negatives
+            .iter()
+            .filter(|item| {
+                let (candidate_1, candidate_2, fingerprint) =
+                    self.digest_to_buckets(self.digest_of(*item));
+                self.internal_lookup(candidate_1, candidate_2, fingerprint)
+            })
+            .count();
+        false_positives as f64 / negatives.len() as f64
+    }
+
+    /// Clear the diagnostics (the totals in `stats`, `cache_hit_count` where available, and the eviction counts, swap counts and insert trace with the `diagnostics` feature) without touching the filter's contents or eviction cache
+    ///
+    /// Handy for windowed metrics: snapshot the stats, reset them, and the next snapshot only reflects operations since the reset.
+    pub fn reset_stats(&mut self) {
+        self.stats = FilterStats::default();
+        #[cfg(feature = "diagnostics")]
+        {
+            self.eviction_counts.clear();
+            self.swap_counts.clear();
+            self.data_trace.clear();
+        }
+        #[cfg(target_has_atomic = "ptr")]
+        {
+            *self.cache_hit_lookups.get_mut() = 0;
+        }
+    }
+
+    /// Log an insert attempt: add it to the totals behind `stats`, and to the per-insert diagnostics (how many kicks and swaps it took, and its placement if it succeeded)
+    ///
+    /// Without the `diagnostics` feature only the totals are kept, so production builds don't grow a log with every insert.
+    #[cfg_attr(not(feature = "diagnostics"), allow(unused_variables))]
+    fn record_insert(
+        &mut self,
+        kicks: u16,
+        swaps: u16,
+        placed: Option<(BucketIndex, BucketIndex, Fingerprint)>,
+    ) {
+        self.stats.total_inserts += 1;
+        if swaps > 0 {
+            self.stats.total_evictions += 1;
+            self.stats.total_swaps += swaps as usize;
+            self.stats.max_eviction_chain = self.stats.max_eviction_chain.max(swaps);
+        }
+        #[cfg(feature = "diagnostics")]
+        {
+            self.eviction_counts.push(kicks);
+            self.swap_counts.push(swaps);
+            self.data_trace.extend(placed);
+        }
+    }
+
+    /// How much work inserts have done since the filter was built (or since `reset_stats`), and how full the eviction cache is
+    ///
+    /// Cheap to call: the totals are kept as inserts happen. A `max_eviction_chain` creeping up towards the kick limit, or a growing `items_in_cache`, means the filter is close to full.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let stats = filter.stats();
+    /// assert_eq!(stats.total_inserts, 1);
+    /// assert_eq!(stats.total_evictions, 0);
+    /// ```
+    pub fn stats(&self) -> FilterStats {
+        FilterStats {
+            items_in_cache: self.eviction_cache.len(),
+            ..self.stats
+        }
+    }
+
+    /// How many lookups were answered by the eviction cache
+    ///
+    /// The cache only holds a few fingerprints, so if this keeps rising, the filter is saturated and you're leaning on it: time to grow. Every lookup counts, including the ones done internally (by `insert_dedup`, for example).
+    ///
+    /// Only available on targets with atomic read-modify-write (`target_has_atomic = "ptr"`), so not on microcontrollers such as thumbv6m or riscv32imc.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// assert_eq!(filter.cache_hit_count(), 0);
+    /// ```
+    #[cfg(target_has_atomic = "ptr")]
+    pub fn cache_hit_count(&self) -> usize {
+        self.cache_hit_lookups.load(Ordering::Relaxed)
+    }
+
+    /// Record the current `(len(), load_factor())` in a small ring buffer, for watching trends without a metrics system
+    ///
+    /// Call this periodically (say, once a minute); the last 64 samples are kept. Comparing them shows whether the filter is filling up faster than expected. The buffer is only allocated on the first call, and nothing else (including `reset_stats`) clears it.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// filter.record_sample();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// filter.record_sample();
+    /// assert_eq!(filter.recent_samples()[1].0, 1);
+    /// ```
+    pub fn record_sample(&mut self) {
+        let sample = (self.len(), self.load_factor());
+        self.samples
+            .get_or_insert_with(|| Box::new(SampleRing::new()))
+            .push(sample);
+    }
+
+    /// Samples taken by `record_sample`, oldest first (at most the last 64)
+    pub fn recent_samples(&self) -> &[(usize, f32)] {
+        self.samples.as_ref().map_or(&[], |ring| ring.as_slice())
+    }
+
+    /// Empty the CF, keeping its size and allocation, so it can be refilled
+    ///
+    /// Cheaper than building a new filter for workloads that start over every cycle. The seed is kept; everything else (contents, eviction cache, pending deletes, stats, and the eviction slot generator) is reset.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// filter.clear();
+    /// assert!(filter.is_empty());
+    /// assert!(!filter.contains(&"hello, I am some data"));
+    /// ```
+    pub fn clear(&mut self) {
+        self.data.fill([0; B]);
+        self.count = 0;
+        self.eviction_cache.reset();
+        self.pending_deletes.clear();
+        self.slot_rng = SplitMix64::new(self.seed as u64);
+        self.reset_stats();
+    }
+
+    /// Empty the CF and change its size for the next batch of items, in one call
+    ///
+    /// The new size is rounded the same way as `new`. The seed is kept; everything else (contents, eviction cache, pending deletes, stats, and the eviction slot generator) is reset. If the number of buckets doesn't change, the existing allocation is zeroed and reused.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.clear_and_resize(1024).is_ok());
+    /// assert_eq!(filter.capacity(), 1024);
+    /// assert!(!filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT` (the filter is left untouched)
+    pub fn clear_and_resize(&mut self, new_max_items: usize) -> Result<(), CuckooFilterError> {
+        if new_max_items > Self::MAX_ITEMS {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let number_of_buckets: usize = (new_max_items / B).next_power_of_two();
+        if number_of_buckets != self.data.len() {
+            self.data = vec![[0; B]; number_of_buckets];
+            self.length_u32 = number_of_buckets as u32;
+        }
+        self.clear();
+        Ok(())
+    }
+
+    /// Is the Cuckoo Filter full of items (practically speaking)?
+    ///
+    /// Criteria is that every slot of the eviction cache holds a fingerprint left over after trying to move it for the max number of kicks
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_full()
+    }
+
+    /// The newest fingerprint in the eviction cache and the bucket index kept with it (one of its two candidates), if the cache holds any
+    ///
+    /// For diagnostics: once the filter reports full, this is the fingerprint left homeless by the insert that filled it, which can help when correlating with your own data. The other cached fingerprints come last out of `iter_occupied`. A fingerprint is 8 bits of the item's hash, so it can't be turned back into the item: all it tells you is that the item was one of the many that share these buckets and fingerprint.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert_eq!(filter.eviction_victim(), None);
+    /// let mut i = 0;
+    /// while filter.insert(&i).is_ok() {
+    ///     i += 1;
+    /// }
+    /// assert!(filter.eviction_victim().is_some());
+    /// ```
+    pub fn eviction_victim(&self) -> Option<(BucketIndex, Fingerprint)> {
+        self.eviction_cache.victims().last().copied()
+    }
+
+    /// Given a hash value (digest), compute the buckets and fingerprint for this filter's size
+    ///
+    /// See `buckets_for` for the math
+    fn digest_to_buckets(&self, hash_value: u64) -> (BucketIndex, BucketIndex, Fingerprint) {
+        let triple = buckets_for(hash_value, self.length_u32);
+        debug_assert_ne!(triple.2, 0, "an item produced the empty-slot fingerprint");
+        triple
+    }
+
+    /// Set the item count from a full scan, for when the buckets were filled in wholesale
+    fn recount(&mut self) {
+        self.count = self.estimate_count();
+    }
+
+    /// Check the zero-fingerprint invariant: 0 means "empty slot", so nothing stored may use it
+    ///
+    /// Slots are empty by definition when they hold 0, so the only place a stored zero can hide is the eviction cache (which tracks how many victims it holds separately). That can't happen through the API, but a corrupt serialized filter can claim it.
+    fn verify_no_zero_fingerprints(&self) -> bool {
+        self.eviction_cache
+            .victims()
+            .iter()
+            .all(|&(_, fingerprint)| fingerprint != 0)
+    }
+
+    /// A hasher in its starting state, keyed if the filter has a hasher key, and with the seed (if any) already written in
+    fn fresh_hasher(&self) -> H {
+        match self.hasher_key {
+            Some((key, keyed_hasher)) => {
+                let mut hasher = keyed_hasher(key);
+                if self.seed != 0 {
+                    hasher.write_u32(self.seed);
+                }
+                hasher
+            }
+            None => match self.native_seed {
+                Some(with_seed) => with_seed(self.seed),
+                None => seeded_hasher(self.seed),
+            },
+        }
+    }
+
+    /// Do both filters hash items the same way?
+    fn same_hashing(&self, other: &Self) -> bool {
+        self.seed == other.seed
+            && self.native_seed.is_some() == other.native_seed.is_some()
+            && self.hasher_key.map(|(key, _)| key) == other.hasher_key.map(|(key, _)| key)
+    }
+
+    /// Hash an item with a fresh hasher, leaving the filter's own state alone
+    fn digest_of<T: Hash>(&self, item: &T) -> u64 {
+        let mut hasher = self.fresh_hasher();
+        item.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Calculate the buckets given a `Hash`able item
+    ///
+    /// Each call hashes with its own fresh hasher (see `digest_of`), so this only needs `&self` and queries can run from several threads at once.
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex, Fingerprint) {
+        self.digest_to_buckets(self.digest_of(item))
+    }
+
+    ///Compute buckets from a provided hash function without touching the internal state. This doesn't use the `Hash` trait, so it requires having access to the bytes of the item.
+    ///
+    /// This has a theoretical performance benefit because we don't need to reset the hasher (call `H::default()`). Your mileage may vary.
+    ///
+    /// In debug builds, panics if the filter was built with `new_with_hasher_fn` and `hasher` isn't that function.
+    fn buckets_from_item_stateless(
+        &self,
+        item: &[u8],
+        hasher: fn(&[u8]) -> u64,
+    ) -> (BucketIndex, BucketIndex, Fingerprint) {
+        if let Some(stored) = self.hash_function {
+            debug_assert!(
+                core::ptr::fn_addr_eq(stored, hasher),
+                "stateless call used a different hash function than the one this cuckoo filter was built with"
+            );
+        }
+        let hash_value: u64 = hasher(item);
+        self.digest_to_buckets(hash_value)
+    }
+
+    /// We can calculate a new bucket for an evicted item despite only having that item's fingerprint
+    ///
+    /// This normally would be Equation 2 in Section 3.1 of the paper, but because we use the magic number optimization that no longer applies
+    // That code would have been (old_bucket ^ (fingerprint as u32)) & (self.length_u32 - 1)
+    fn bucket_from_evicted(
+        &self,
+        old_bucket: BucketIndex,
+        fingerprint: Fingerprint,
+    ) -> BucketIndex {
+        alternate_bucket(old_bucket, fingerprint, self.length_u32)
+    }
+
+    /// Internal method to try inserting a fingerprint into a bucket.
+    ///
+    /// Returns the slot the fingerprint went into, or `None` if the bucket was full
+    fn try_insert_at_bucket(
+        &mut self,
+        bucket_index: BucketIndex,
+        fingerprint: Fingerprint,
+    ) -> Option<usize> {
+        let bucket = &mut self.data[bucket_index as usize];
+        for (slot_index, slot) in bucket.iter_mut().enumerate() {
+            if *slot == 0 {
+                *slot = fingerprint;
+                self.count += 1;
+                return Some(slot_index);
+            }
+        }
+        None
+    }
+
+    /// Internal method to swap an existing fingerprint for a new one (the Cuckoo mechanism)
+    fn swap_at_bucket(
+        &mut self,
+        bucket_index: BucketIndex,
+        fingerprint: Fingerprint,
+        slot: usize,
+    ) -> Fingerprint {
+        let bucket = &mut self.data[bucket_index as usize];
+        let evicted_fingerprint = bucket[slot];
+        bucket[slot] = fingerprint;
+        evicted_fingerprint
+    }
+
+    /// Place a bare fingerprint, given either of its candidate buckets (the other is derived from the fingerprint)
+    ///
+    /// This is how we move contents between filters, where we only have fingerprints and not the original items
+    fn insert_fingerprint(
+        &mut self,
+        bucket_index: BucketIndex,
+        fingerprint: Fingerprint,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        let alternate_index = self.bucket_from_evicted(bucket_index, fingerprint);
+        self.internal_insert(bucket_index, alternate_index, fingerprint)
+    }
+
+    /// Number of free slots in a bucket
+    fn free_slots(&self, bucket_index: BucketIndex) -> usize {
+        self.data[bucket_index as usize]
+            .iter()
+            .filter(|&&slot| slot == 0)
+            .count()
+    }
+
+    /// Tries to place an item into a free slot in one of its buckets, without starting an eviction chain
+    ///
+    /// If `balanced` is set, the emptier bucket is tried first
+    fn internal_insert_without_eviction(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+        balanced: bool,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        if self.eviction_cache.is_full() {
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        let order = if balanced && self.free_slots(candidate_2) > self.free_slots(candidate_1) {
+            [candidate_2, candidate_1]
+        } else {
+            [candidate_1, candidate_2]
+        };
+        for &bucket_index in &order {
+            if let Some(slot) = self.try_insert_at_bucket(bucket_index, fingerprint) {
+                self.record_insert(0, 0, Some((candidate_1, candidate_2, fingerprint)));
+                return Ok((bucket_index, slot));
+            }
+        }
+        Err(CuckooFilterError::OutOfSpace)
+    }
+
+    /// The policy `insert` and friends follow: the defaults, with this filter's kick limit
+    fn default_policy(&self) -> InsertPolicy {
+        InsertPolicy {
+            max_kicks: self.max_evictions,
+            ..InsertPolicy::default()
+        }
+    }
+
+    /// Tries to place an item into the filter with the default policy
+    ///
+    /// Internal method, public APIs wrap this. On success, returns the bucket and slot where the new item's fingerprint ended up (which accounts for the item itself being kicked along the eviction chain)
+    fn internal_insert(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        self.internal_insert_with_policy(
+            candidate_1,
+            candidate_2,
+            fingerprint,
+            self.default_policy(),
+        )
+        .map(|(position, _)| position)
+    }
+
+    /// Tries to place an item into the filter, following `policy`
+    ///
+    /// On success, returns where the new item's fingerprint ended up (as in `internal_insert`) and how many fingerprints were kicked to make room
+    fn internal_insert_with_policy(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+        policy: InsertPolicy,
+    ) -> Result<((BucketIndex, usize), u16), CuckooFilterError> {
+        // If the cache is filled then we're (effectively) out of space, unless deletes have since freed up a slot for a cached fingerprint
+        if self.eviction_cache.is_full() {
+            self.reclaim_eviction_cache();
+            if self.eviction_cache.is_full() {
+                return Err(CuckooFilterError::OutOfSpace);
+            }
+        }
+        if self.max_bucket_collisions.is_some_and(|limit| {
+            self.matching_copies(candidate_1, candidate_2, fingerprint) >= limit
+        }) {
+            return Err(CuckooFilterError::TooManyCollisions);
+        }
+        // Try inserting into either bucket
+        if let Ok(position) = self.internal_insert_without_eviction(
+            candidate_1,
+            candidate_2,
+            fingerprint,
+            policy.balanced,
+        ) {
+            return Ok((position, 0));
+        }
+
+        // If both buckets are full, begin eviction process
+        let mut target_bucket_index = if fingerprint.is_multiple_of(2) {
+            candidate_1
+        } else {
+            candidate_2
+        };
+        // The fingerprint currently looking for a home (starts as the new item, then whatever it displaced)
+        let mut evicted_fingerprint: u8 = fingerprint;
+        // Where the new item sits, or `None` while it's the one being carried along the chain
+        let mut item_position: Option<(BucketIndex, usize)> = None;
+
+        let mut swaps: u16 = 0;
+        // Every (bucket, slot) we swapped at, so the chain can be undone if the policy says not to use the cache
+        let mut path: Vec<(BucketIndex, usize)> = Vec::new();
+
+        for kick in 0..policy.max_kicks {
+            // If kick == 0, we already tried inserting into a bucket
+            if kick > 0 {
+                if let Some(slot) =
+                    self.try_insert_at_bucket(target_bucket_index, evicted_fingerprint)
+                {
+                    self.record_insert(kick, swaps, Some((candidate_1, candidate_2, fingerprint)));
+                    return Ok((item_position.unwrap_or((target_bucket_index, slot)), swaps));
+                }
+            }
+
+            // Randomly choose a slot to evict from and swap
+            let slot = (self.slot_rng.next_u64() % B as u64) as usize;
+            evicted_fingerprint =
+                self.swap_at_bucket(target_bucket_index, evicted_fingerprint, slot);
+            swaps += 1;
+            if !policy.use_cache {
+                path.push((target_bucket_index, slot));
+            }
+            // Keep track of the new item in case the chain loops back around and kicks it out again
+            item_position = match item_position {
+                None => Some((target_bucket_index, slot)),
+                Some(position) if position == (target_bucket_index, slot) => None,
+                unchanged => unchanged,
+            };
+
+            // Recalculate the next target bucket based on the evicted fingerprint
+            target_bucket_index =
+                self.bucket_from_evicted(target_bucket_index, evicted_fingerprint);
+        }
+        self.record_insert(policy.max_kicks, swaps, None);
+        if !policy.use_cache {
+            // Walk the chain backwards, putting every displaced fingerprint back where it was. What's left over at the end is the new item's fingerprint, which we drop
+            for &(bucket_index, slot) in path.iter().rev() {
+                evicted_fingerprint = self.swap_at_bucket(bucket_index, evicted_fingerprint, slot);
+            }
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        // If the kick limit is reached, store the fingerprint in the eviction cache -- this avoids "missing" the item we couldn't insert so that lookups are still correct even when it's full
+        self.eviction_cache
+            .push(target_bucket_index, evicted_fingerprint);
+        // Every slot was already taken, so the new item only adds to the count by way of the cache
+        self.count += 1;
+        if self.eviction_cache.is_full() {
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        // The cache still has room, so this doesn't count as running out of space yet
+        Ok((item_position.unwrap_or((target_bucket_index, B)), swaps))
+    }
+
+    /// Add item to filter. Returns Err if filter is full
+    ///
+    /// ```
+    /// use cuckoo_filter::CuckooFilter;
+    /// use cuckoo_filter::Murmur3Hasher;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    /// let ins = filter.insert(&"hello, I am some data");
+    /// assert!(ins.is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Add item to filter, with the eviction settings given for this call only
+    ///
+    /// Handy for mixed workloads on one filter: a small `max_kicks` keeps inserts of cold data fast, while hot data can get the full eviction budget. With `use_cache: false` a failed insert leaves the filter untouched rather than marking it full.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let policy = InsertPolicy {
+    ///     max_kicks: 16,
+    ///     balanced: true,
+    ///     use_cache: false,
+    /// };
+    /// assert!(filter.insert_with_policy(&"hello, I am some data", policy).is_ok());
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is already full, or the eviction chain hit `max_kicks`
+    pub fn insert_with_policy<T: Hash>(
+        &mut self,
+        item: &T,
+        policy: InsertPolicy,
+    ) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_insert_with_policy(candidate_1, candidate_2, fingerprint, policy)
+            .map(|_| ())
+    }
+
+    /// Add item to this filter, or to `backup` once this one is out of space
+    ///
+    /// A lightweight two-level overflow: size `backup` for the spill-over you expect and query with `lookup_with_backup`. The two filters may differ in size.
+    ///
+    /// The insert that fills this filter still lands here (its fingerprint, or one it displaced, goes into the eviction cache where lookups find it), so only items that arrive after that go to `backup`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let mut backup = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert!(filter.insert_with_backup(&"hello, I am some data", &mut backup).is_ok());
+    /// assert!(filter.lookup_with_backup(&"hello, I am some data", &backup));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: both filters are full
+    pub fn insert_with_backup<T: Hash>(
+        &mut self,
+        item: &T,
+        backup: &mut CuckooFilter<H, B>,
+    ) -> Result<(), CuckooFilterError> {
+        if self.is_full() {
+            return backup.insert(item);
+        }
+        match self.insert(item) {
+            // The item is in the eviction cache now, which counts as stored
+            Err(CuckooFilterError::OutOfSpace) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Add item to filter, and report whether it went straight into a free slot or needed evictions
+    ///
+    /// Meant for latency analysis: inserts that evict are the slow ones, and this is cheaper to branch on than a kick count.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let outcome = filter.insert_outcome(&"hello, I am some data");
+    /// assert_eq!(outcome, Ok(InsertOutcome::DirectPlacement));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_outcome<T: Hash>(
+        &mut self,
+        item: &T,
+    ) -> Result<InsertOutcome, CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        let (_, kicks) = self.internal_insert_with_policy(
+            candidate_1,
+            candidate_2,
+            fingerprint,
+            self.default_policy(),
+        )?;
+        Ok(match kicks {
+            0 => InsertOutcome::DirectPlacement,
+            kicks => InsertOutcome::Evicted { kicks },
+        })
+    }
+
+    /// Add item to filter only if it isn't already there, pushing something else out if the filter is full
+    ///
+    /// This is the whole admission step for a bounded dedup cache in one call: a hit changes nothing, and a new item always gets in (short of the eviction chain handing back its own fingerprint, or `set_max_bucket_collisions` refusing it). When there's no room (the eviction cache is full), the fingerprint left over from the eviction chain (or the newest one already in the eviction cache) is removed from the filter and returned, so the caller can account for whatever it stood for. If the filter was already full, making room for the item may strand a different fingerprint in the eviction cache, which the next call will push out.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert_eq!(filter.insert_if_new(&"cat"), InsertIfNewResult::Inserted);
+    /// assert_eq!(filter.insert_if_new(&"cat"), InsertIfNewResult::AlreadyPresent);
+    /// ```
+    pub fn insert_if_new<T: Hash>(&mut self, item: &T) -> InsertIfNewResult {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            return InsertIfNewResult::AlreadyPresent;
+        }
+        // Copies hidden by `lazy_delete` still count towards the cap, so check it before pushing anything out
+        if self.max_bucket_collisions.is_some_and(|limit| {
+            self.matching_copies(candidate_1, candidate_2, fingerprint) >= limit
+        }) {
+            return InsertIfNewResult::Refused;
+        }
+        let mut displaced = None;
+        if self.is_full() {
+            displaced = self.take_eviction_victim();
+        }
+        if let Err(CuckooFilterError::OutOfSpace) =
+            self.internal_insert(candidate_1, candidate_2, fingerprint)
+        {
+            if displaced.is_none() {
+                displaced = self.take_eviction_victim();
+            }
+        }
+        match displaced {
+            Some(victim) => InsertIfNewResult::Displaced(victim),
+            None => InsertIfNewResult::Inserted,
+        }
+    }
+
+    /// Take the newest fingerprint out of the eviction cache
+    fn take_eviction_victim(&mut self) -> Option<(BucketIndex, Fingerprint)> {
+        let victim = self.eviction_cache.pop();
+        self.count -= victim.is_some() as usize;
+        victim
+    }
+
+    /// Add item to filter, and hand back the 64-bit digest computed along the way
+    ///
+    /// For pipelines that also use the item's hash downstream (sharding, metrics, or later `lookup_hash` calls), so it doesn't need hashing twice. The digest includes the filter's seed, if it has one.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let hash = filter.insert_returning_hash(&"hello, I am some data").unwrap();
+    /// assert_eq!(hash, murmur3_x86_64bit(b"hello, I am some data"));
+    /// assert!(filter.lookup_hash(hash));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_returning_hash<T: Hash>(&mut self, item: &T) -> Result<u64, CuckooFilterError> {
+        let hash_value = self.digest_of(item);
+        let (candidate_1, candidate_2, fingerprint) = self.digest_to_buckets(hash_value);
+        self.internal_insert(candidate_1, candidate_2, fingerprint)?;
+        Ok(hash_value)
+    }
+
+    /// Add item to filter, choosing which of its two candidate buckets to try first
+    ///
+    /// `insert` always tries the first candidate bucket first. Flipping that per call is useful for reproducing another cuckoo implementation's exact layout. If the preferred bucket is full, the other one is tried, and then the usual eviction chain kicks in.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert!(filter.insert_prefer(&"hello, I am some data", true).is_ok());
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_prefer<T: Hash>(
+        &mut self,
+        item: &T,
+        prefer_second: bool,
+    ) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        // The buckets are interchangeable as far as the filter is concerned, so we can just swap them
+        let (first, second) = if prefer_second {
+            (candidate_2, candidate_1)
+        } else {
+            (candidate_1, candidate_2)
+        };
+        self.internal_insert(first, second, fingerprint).map(|_| ())
+    }
+
+    /// The two candidate buckets an item maps to, without inserting or looking anything up
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let (first, second) = filter.peek_buckets(&"hello, I am some data");
+    /// let (bucket_index, _) = filter.insert_locating(&"hello, I am some data").unwrap();
+    /// assert!(bucket_index == first || bucket_index == second);
+    /// ```
+    pub fn peek_buckets<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex) {
+        let (candidate_1, candidate_2, _) = self.digest_to_buckets(self.digest_of(item));
+        (candidate_1, candidate_2)
+    }
+
+    /// Add item to filter, and report where its fingerprint was placed as `(bucket index, slot)`
+    ///
+    /// The position accounts for any evictions the insert caused, so it's useful for maintaining a side-index from items to physical positions. Keep in mind that later inserts may kick the fingerprint somewhere else.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let (bucket_index, slot) = filter.insert_locating(&"hello, I am some data").unwrap();
+    /// assert!((bucket_index as usize) < filter.num_buckets());
+    /// assert!(slot < filter.bucket_size());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_locating<T: Hash>(
+        &mut self,
+        item: &T,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Add item to filter, unless it's already present, in which case this is a no-op that returns `Ok`
+    ///
+    /// Re-inserting an item that's already there wastes a slot and pushes the filter towards saturation, so workloads with heavy key repetition should prefer this over `insert`. (Unlike `insert_unique`, duplicates aren't an error.)
+    ///
+    /// Because presence is probabilistic, a new item whose fingerprint collides with one already in its buckets is treated as a duplicate and skipped. Lookups for it still succeed (that's what the collision means), but deleting either item will make both disappear.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// for _ in 0..10 {
+    ///     assert!(filter.insert_dedup(&"hello, I am some data").is_ok());
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_dedup<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            return Ok(());
+        }
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Add item to filter if it's new, without ever evicting anything
+    ///
+    /// Returns `Ok(false)` if the item is already present, `Ok(true)` if it was placed in a free slot of one of its buckets. Because there's no eviction chain, latency is bounded (two buckets probed, at most), which suits strict dedup caches. The flip side is that this gives up as soon as both buckets are full, even if `insert` could have made room.
+    ///
+    /// As with `insert_dedup`, "already present" is probabilistic: a new item whose fingerprint collides with one in its buckets reports `Ok(false)`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert_eq!(filter.try_insert_unique(&"hello, I am some data"), Ok(true));
+    /// assert_eq!(filter.try_insert_unique(&"hello, I am some data"), Ok(false));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: both candidate buckets are full (or the filter is already full)
+    pub fn try_insert_unique<T: Hash>(&mut self, item: &T) -> Result<bool, CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            return Ok(false);
+        }
+        self.internal_insert_without_eviction(candidate_1, candidate_2, fingerprint, false)
+            .map(|_| true)
+    }
+
+    /// Add item to filter, but use a provided stateless hash function. Requires the item to be passed as bytes (because we're bypassing the `Hash` Trait).
+    ///
+    /// This allows items to be inserted that don't implement `Hash`, for whatever reason.
+    ///
+    /// Technically, this should be "faster" because it doesn't require setting up a fresh Hasher, but depending on compiler optimizations it may not pan out. Benchmark on your system first!
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    /// let ins = filter.insert_stateless(&"hello, I am some data".as_bytes(), murmur3_x86_64bit);
+    /// assert!(ins.is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_stateless(
+        &mut self,
+        item: &[u8],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) =
+            self.buckets_from_item_stateless(item, hash_function);
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Add an item that arrives as a stream of bytes (from a parser, say), using a provided stateless hash function
+    ///
+    /// The result is exactly `insert_stateless` on the collected bytes, so `contains_stateless` finds it. A stateless hash function needs the whole key as one slice, so the bytes are gathered into a 256 byte stack buffer, and only keys longer than that spill over onto the heap.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let streamed = "hello, I am some data".bytes();
+    /// assert!(filter.insert_from_byte_iter(streamed, murmur3_x86_64bit).is_ok());
+    /// assert!(filter.contains_stateless(b"hello, I am some data", murmur3_x86_64bit));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_from_byte_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        bytes: I,
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Result<(), CuckooFilterError> {
+        const STACK_BYTES: usize = 256;
+        let mut buffer = [0u8; STACK_BYTES];
+        let mut bytes = bytes.into_iter();
+        let mut filled = 0;
+        for (slot, byte) in buffer.iter_mut().zip(bytes.by_ref()) {
+            *slot = byte;
+            filled += 1;
+        }
+        if filled < STACK_BYTES {
+            return self.insert_stateless(&buffer[..filled], hash_function);
+        }
+        // Too long for the stack buffer (or exactly full, in which case `bytes` is just empty)
+        let mut spilled = buffer.to_vec();
+        spilled.extend(bytes);
+        self.insert_stateless(&spilled, hash_function)
+    }
+
+    /// Identifies if an item is in the filter
+    ///
+    /// This is an internal method that public APIs wrap around
+    fn internal_lookup(&self, candidate_1: u32, candidate_2: u32, fingerprint: u8) -> bool {
+        // Tombstoned copies (from `lazy_delete`) don't count until they're flushed
+        if !self.pending_deletes.is_empty() {
+            let found = self.matching_copies(candidate_1, candidate_2, fingerprint)
+                > self.pending_copies(candidate_1, fingerprint);
+            if found && self.cache_matches(candidate_1, candidate_2, fingerprint) {
+                self.count_cache_hit();
+            }
+            return found;
+        }
+        // Check cache
+        if self.cache_matches(candidate_1, candidate_2, fingerprint) {
+            self.count_cache_hit();
+            return true;
+        }
+        // Check buckets
+        bucket_contains(&self.data[candidate_1 as usize], fingerprint)
+            || bucket_contains(&self.data[candidate_2 as usize], fingerprint)
+    }
+
+    /// Add a lookup to `cache_hit_count` (a no-op on targets without atomic read-modify-write)
+    #[inline(always)]
+    fn count_cache_hit(&self) {
+        #[cfg(target_has_atomic = "ptr")]
+        self.cache_hit_lookups.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Is this fingerprint, for this pair of buckets, in the eviction cache?
+    fn cache_matches(&self, candidate_1: u32, candidate_2: u32, fingerprint: u8) -> bool {
+        self.eviction_cache
+            .position(candidate_1, candidate_2, fingerprint)
+            .is_some()
+    }
+
+    /// How many copies of a fingerprint are stored across an item's buckets and the eviction cache
+    fn matching_copies(&self, candidate_1: u32, candidate_2: u32, fingerprint: u8) -> usize {
+        let in_cache = self
+            .eviction_cache
+            .copies(candidate_1, candidate_2, fingerprint);
+        let in_bucket = |bucket_index: u32| {
+            self.data[bucket_index as usize]
+                .iter()
+                .filter(|entry| **entry == fingerprint)
+                .count()
+        };
+        let mut copies = in_cache + in_bucket(candidate_1);
+        if candidate_2 != candidate_1 {
+            copies += in_bucket(candidate_2);
+        }
+        copies
+    }
+
+    /// How many pending deletes target this fingerprint and bucket pair (either bucket plus the fingerprint pins down the pair)
+    fn pending_copies(&self, candidate_1: u32, fingerprint: u8) -> usize {
+        self.pending_deletes
+            .iter()
+            .filter(|&&(pending_1, pending_2, pending_fingerprint)| {
+                pending_fingerprint == fingerprint
+                    && (pending_1 == candidate_1 || pending_2 == candidate_1)
+            })
+            .count()
+    }
+
+    /// Add item to filter, unless it's already present, in which case this is an error
+    ///
+    /// "Already present" has the same meaning as `contains`: the item's fingerprint is in one of its buckets (or the eviction cache). Because the filter is probabilistic, `ItemAlreadyExists` may be a false positive: a distinct item that happens to share buckets and a fingerprint with something already inserted is refused too. (Use `insert_dedup` if duplicates shouldn't be an error.)
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// assert!(filter.insert_unique(&"hello, I am some data").is_ok());
+    /// assert_eq!(
+    ///     filter.insert_unique(&"hello, I am some data"),
+    ///     Err(CuckooFilterError::ItemAlreadyExists)
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemAlreadyExists`: the item (or something indistinguishable from it) is already in the filter
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_unique<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_insert_unique(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Add item to filter unless it's already present, using a provided stateless hash function. See `insert_unique`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemAlreadyExists`: the item (or something indistinguishable from it) is already in the filter
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert_unique_stateless(
+        &mut self,
+        item: &[u8],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) =
+            self.buckets_from_item_stateless(item, hash_function);
+        self.internal_insert_unique(candidate_1, candidate_2, fingerprint)
+    }
+
+    fn internal_insert_unique(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+    ) -> Result<(), CuckooFilterError> {
+        if self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            return Err(CuckooFilterError::ItemAlreadyExists);
+        }
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Check if item is in filter
+    ///
+    /// `true` means the item is probably in the filter: it may be a false positive (see `false_positive_rate`). `false` is always right, so long as nothing was deleted that wasn't inserted.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    ///
+    /// let item = "hello, I am some data";
+    /// let _ = filter.insert(&item);
+    /// let was_found = filter.contains(&item);
+    /// assert!(was_found);
+    /// ```
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Check if item is in filter, remembering the item so that looking it up again straight away skips hashing it
+    ///
+    /// Meant for check-then-act loops that query the same item twice in a row. The filter keeps a copy of the last item (boxed) plus its buckets and fingerprint, trading that bit of memory for speed on repeats. The cached buckets survive inserts and deletes, since those don't change where an item belongs, but are dropped if the filter is resized or its hasher key changes.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let item = String::from("hello, I am some data");
+    /// let _ = filter.insert(&item);
+    /// assert!(filter.lookup_cached(&item));
+    /// // No hashing the second time around
+    /// assert!(filter.lookup_cached(&item));
+    /// ```
+    pub fn lookup_cached<T>(&mut self, item: &T) -> bool
+    where
+        T: Hash + PartialEq + Clone + Send + Sync + 'static,
+    {
+        let cached = self.last_lookup.as_ref().and_then(|last| {
+            let same_item = last.item.downcast_ref::<T>() == Some(item);
+            (same_item && last.length_u32 == self.length_u32).then_some(last.triple)
+        });
+        let (candidate_1, candidate_2, fingerprint) = match cached {
+            Some(triple) => triple,
+            None => {
+                let triple = self.buckets_from_item(item);
+                self.last_lookup = Some(LastLookup {
+                    item: Box::new(item.clone()),
+                    length_u32: self.length_u32,
+                    triple,
+                });
+                triple
+            }
+        };
+        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter, but use a provided stateless hash function.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    ///
+    /// let item = "hello, I am some data";
+    /// let _ = filter.insert_stateless(&item.as_bytes(), murmur3_x86_64bit);
+    /// let was_found = filter.contains_stateless(&item.as_bytes(), murmur3_x86_64bit);
+    /// assert!(was_found);
+    /// ```
+    pub fn contains_stateless(&self, item: &[u8], hash_function: fn(&[u8]) -> u64) -> bool {
+        let (candidate_1, candidate_2, fingerprint) =
+            self.buckets_from_item_stateless(item, hash_function);
+        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter, but use a provided stateless hash function. The paper's name for `contains_stateless`
+    #[deprecated(note = "use `contains_stateless`, which this forwards to")]
+    pub fn lookup_stateless(&self, item: &[u8], hash_function: fn(&[u8]) -> u64) -> bool {
+        self.contains_stateless(item, hash_function)
+    }
+
+    /// Check if item is in filter, spelling out how much the answer can be trusted
+    ///
+    /// The same check as `contains`, but a `bool` makes it easy to forget that `true` only means "probably". Matching on `Membership` keeps the false positive case visible in downstream code.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"cat");
+    /// assert_eq!(filter.membership(&"cat"), Membership::ProbablyPresent);
+    /// ```
+    pub fn membership<T: Hash>(&self, item: &T) -> Membership {
+        let (candidate_1, candidate_2, fingerprint) = self.digest_to_buckets(self.digest_of(item));
+        if self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            Membership::ProbablyPresent
+        } else {
+            Membership::DefinitelyAbsent
+        }
+    }
+
+    /// Check if item is in this filter or in `backup` (see `insert_with_backup`)
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let mut backup = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = backup.insert(&"hello, I am some data");
+    /// assert!(filter.lookup_with_backup(&"hello, I am some data", &backup));
+    /// ```
+    pub fn lookup_with_backup<T: Hash>(&self, item: &T, backup: &CuckooFilter<H, B>) -> bool {
+        [self, backup].iter().any(|filter| {
+            let (candidate_1, candidate_2, fingerprint) =
+                filter.digest_to_buckets(filter.digest_of(item));
+            filter.internal_lookup(candidate_1, candidate_2, fingerprint)
+        })
+    }
+
+    fn internal_delete(
+        &mut self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: u8,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        // If every stored copy is already lazily deleted, the item is gone as far as lookups are concerned, and clearing a slot would leave a tombstone hiding the next insert
+        if !self.pending_deletes.is_empty()
+            && self.matching_copies(candidate_1, candidate_2, fingerprint)
+                <= self.pending_copies(candidate_1, fingerprint)
+        {
+            return Err(CuckooFilterError::ItemDoesNotExist);
+        }
+        // Check cache and clear if found
+        if let Some(position) = self
+            .eviction_cache
+            .position(candidate_1, candidate_2, fingerprint)
+        {
+            let (bucket_index, _) = self.eviction_cache.remove(position);
+            self.count -= 1;
+            return Ok((bucket_index, B));
+        }
+        // Check buckets and clear if found
+        for &bucket_index in &[candidate_1, candidate_2] {
+            for (slot, entry) in self.data[bucket_index as usize].iter_mut().enumerate() {
+                if *entry == fingerprint {
+                    *entry = 0;
+                    self.count -= 1;
+                    return Ok((bucket_index, slot));
+                }
+            }
+        }
+        Err(CuckooFilterError::ItemDoesNotExist)
+    }
+
+    /// Delete an item from the filter, and report which position was cleared as `(bucket index, slot)`
+    ///
+    /// The counterpart to `insert_locating`, for keeping a side-index from items to positions up to date. If the item was in the eviction cache, the bucket index is the cache's and the slot is `EVICTION_CACHE_SLOT` (`B` with a non-default bucket size).
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let placed = filter.insert_locating(&"hello, I am some data").unwrap();
+    /// assert_eq!(filter.delete_locating(&"hello, I am some data"), Ok(placed));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete_locating<T: Hash>(
+        &mut self,
+        item: &T,
+    ) -> Result<(BucketIndex, usize), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_delete(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Delete an item from the filter
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    ///
+    /// let item = "hello, I am some data";
+    /// let _ = filter.insert(&item);
+    /// let was_found = filter.contains(&item);
+    /// assert!(was_found);
+    ///
+    /// let was_deleted = filter.delete(&item);
+    /// assert!(was_deleted.is_ok());
+    /// ```
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.internal_delete(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Remove the item if it's in the filter, and say whether it was
+    ///
+    /// For "if present, remove it and act": the same as `delete`, with a `bool` instead of a `Result`, and it hashes the item once where `contains` followed by `delete` hashes it twice. Like `contains`, it can be fooled by a false positive, in which case it removes the other item's fingerprint.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.take(&"hello, I am some data"));
+    /// assert!(!filter.take(&"hello, I am some data"));
+    /// ```
+    pub fn take<T: Hash>(&mut self, item: &T) -> bool {
+        self.delete(item).is_ok()
+    }
+
+    /// Delete an item from the filter, but refuse if more than one slot matches it
+    ///
+    /// Two matching slots (in either bucket, or the eviction cache) mean the item was inserted twice, or that another item shares its buckets and fingerprint. `delete` just removes one of them; this leaves the filter unchanged and lets you decide.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert_eq!(
+    ///     filter.strict_delete(&"hello, I am some data"),
+    ///     Err(CuckooFilterError::AmbiguousDelete)
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: nothing matches the item
+    /// - `CuckooFilterError::AmbiguousDelete`: more than one slot matches the item
+    pub fn strict_delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.matching_copies(candidate_1, candidate_2, fingerprint) > 1 {
+            return Err(CuckooFilterError::AmbiguousDelete);
+        }
+        self.internal_delete(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Mark an item as deleted without touching its slot yet; `flush_deletes` applies all pending deletes in one pass
+    ///
+    /// Lookups treat lazily deleted items as absent straight away, so this behaves like `delete` from the outside. Deferring the slot clearing lets delete-heavy workloads batch their maintenance. Until the flush, whole-filter operations (serialization, `drain_into`, the diagnostics) still see the slots as occupied, so flush before using them. `grow` and `shrink_to_fit` flush for you, and `clone_shrunk` leaves lazily deleted items out of its copy. An eager `delete` of an item whose every copy is lazily deleted fails with `ItemDoesNotExist`, just as it would after a flush.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let item = "hello, I am some data";
+    /// let _ = filter.insert(&item);
+    /// assert!(filter.lazy_delete(&item).is_ok());
+    /// assert!(!filter.contains(&item));
+    /// filter.flush_deletes();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter (or already has a pending delete)
+    pub fn lazy_delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if !self.internal_lookup(candidate_1, candidate_2, fingerprint) {
+            return Err(CuckooFilterError::ItemDoesNotExist);
+        }
+        self.pending_deletes
+            .push((candidate_1, candidate_2, fingerprint));
+        Ok(())
+    }
+
+    /// Apply every pending `lazy_delete`, freeing the slots
+    ///
+    /// Afterwards, if items are stuck in the eviction cache, we try to move them back into the table now that there may be room (which clears `is_full`).
+    pub fn flush_deletes(&mut self) {
+        let pending = core::mem::take(&mut self.pending_deletes);
+        for (candidate_1, candidate_2, fingerprint) in pending {
+            // Anything that was already removed some other way is simply skipped
+            let _ = self.internal_delete(candidate_1, candidate_2, fingerprint);
+        }
+        self.reclaim_eviction_cache();
+    }
+
+    /// If items are stuck in the eviction cache, try to move them back into one of their buckets
+    fn reclaim_eviction_cache(&mut self) {
+        let mut position = 0;
+        while position < self.eviction_cache.len() {
+            let (bucket_index, fingerprint) = self.eviction_cache.victims()[position];
+            let alternate_index = self.bucket_from_evicted(bucket_index, fingerprint);
+            if [bucket_index, alternate_index]
+                .iter()
+                .any(|&candidate| self.try_insert_at_bucket(candidate, fingerprint).is_some())
+            {
+                // Moved, not added, so undo the count bump from the insert
+                self.eviction_cache.remove(position);
+                self.count -= 1;
+            } else {
+                position += 1;
+            }
+        }
+    }
+
+    /// Delete an item from the filter, using a provided stateless hash function
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+    /// let mut filter = try_filter.unwrap();
+    ///
+    /// let item = "hello, I am some data";
+    /// let _ = filter.insert_stateless(&item.as_bytes(), murmur3_x86_64bit);
+    /// let was_found = filter.contains_stateless(&item.as_bytes(), murmur3_x86_64bit);
+    /// assert!(was_found);
+    ///
+    /// let was_deleted = filter.delete_stateless(&item.as_bytes(), murmur3_x86_64bit);
+    /// assert!(was_deleted.is_ok());
+    /// ```
+    pub fn delete_stateless(
+        &mut self,
+        item: &[u8],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) =
+            self.buckets_from_item_stateless(item, hash_function);
+        self.internal_delete(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Remove the item if it's in the filter and say whether it was, using a provided stateless hash function (see `take`)
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert_stateless(b"hello, I am some data", murmur3_x86_64bit);
+    /// assert!(filter.take_stateless(b"hello, I am some data", murmur3_x86_64bit));
+    /// assert!(!filter.take_stateless(b"hello, I am some data", murmur3_x86_64bit));
+    /// ```
+    pub fn take_stateless(&mut self, item: &[u8], hash_function: fn(&[u8]) -> u64) -> bool {
+        self.delete_stateless(item, hash_function).is_ok()
+    }
+
+    /// Add a batch of items to the filter, returning one result per item (in order)
+    ///
+    /// Once an insert fills the filter, nothing later in the batch can fit, so the rest are marked `OutOfSpace` without being hashed. The index of the first `OutOfSpace` is where the filter saturated (that item's fingerprint is still in the filter, in the eviction cache).
+    ///
+    /// The results take one byte per item (`Result<(), CuckooFilterError>` is a single byte), so a batch of a million items allocates about 1 MB on top of the filter. Split very large batches up if that matters.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let results = filter.insert_all(&["cat", "dog"]);
+    /// assert!(results.iter().all(|r| r.is_ok()));
+    /// ```
+    pub fn insert_all<T: Hash>(&mut self, items: &[T]) -> Vec<Result<(), CuckooFilterError>> {
+        let mut saturated = false;
+        items
+            .iter()
+            .map(|item| {
+                if saturated {
+                    return Err(CuckooFilterError::OutOfSpace);
+                }
+                let result = self.insert(item);
+                saturated = self.is_full();
+                result
+            })
+            .collect()
+    }
+
+    /// Add a batch of items to the filter using a provided stateless hash function, returning one result per item (in order)
+    ///
+    /// This is the bulk version of `insert_stateless`; the same function pointer is reused for the whole batch. As in `insert_all`, everything after the insert that fills the filter is marked `OutOfSpace` without being hashed, and the results cost one byte per item.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let items: [&[u8]; 2] = [b"cat", b"dog"];
+    /// let results = filter.insert_all_stateless(&items, murmur3_x86_64bit);
+    /// assert!(results.iter().all(|r| r.is_ok()));
+    /// ```
+    pub fn insert_all_stateless(
+        &mut self,
+        items: &[&[u8]],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Vec<Result<(), CuckooFilterError>> {
+        let mut saturated = false;
+        items
+            .iter()
+            .map(|item| {
+                if saturated {
+                    return Err(CuckooFilterError::OutOfSpace);
+                }
+                let result = self.insert_stateless(item, hash_function);
+                saturated = self.is_full();
+                result
+            })
+            .collect()
+    }
+
+    /// Check a batch of items using a provided stateless hash function, returning one answer per item (in order)
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert_stateless(b"cat", murmur3_x86_64bit);
+    /// let items: [&[u8]; 2] = [b"cat", b"dog"];
+    /// assert_eq!(
+    ///     filter.lookup_all_stateless(&items, murmur3_x86_64bit),
+    ///     vec![true, false]
+    /// );
+    /// ```
+    pub fn lookup_all_stateless(
+        &self,
+        items: &[&[u8]],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Vec<bool> {
+        items
+            .iter()
+            .map(|item| self.contains_stateless(item, hash_function))
+            .collect()
+    }
+
+    /// Check a batch of items, returning one answer per item (in order)
+    ///
+    /// Gives the same answers as calling `contains` on each item, but faster for big filters: the lookups are independent, so the candidate buckets for a group of items are computed and prefetched before any of them are compared, letting those cache misses overlap instead of being paid one after another. Groups are kept small enough that the prefetched lines are still in cache by the time they're read.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"cat");
+    /// assert_eq!(filter.lookup_all(&["cat", "dog"]), vec![true, false]);
+    /// ```
+    pub fn lookup_all<T: Hash>(&self, items: &[T]) -> Vec<bool> {
+        const PREFETCH_GROUP: usize = 16;
+        let mut results = Vec::with_capacity(items.len());
+        let mut triples = [(0, 0, 0); PREFETCH_GROUP];
+        for group in items.chunks(PREFETCH_GROUP) {
+            for (triple, item) in triples.iter_mut().zip(group) {
+                *triple = self.digest_to_buckets(self.digest_of(item));
+                self.prefetch_bucket(triple.0);
+                self.prefetch_bucket(triple.1);
+            }
+            results.extend(triples[..group.len()].iter().map(
+                |&(candidate_1, candidate_2, fingerprint)| {
+                    self.internal_lookup(candidate_1, candidate_2, fingerprint)
+                },
+            ));
+        }
+        results
+    }
+
+    /// Hint to the CPU that a bucket is about to be read (a no-op off x86_64)
+    #[inline(always)]
+    fn prefetch_bucket(&self, bucket_index: BucketIndex) {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: prefetching is only a hint and never faults, and the pointer is in bounds anyway
+        unsafe {
+            use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            let bucket = self.data.as_ptr().add(bucket_index as usize);
+            _mm_prefetch::<_MM_HINT_T0>(bucket.cast());
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = bucket_index;
+    }
+
+    /// Delete a batch of items using a provided stateless hash function, returning one result per item (in order)
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert_stateless(b"cat", murmur3_x86_64bit);
+    /// let items: [&[u8]; 2] = [b"cat", b"dog"];
+    /// let results = filter.delete_all_stateless(&items, murmur3_x86_64bit);
+    /// assert_eq!(results, vec![Ok(()), Err(CuckooFilterError::ItemDoesNotExist)]);
+    /// assert!(!filter.contains_stateless(b"cat", murmur3_x86_64bit));
+    /// ```
+    pub fn delete_all_stateless(
+        &mut self,
+        items: &[&[u8]],
+        hash_function: fn(&[u8]) -> u64,
+    ) -> Vec<Result<(), CuckooFilterError>> {
+        items
+            .iter()
+            .map(|item| self.delete_stateless(item, hash_function))
+            .collect()
+    }
+
+    /// Add an item to the filter using a hash you've already computed for it
+    ///
+    /// Useful when your data already carries 64-bit hashes of each key. Use `lookup_hash` and `delete_hash` with the same hash to query it.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let hash_value = murmur3_x86_64bit("hello, I am some data".as_bytes());
+    /// assert!(filter.insert_hash(hash_value).is_ok());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items (the last insert failed because it tried to evict too many items). This can occur _before_ the filter is "theoretically" full due to hash collisions.
+    pub fn insert_hash(&mut self, hash_value: u64) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.digest_to_buckets(hash_value);
+        self.internal_insert(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+
+    /// Check if an item is in the filter, using a hash you've already computed for it
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let hash_value = murmur3_x86_64bit("hello, I am some data".as_bytes());
+    /// let _ = filter.insert_hash(hash_value);
+    /// assert!(filter.lookup_hash(hash_value));
+    /// ```
+    pub fn lookup_hash(&self, hash_value: u64) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = self.digest_to_buckets(hash_value);
+        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Delete an item from the filter, using a hash you've already computed for it
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: nothing in the filter matches the hash
+    pub fn delete_hash(&mut self, hash_value: u64) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.digest_to_buckets(hash_value);
+        self.internal_delete(candidate_1, candidate_2, fingerprint)
+            .map(|_| ())
+    }
+}
+
+impl<H: KeyedHasher, const B: usize> CuckooFilter<H, B> {
+    /// Try to create a new Cuckoo Filter whose hasher is keyed with a secret `key`
+    ///
+    /// With an unkeyed hasher, anyone can work out which buckets an item lands in, and craft items that all pile into the same few buckets so the filter reports `OutOfSpace` far below its capacity. With a keyed hasher such as `SipHasher13` and a key kept secret (generate it randomly), an attacker can't predict placement, so they can't force collisions. This doesn't make the fingerprints cryptographically strong: they're still 8 bits, and the false positive rate is unchanged.
+    ///
+    /// The key applies to the `Hash`-based methods; the `_stateless` and `_hash` methods take their hash from you. It's carried over to resized copies, but isn't serialized, so call `set_hasher_key` on a filter restored with `from_bytes`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let key = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+    /// let mut filter = CuckooFilter::<SipHasher13>::new_with_keyed_hasher(128, key).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new_with_keyed_hasher(
+        max_items: usize,
+        key: u128,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new(max_items, false)?;
+        filter.set_hasher_key(key);
+        Ok(filter)
+    }
+
+    /// Install the key the hasher starts from
+    ///
+    /// Items already in the filter were placed with the old key (or none), and can't be found with a different one, so only call this on an empty filter, or to restore the key of a filter rebuilt with `from_bytes`.
+    pub fn set_hasher_key(&mut self, key: u128) {
+        self.hasher_key = Some((key, H::with_key));
+        self.last_lookup = None;
+    }
+}
+
+impl<H: SeededHasher, const B: usize> CuckooFilter<H, B> {
+    /// Try to create a new Cuckoo Filter that hands its seed to the hasher (`SeededHasher::with_seed`), instead of writing it in ahead of every item
+    ///
+    /// Otherwise this is `new_with_seed`: rotating the seed moves items to different buckets. The two ways of seeding place items differently, so a filter can't switch between them once it holds items. Which one a filter uses isn't serialized, so call `set_seeded_hasher` on a filter restored with `from_bytes`.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new_with_seeded_hasher(128, 7).unwrap();
+    /// assert_eq!(filter.seed(), 7);
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// assert!(filter.contains(&"hello, I am some data"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new_with_seeded_hasher(
+        max_items: usize,
+        seed: u32,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new_with_seed(max_items, seed)?;
+        filter.set_seeded_hasher();
+        Ok(filter)
+    }
+
+    /// Seed the hasher with `SeededHasher::with_seed` from now on
+    ///
+    /// Items already in the filter were placed with the seed written in ahead of them, and can't be found this way, so only call this on an empty filter, or to restore the setting on a filter rebuilt with `from_bytes`.
+    pub fn set_seeded_hasher(&mut self) {
+        self.native_seed = Some(H::with_seed);
+        self.last_lookup = None;
+    }
+}
+
+/// Two filters are equal when they're in the same physical state: the same buckets holding the same fingerprints in the same slots, the same eviction cache, and the same seed (seeded the same way) and hasher key
+///
+/// This is not set equality. Inserting the same items in a different order can leave fingerprints in different slots (or different buckets, after evictions), so such filters can compare unequal while answering every lookup the same way. Bookkeeping that doesn't affect lookups (stats, pending lazy deletes, limits, and the stored hash function) is ignored.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&filter.to_bytes()).unwrap();
+/// assert_eq!(restored, filter);
+/// ```
+impl<H: Hasher + Default, const B: usize> PartialEq for CuckooFilter<H, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.length_u32 == other.length_u32
+            && self.same_hashing(other)
+            && self.eviction_cache.victims() == other.eviction_cache.victims()
+            && self.data == other.data
+    }
+}
+
+impl<H: Hasher + Default, const B: usize> Eq for CuckooFilter<H, B> {}
+
+/// Insert every item, dropping the ones that don't fit
+///
+/// `Extend` can't report errors, so: the insert that fills the filter still lands (its fingerprint, or one it displaced, goes into the eviction cache), and iteration stops there, dropping every item after it without hashing it. Items refused for other reasons (`TooManyCollisions`, with `set_max_bucket_collisions`) are skipped and the rest still go in. Dropped items aren't found by `contains` afterwards, so check `is_full()` when that matters, or use `try_from_iter` (which fails instead) or `insert_all` (which says which items didn't fit).
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+/// filter.extend(["cat", "dog"]);
+/// assert!(filter.contains(&"dog"));
+/// assert!(!filter.is_full());
+/// ```
+impl<T: Hash, H: Hasher + Default, const B: usize> Extend<T> for CuckooFilter<H, B> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, items: I) {
+        for item in items {
+            if self.is_full() {
+                break;
+            }
+            let _ = self.insert(&item);
+        }
+    }
+}
+
+/// Check a two-tier setup (a small hot filter in front of a large cold one) and report which tier matched
+///
+/// The item is hashed once and the digest is reused for both filters, so they must use the same `Hasher` (if their seeds or hasher keys differ, the item is hashed once per filter instead). The hot filter is checked first, so an item in both tiers is a `HotHit`.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let hot = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+/// let mut cold = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+/// let item = "hello, I am some data";
+/// let _ = cold.insert(&item);
+/// assert_eq!(tiered_lookup(&hot, &cold, &item), TierResult::ColdHit);
+/// ```
+pub fn tiered_lookup<H: Hasher + Default, T: Hash, const B: usize>(
+    hot: &CuckooFilter<H, B>,
+    cold: &CuckooFilter<H, B>,
+    item: &T,
+) -> TierResult {
+    let hash_value: u64 = hot.digest_of(item);
+    let (candidate_1, candidate_2, fingerprint) = hot.digest_to_buckets(hash_value);
+    if hot.internal_lookup(candidate_1, candidate_2, fingerprint) {
+        return TierResult::HotHit;
+    }
+    let hash_value: u64 = if cold.same_hashing(hot) {
+        hash_value
+    } else {
+        cold.digest_of(item)
+    };
+    let (candidate_1, candidate_2, fingerprint) = cold.digest_to_buckets(hash_value);
+    if cold.internal_lookup(candidate_1, candidate_2, fingerprint) {
+        return TierResult::ColdHit;
+    }
+    TierResult::NotFound
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{murmur3_x86_64bit, Murmur3Hasher};
+    use rand::{distributions::Uniform, prelude::*};
+    use rand_chacha::ChaCha8Rng;
+
+    // Utility fns
+    fn occupied_slots(cf: &CuckooFilter<Murmur3Hasher>) -> usize {
+        cf.data.iter().flatten().filter(|fp| **fp != 0).count()
+    }
+
+    fn get_random_string(rng: &mut ChaCha8Rng, len: usize) -> String {
+        rng.sample_iter::<char, _>(&rand::distributions::Standard)
+            .take(len)
+            .collect()
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            CuckooFilterError::OutOfSpace.to_string(),
+            "the filter is out of space"
+        );
+        assert_eq!(
+            CuckooFilterError::UnsupportedFormatVersion.to_string(),
+            "the serialized filter uses an unsupported format version"
+        );
+        // Works with `?` in functions returning a boxed error
+        fn insert_twice() -> Result<(), Box<dyn std::error::Error>> {
+            let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false)?;
+            cf.insert_unique(&1u64)?;
+            cf.insert_unique(&1u64)?;
+            Ok(())
+        }
+        let error = insert_twice().unwrap_err();
+        assert_eq!(error.to_string(), "the item is already in the filter");
+        assert_eq!(
+            error.downcast_ref::<CuckooFilterError>(),
+            Some(&CuckooFilterError::ItemAlreadyExists)
+        );
+    }
+
+    #[test]
+    fn make_filter_normal_conditions() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        assert!(filter.is_ok());
+        let cf = filter.unwrap();
+        assert_eq!(cf.length_u32, 128 / 4);
+        assert_eq!(128 / 4, cf.data.len() as u32);
+    }
+
+    // The filter should hold exactly the item limit but no more (error is around secondary checks relating to power of 2 rounding)
+    #[test]
+    fn make_filter_item_limit_boundary() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(ITEM_LIMIT, false);
+        assert!(filter.is_ok());
+        let filter2 = CuckooFilter::<Murmur3Hasher>::new(ITEM_LIMIT + 1, false);
+        assert!(filter2.is_err());
+        assert_eq!(
+            CuckooFilterError::CapacityExceedsItemLimit,
+            filter2.unwrap_err()
+        );
+    }
+
+    // Check that the comp time check throws
+    #[test]
+    #[should_panic(expected = "cuckoo filter initialized with too many items")]
+    fn make_filter_comp_time_check() {
+        const TOO_MANY_ITEMS: usize = ITEM_LIMIT + 1;
+        let _filter = CuckooFilter::<Murmur3Hasher>::new(TOO_MANY_ITEMS, true);
+    }
+
+    #[test]
+    fn check_size() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let cf = filter.unwrap();
+        assert_eq!(cf.estimate_size(), 128);
+    }
+
+    #[test]
+    fn check_geometry() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(1000, false);
+        let cf = filter.unwrap();
+        assert_eq!(cf.num_buckets(), 256);
+        assert_eq!(cf.bucket_size(), 4);
+        assert_eq!(cf.num_buckets() * cf.bucket_size(), cf.capacity());
+    }
+
+    #[test]
+    fn shrink_empty_filter_respects_floor() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(4096, false);
+        let mut cf = filter.unwrap();
+        cf.shrink_to_fit(256);
+        assert!(cf.capacity() >= 256);
+        assert!(cf.capacity() < 4096);
+    }
+
+    #[test]
+    fn shrink_keeps_contents() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(4096, false);
+        let mut cf = filter.unwrap();
+        let items: Vec<u64> = (0..100).collect();
+        for i in items.iter() {
+            assert!(cf.insert(i).is_ok());
+        }
+        cf.shrink_to_fit(0);
+        assert_eq!(cf.capacity(), 128);
+        assert!(items.iter().all(|i| cf.contains(i)));
+    }
+
+    #[test]
+    fn check_bucket_equivalence() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(1, false);
+        let cf = filter.unwrap();
+        let (b1, b2, f) = cf.digest_to_buckets(murmur3_x86_64bit("test".as_bytes()));
+        let b2alt = cf.bucket_from_evicted(b1, f);
+        let b1alt = cf.bucket_from_evicted(b2, f);
+        assert_eq!(b1, b1alt);
+        assert_eq!(b2, b2alt);
+    }
+
+    #[test]
+    fn buckets_for_matches_filter() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..1000u64 {
+            let hash_value = murmur3_x86_64bit(&i.to_le_bytes());
+            assert_eq!(
+                buckets_for(hash_value, cf.length_u32),
+                cf.digest_to_buckets(hash_value)
+            );
+        }
+    }
+
+    #[test]
+    fn insert_bytes() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        let r = cf.insert(&[1, 2, 3, 4, 5]);
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn insert_number() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        let r = cf.insert(&19384);
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn insert_string() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        let r = cf.insert(&"hello");
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn retrieve_item() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        let item = [1u8, 2, 3, 4, 5];
+        let r = cf.insert(&item);
+        assert!(r.is_ok());
+        let is_found = cf.contains(&item);
+        assert!(is_found);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn lookup_is_an_alias_for_contains() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..900u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Members, non-members, and whatever false positives turn up among them
+        for i in 0..100_000u64 {
+            assert_eq!(cf.lookup(&i), cf.contains(&i));
+            let bytes = i.to_le_bytes();
+            assert_eq!(
+                cf.lookup_stateless(&bytes, murmur3_x86_64bit),
+                cf.contains_stateless(&bytes, murmur3_x86_64bit)
+            );
+        }
+    }
+
+    #[test]
+    fn take_succeeds_once() {
+        // Lightly loaded, so a false positive doesn't let an item be taken twice
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        for i in 0..100u64 {
+            assert!(cf.insert(&i).is_ok());
+            assert!(cf
+                .insert_stateless(&(i + 10_000).to_le_bytes(), murmur3_x86_64bit)
+                .is_ok());
+        }
+        for i in 0..100u64 {
+            assert!(cf.take(&i));
+            assert!(!cf.take(&i));
+            let bytes = (i + 10_000).to_le_bytes();
+            assert!(cf.take_stateless(&bytes, murmur3_x86_64bit));
+            assert!(!cf.take_stateless(&bytes, murmur3_x86_64bit));
+        }
+        assert!(cf.is_empty());
+        // Each copy of a duplicate is taken separately
+        assert!(cf.insert(&"dup").is_ok());
+        assert!(cf.insert(&"dup").is_ok());
+        assert!(cf.take(&"dup") && cf.take(&"dup"));
+        assert!(!cf.take(&"dup"));
+    }
+
+    #[test]
+    fn delete_item() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        let item = [1u8, 2, 3, 4, 5];
+        let r = cf.insert(&item);
+        assert!(r.is_ok());
+        let is_found = cf.contains(&item);
+        assert!(is_found);
+        let d = cf.delete(&item);
+        assert!(d.is_ok());
+        // Check that the item is no longer present
+        assert!(!cf.contains(&item));
+    }
+
+    #[test]
+    fn resize_from_items_up_and_down() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(512, false);
+        let mut cf = filter.unwrap();
+        let items: Vec<u64> = (0..300).collect();
+        assert!(cf.resize_from_items(4096, items.iter()).is_ok());
+        assert_eq!(cf.capacity(), 4096);
+        assert!(items.iter().all(|i| cf.contains(i)));
+        assert!(cf.resize_from_items(512, items.iter()).is_ok());
+        assert_eq!(cf.capacity(), 512);
+        assert!(items.iter().all(|i| cf.contains(i)));
+        // Too small: the filter keeps its old contents
+        assert_eq!(
+            cf.resize_from_items(64, items.iter()),
+            Err(CuckooFilterError::OutOfSpace)
+        );
+        assert_eq!(cf.capacity(), 512);
+        assert!(items.iter().all(|i| cf.contains(i)));
+    }
+
+    #[test]
+    fn insert_dedup_skips_duplicates() {
+        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
+        let mut cf = filter.unwrap();
+        assert!(cf.insert(&"first").is_ok());
+        let before = occupied_slots(&cf);
+        for _ in 0..100 {
+            assert!(cf.insert_dedup(&"repeated").is_ok());
+        }
+        assert_eq!(occupied_slots(&cf), before + 1);
+        assert!(cf.contains(&"repeated"));
+    }
+
+    #[test]
+    fn drain_into_moves_everything() {
+        let mut source = CuckooFilter::<Murmur3Hasher>::new(256, false).unwrap();
+        let mut dest = CuckooFilter::<Murmur3Hasher>::new(256, false).unwrap();
+        let moved: Vec<u64> = (0..100).collect();
+        let kept: Vec<u64> = (100..150).collect();
+        for i in moved.iter() {
+            assert!(source.insert(i).is_ok());
+        }
+        for i in kept.iter() {
+            assert!(dest.insert(i).is_ok());
+        }
+        assert!(source.drain_into(&mut dest).is_ok());
+        assert_eq!(occupied_slots(&source), 0);
+        assert!(!source.is_full());
+        assert_eq!(occupied_slots(&dest), 150);
+        assert!(moved.iter().chain(kept.iter()).all(|i| dest.contains(i)));
+        // Different sizes can't be drained into each other
+        let mut other = CuckooFilter::<Murmur3Hasher>::new(512, false).unwrap();
+        assert_eq!(
+            dest.drain_into(&mut other),
+            Err(CuckooFilterError::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn union_keeps_items_from_both() {
+        let mut ours = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let mut theirs = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..300u64 {
+            assert!(ours.insert(&i).is_ok());
+        }
+        for i in 300..600u64 {
+            assert!(theirs.insert(&i).is_ok());
+        }
+        assert!(ours.union(&theirs).is_ok());
+        assert_eq!(occupied_slots(&ours), 600);
+        assert!((0..600u64).all(|i| ours.contains(&i)));
+        // The source is left alone
+        assert_eq!(occupied_slots(&theirs), 300);
+        // Different sizes can't be merged
+        let other = CuckooFilter::<Murmur3Hasher>::new(512, false).unwrap();
+        assert_eq!(ours.union(&other), Err(CuckooFilterError::LengthMismatch));
+        // Merging into a filter without room runs out of space
+        let mut small = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        let mut also_small = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        for i in 0..50u64 {
+            assert!(small.insert(&i).is_ok());
+            assert!(also_small.insert(&(i + 1000)).is_ok());
+        }
+        assert_eq!(small.union(&also_small), Err(CuckooFilterError::OutOfSpace));
+    }
+
+    #[test]
+    fn estimate_difference_of_superset() {
+        let mut superset = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let mut subset = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..300u64 {
+            assert!(superset.insert(&i).is_ok());
+            if i < 200 {
+                assert!(subset.insert(&i).is_ok());
+            }
+        }
+        let difference = superset.estimate_difference(&subset).unwrap();
+        // Collisions can only hide extra items here (there are no duplicates), so expect a little under 100
+        assert!((90..=100).contains(&difference), "{difference}");
+        assert_eq!(subset.estimate_difference(&superset), Ok(0));
+    }
+
+    #[test]
+    fn estimate_intersection_of_overlapping_sets() {
+        let mut left = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let mut right = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let item = |i: u32| std::format!("customer-{i}");
+        for i in 0..1500 {
+            assert!(left.insert(&item(i)).is_ok());
+        }
+        for i in 1000..2500 {
+            assert!(right.insert(&item(i)).is_ok());
+        }
+        // 500 shared, plus about 1000 * 1.1% accidental matches before the correction
+        let raw_matches = left.len() - left.estimate_difference(&right).unwrap();
+        assert!(raw_matches > 500, "{raw_matches}");
+        for estimate in [
+            left.estimate_intersection(&right).unwrap(),
+            right.estimate_intersection(&left).unwrap(),
+        ] {
+            assert!((490..=510).contains(&estimate), "{estimate}");
+            assert!(estimate.abs_diff(500) <= raw_matches.abs_diff(500));
+        }
+        let empty = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        assert_eq!(left.estimate_intersection(&empty), Ok(0));
+        assert_eq!(left.estimate_intersection(&left), Ok(1500));
+        let smaller = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert_eq!(
+            left.estimate_intersection(&smaller),
+            Err(CuckooFilterError::LengthMismatch)
+        );
+    }
+
+    // len() is stale after raw_buckets_mut, which mustn't underflow the estimate
+    #[test]
+    fn estimate_intersection_with_stale_len() {
+        let mut edited = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        edited.raw_buckets_mut()[0] = [7, 9, 0, 0];
+        assert_eq!(edited.len(), 0);
+        let mut copy = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        copy.raw_buckets_mut()[0] = [7, 9, 0, 0];
+        copy.recompute_metadata();
+        let empty = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        assert_eq!(edited.estimate_intersection(&empty), Ok(0));
+        assert_eq!(edited.estimate_intersection(&copy), Ok(2));
+    }
+
+    #[test]
+    fn build_from_hashes() {
+        let hashes: Vec<u64> = (0..500u64)
+            .map(|i| murmur3_x86_64bit(&i.to_le_bytes()))
+            .collect();
+        let mut cf =
+            CuckooFilter::<Murmur3Hasher>::from_hashes(1024, hashes.iter().copied()).unwrap();
+        assert!(hashes.iter().all(|h| cf.lookup_hash(*h)));
+        // Hashes line up with the stateless path
+        assert!(cf.contains_stateless(&7u64.to_le_bytes(), murmur3_x86_64bit));
+        assert!(cf.delete_hash(hashes[0]).is_ok());
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_hashes(16, hashes.iter().copied()).unwrap_err(),
+            CuckooFilterError::OutOfSpace
+        );
+    }
+
+    #[test]
+    fn measure_fp_rate_matches_theory() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        for i in 0..2500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let negatives: Vec<u64> = (1_000_000..1_100_000).collect();
+        let empirical = cf.measure_fp_rate(&negatives);
+        // Standard bound: each of the 2 * 4 slots we compare against is occupied with probability `load`, and matches with probability 1/255
+        let load = 2500.0 / 4096.0;
+        let analytic = 1.0 - (1.0 - 1.0 / 255.0f64).powf(2.0 * 4.0 * load);
+        println!("empirical {empirical}, analytic {analytic}");
+        assert!(empirical > analytic * 0.5 && empirical < analytic * 1.5);
+    }
+
+    #[test]
+    fn false_positive_rate_estimate() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        assert_eq!(cf.false_positive_rate(), 0.0);
+        for i in 0..2048u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let estimate = cf.false_positive_rate();
+        let negatives: Vec<u64> = (1_000_000..1_100_000).collect();
+        let empirical = cf.measure_fp_rate(&negatives);
+        assert!(
+            empirical > estimate * 0.75 && empirical < estimate * 1.25,
+            "empirical {empirical}, estimate {estimate}"
+        );
+        // Fuller means more false positives, and bigger buckets compare against more slots
+        for i in 2048..3500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!(cf.false_positive_rate() > estimate);
+        let mut wide = CuckooFilter::<Murmur3Hasher, 8>::new(4096, false).unwrap();
+        for i in 0..2048u64 {
+            assert!(wide.insert(&i).is_ok());
+        }
+        assert!(wide.false_positive_rate() > estimate);
+    }
+
+    #[test]
+    fn try_insert_unique_outcomes() {
+        // A single bucket, so both candidates are the same 4 slots
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4, false).unwrap();
+        assert_eq!(cf.try_insert_unique(&0u64), Ok(true));
+        assert_eq!(cf.try_insert_unique(&0u64), Ok(false));
+        let mut items = 1u64..;
+        while occupied_slots(&cf) < 4 {
+            let _ = cf.try_insert_unique(&items.next().unwrap());
+        }
+        // Find something whose fingerprint isn't already there: with no free slots, it has nowhere to go
+        let newcomer = items.find(|i| !cf.contains(i)).unwrap();
+        assert_eq!(
+            cf.try_insert_unique(&newcomer),
+            Err(CuckooFilterError::OutOfSpace)
+        );
+        // Nothing was evicted into the cache
+        assert!(!cf.is_full());
+    }
+
+    #[test]
+    fn stats_across_fill() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert_eq!(cf.stats(), FilterStats::default());
+        // Plenty of room, so nothing needs kicking
+        for i in 0..100u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let light = cf.stats();
+        assert_eq!(light.total_inserts, 100);
+        assert_eq!(light.total_evictions, 0);
+        assert_eq!(light.max_eviction_chain, 0);
+        let mut i = 100u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        let full = cf.stats();
+        assert_eq!(full.total_inserts as u64, i + 1);
+        assert!(full.total_evictions > 0);
+        assert!(full.total_swaps > full.total_evictions);
+        // The chains that gave up ran all the way to the kick limit
+        assert_eq!(full.max_eviction_chain, MAX_EVICTIONS);
+        assert_eq!(full.items_in_cache, EVICTION_CACHE_CAPACITY);
+        #[cfg(feature = "diagnostics")]
+        {
+            assert_eq!(full.total_inserts, cf.eviction_counts.len());
+            assert_eq!(
+                full.total_swaps,
+                cf.swap_counts
+                    .iter()
+                    .map(|&swaps| swaps as usize)
+                    .sum::<usize>()
+            );
+        }
+        // Refused outright, so not counted
+        assert!(cf.insert(&i).is_err());
+        assert_eq!(cf.stats(), full);
+        cf.reset_stats();
+        assert_eq!(
+            cf.stats(),
+            FilterStats {
+                items_in_cache: EVICTION_CACHE_CAPACITY,
+                ..FilterStats::default()
+            }
+        );
+    }
+
+    #[test]
+    fn reset_stats_keeps_contents() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..20u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        #[cfg(feature = "diagnostics")]
+        assert_eq!(cf.eviction_counts.len(), 20);
+        cf.reset_stats();
+        #[cfg(feature = "diagnostics")]
+        {
+            assert!(cf.eviction_counts.is_empty());
+            assert!(cf.swap_counts.is_empty());
+            assert!(cf.data_trace.is_empty());
+        }
+        assert!((0..20u64).all(|i| cf.contains(&i)));
+        for i in 20..25u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Only the post-reset inserts show up
+        #[cfg(feature = "diagnostics")]
+        {
+            assert_eq!(cf.eviction_counts.len(), 5);
+            assert_eq!(cf.swap_counts.len(), 5);
+            assert_eq!(cf.data_trace.len(), 5);
+        }
+    }
+
+    #[test]
+    fn batch_stateless() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let owned: Vec<[u8; 8]> = (0..50u64).map(|i| i.to_le_bytes()).collect();
+        let items: Vec<&[u8]> = owned.iter().map(|i| i.as_slice()).collect();
+        let inserted = cf.insert_all_stateless(&items, murmur3_x86_64bit);
+        assert_eq!(inserted.len(), 50);
+        assert!(inserted.iter().all(|r| r.is_ok()));
+        assert!(cf
+            .lookup_all_stateless(&items, murmur3_x86_64bit)
+            .iter()
+            .all(|found| *found));
+        let deleted = cf.delete_all_stateless(&items[..10], murmur3_x86_64bit);
+        assert!(deleted.iter().all(|r| r.is_ok()));
+        let found = cf.lookup_all_stateless(&items, murmur3_x86_64bit);
+        assert!(found[..10].iter().all(|found| !*found));
+        assert!(found[10..].iter().all(|found| *found));
+    }
+
+    #[test]
+    fn batch_insert_reports_saturation() {
+        let items: Vec<u64> = (0..200).collect();
+        // Find where one-at-a-time inserts fill the filter
+        let mut reference = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let saturated_at = items
+            .iter()
+            .position(|i| reference.insert(i).is_err())
+            .unwrap();
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let results = cf.insert_all(&items);
+        assert_eq!(results.len(), items.len());
+        assert!(results[..saturated_at].iter().all(|r| r.is_ok()));
+        assert!(results[saturated_at..]
+            .iter()
+            .all(|r| *r == Err(CuckooFilterError::OutOfSpace)));
+        assert_eq!(cf.data, reference.data);
+        assert!(items[..=saturated_at].iter().all(|i| cf.contains(i)));
+        // Same for the stateless version
+        let owned: Vec<[u8; 8]> = items.iter().map(|i| i.to_le_bytes()).collect();
+        let byte_items: Vec<&[u8]> = owned.iter().map(|i| i.as_slice()).collect();
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let results = cf.insert_all_stateless(&byte_items, murmur3_x86_64bit);
+        let first_failure = results.iter().position(|r| r.is_err()).unwrap();
+        assert!(cf.is_full());
+        assert!(results[first_failure..].iter().all(|r| r.is_err()));
+    }
+
+    #[test]
+    fn lookup_all_matches_lookup() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(1024, 7).unwrap();
+        for i in 0..500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Not a multiple of the prefetch group, so the last group is partial
+        let queries: Vec<u64> = (250..1037).collect();
+        let expected: Vec<bool> = queries.iter().map(|q| cf.contains(q)).collect();
+        assert_eq!(cf.lookup_all(&queries), expected);
+        assert!(cf.lookup_all::<u64>(&[]).is_empty());
+    }
+
+    #[test]
+    fn occupancy_histogram_shifts_as_filter_fills() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        assert_eq!(cf.occupancy_histogram(), vec![1024, 0, 0, 0, 0]);
+        for i in 0..200u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let light = cf.occupancy_histogram();
+        assert_eq!(light.len(), BUCKET_SIZE + 1);
+        assert_eq!(light.iter().sum::<usize>(), 1024);
+        // Every fingerprint is counted once
+        let weighted: usize = light.iter().enumerate().map(|(k, &count)| k * count).sum();
+        assert_eq!(weighted, 200);
+        assert!(light[0] > 1024 * 3 / 4, "{light:?}");
+        let mut i = 200u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        let full = cf.occupancy_histogram();
+        assert!(full[BUCKET_SIZE] > 1024 * 3 / 4, "{full:?}");
+        assert!(full[0] < light[0] / 100, "{full:?}");
+        // Other bucket sizes get one entry per possible count
+        let wide = CuckooFilter::<Murmur3Hasher, 8>::new(128, false).unwrap();
+        assert_eq!(wide.occupancy_histogram(), vec![16, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn fingerprint_histogram_is_flat() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(16384, false).unwrap();
+        const ITEMS: u64 = 10_000;
+        for i in 0..ITEMS {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let histogram = cf.fingerprint_histogram();
+        assert_eq!(histogram[0], 0);
+        assert_eq!(histogram.iter().sum::<u32>() as u64, ITEMS);
+        // Chi-squared against a uniform spread over 1-255 (254 degrees of freedom, so expect roughly 254 plus the bump at 1)
+        let expected = ITEMS as f64 / 255.0;
+        let chi_squared: f64 = histogram[1..]
+            .iter()
+            .map(|observed| (*observed as f64 - expected).powi(2) / expected)
+            .sum();
+        println!("chi squared: {chi_squared}");
+        assert!(chi_squared < 2.0 * 254.0);
+    }
+
+    // Keeps only the low 32 bits of the digest, so every fingerprint is the same
+    #[cfg(feature = "std")]
+    #[derive(Default)]
+    struct LowBitsHasher(Murmur3Hasher);
+
+    #[cfg(feature = "std")]
+    impl Hasher for LowBitsHasher {
+        fn finish(&self) -> u64 {
+            self.0.finish() & 0xffff_ffff
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn fingerprint_entropy_flags_bad_hasher() {
+        let samples: Vec<u64> = (0..20_000).collect();
+        assert!(CuckooFilter::<Murmur3Hasher>::fingerprint_entropy(&samples) > 7.9);
+        assert_eq!(
+            CuckooFilter::<LowBitsHasher>::fingerprint_entropy(&samples),
+            0.0
+        );
+    }
+
+    #[test]
+    fn clear_empties_filter() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..200u64 {
+            let _ = cf.insert(&i);
+        }
+        assert!(cf.is_full());
+        let allocation = cf.data.as_ptr();
+        cf.clear();
+        assert_eq!(cf.data.as_ptr(), allocation);
+        assert_eq!(cf.capacity(), 128);
+        assert_eq!(occupied_slots(&cf), 0);
+        assert!(!cf.is_full());
+        #[cfg(feature = "diagnostics")]
+        assert!(cf.data_trace.is_empty());
+        // Previous items are gone (apart from the occasional false positive) ...
+        assert!((0..100u64).filter(|i| cf.contains(i)).count() < 5);
+        // ... and the filter takes new ones
+        for i in 1000..1100u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((1000..1100u64).all(|i| cf.contains(&i)));
+    }
+
+    fn exercise_bucket_size<const B: usize>() {
+        let mut cf = CuckooFilter::<Murmur3Hasher, B>::new(1024, false).unwrap();
+        assert_eq!(cf.bucket_size(), B);
+        assert_eq!(cf.capacity(), 1024);
+        for i in 0..700u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((0..700u64).all(|i| cf.contains(&i)));
+        for i in 0..350u64 {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert!((350..700u64).all(|i| cf.contains(&i)));
+        assert_eq!(cf.len(), 350);
+        // Fill until the eviction cache is used, and check nothing was lost along the way
+        let mut i = 700u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!((350..=i).all(|i| cf.contains(&i)));
+        let restored = CuckooFilter::<Murmur3Hasher, B>::from_bytes(&cf.to_bytes()).unwrap();
+        assert_eq!(restored.data, cf.data);
+        // Clearing the eviction cache reports the slot one past the end of the bucket
+        let (index, fingerprint) = cf.eviction_cache.victims()[0];
+        let alternate = cf.bucket_from_evicted(index, fingerprint);
+        assert_eq!(
+            cf.internal_delete(index, alternate, fingerprint),
+            Ok((index, B))
+        );
+    }
+
+    #[test]
+    fn other_bucket_sizes() {
+        exercise_bucket_size::<2>();
+        exercise_bucket_size::<8>();
+        // A filter only reads back with the bucket size it was written with
+        let wide = CuckooFilter::<Murmur3Hasher, 8>::new(1024, false).unwrap();
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&wide.to_bytes()).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+    }
+
+    #[test]
+    fn capacity_and_load_factor() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        assert_eq!(cf.capacity(), 128);
+        assert_eq!(cf.load_factor(), 0.0);
+        for i in 0..96u64 {
+            assert!(cf.insert(&i).is_ok());
+            assert_eq!(cf.load_factor(), (i + 1) as f32 / 128.0);
+        }
+        assert_eq!(cf.load_factor(), 0.75);
+        // Rounding up to a power of two can give more room than asked for
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::new(200, false)
+                .unwrap()
+                .capacity(),
+            256
+        );
+    }
+
+    #[test]
+    fn equality_is_physical() {
+        let items: Vec<u64> = (0..100).collect();
+        let mut forward = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let mut again = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let mut backward = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in items.iter() {
+            assert!(forward.insert(i).is_ok());
+            assert!(again.insert(i).is_ok());
+        }
+        for i in items.iter().rev() {
+            assert!(backward.insert(i).is_ok());
+        }
+        assert_eq!(forward, again);
+        // Same set, different slots
+        assert_ne!(forward, backward);
+        assert!(items
+            .iter()
+            .all(|i| backward.contains(i) && forward.contains(i)));
+        // The eviction cache and seed count, bookkeeping doesn't
+        again.eviction_cache.push(3, 9);
+        assert_ne!(forward, again);
+        again.eviction_cache.reset();
+        again.reset_stats();
+        again.set_max_bucket_collisions(Some(2));
+        assert_eq!(forward, again);
+        again.seed = 7;
+        assert_ne!(forward, again);
+        assert_ne!(
+            forward,
+            CuckooFilter::<Murmur3Hasher>::new(256, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn len_tracks_contents() {
+        let scanned = |cf: &CuckooFilter<Murmur3Hasher>| cf.stored_fingerprints().count();
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        assert!(cf.is_empty());
+        for i in 0..50u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert_eq!(cf.len(), 50);
+        for i in 0..10u64 {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert_eq!(
+            cf.delete(&1_000_000u64),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+        assert_eq!(cf.len(), 40);
+        // Filling up: the items parked in the eviction cache count, the ones turned away after that don't
+        let mut i = 50u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!(cf.is_full());
+        assert_eq!(cf.len(), scanned(&cf));
+        let full = cf.len();
+        assert_eq!(cf.insert(&(i + 1)), Err(CuckooFilterError::OutOfSpace));
+        assert_eq!(cf.len(), full);
+        // Taking the victim out of the cache, or deleting it, removes it from the count
+        assert!(cf.delete(&i).is_ok());
+        assert_eq!(cf.len(), full - 1);
+        assert_eq!(cf.len(), scanned(&cf));
+        assert!(cf.lazy_delete(&20u64).is_ok());
+        cf.flush_deletes();
+        assert_eq!(cf.len(), scanned(&cf));
+        // Bulk operations keep it in step too. Make some room first, so growing has somewhere to put the cached fingerprints
+        for i in 50..80u64 {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert_eq!(cf.len(), scanned(&cf));
+        assert_eq!(cf.grow(false), Ok(0));
+        assert_eq!(cf.len(), scanned(&cf));
+        cf.shrink_to_fit(0);
+        assert_eq!(cf.len(), scanned(&cf));
+        let mut other = cf.empty_like(cf.capacity()).unwrap();
+        assert!(cf.drain_into(&mut other).is_ok());
+        assert!(cf.is_empty());
+        assert_eq!(other.len(), scanned(&other));
+        assert!(cf.union(&other).is_ok());
+        assert_eq!(cf.len(), other.len());
+        cf.clear();
+        assert!(cf.is_empty());
+    }
+
+    #[test]
+    fn clear_and_resize_empties_filter() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..200u64 {
+            let _ = cf.insert(&i);
+        }
+        assert!(cf.is_full());
+        assert!(cf.clear_and_resize(4096).is_ok());
+        assert_eq!(cf.capacity(), 4096);
+        assert_eq!(occupied_slots(&cf), 0);
+        assert!(!cf.is_full());
+        #[cfg(feature = "diagnostics")]
+        assert!(cf.eviction_counts.is_empty());
+        for i in 0..1000u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((0..1000u64).all(|i| cf.contains(&i)));
+        // Same size reuses the allocation
+        let allocation = cf.data.as_ptr();
+        assert!(cf.clear_and_resize(4096).is_ok());
+        assert_eq!(cf.data.as_ptr(), allocation);
+        assert_eq!(occupied_slots(&cf), 0);
+        assert_eq!(
+            cf.clear_and_resize(ITEM_LIMIT + 1),
+            Err(CuckooFilterError::CapacityExceedsItemLimit)
+        );
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn simd_bucket_scan_matches_scalar() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        for i in 0..3500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Every stored bucket against every fingerprint, so matches in each slot (and repeats) are covered
+        for bucket in &cf.data {
+            for fingerprint in 0..=Fingerprint::MAX {
+                assert_eq!(
+                    bucket_contains_sse2(bucket, fingerprint),
+                    bucket_contains_scalar(bucket, fingerprint)
+                );
+            }
+        }
+        assert!(bucket_contains_sse2(&[0, 0, 0, 9], 9));
+        assert!(!bucket_contains_sse2(&[1, 2, 3, 4], 0));
+        assert!((0..3500u64).all(|i| cf.contains(&i)));
+    }
+
+    #[test]
+    fn seeds_change_bucket_placement() {
+        let unseeded = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let seed_1 = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 1).unwrap();
+        let seed_2 = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 2).unwrap();
+        let item = "the cat says meow";
+        assert_ne!(seed_1.peek_buckets(&item), seed_2.peek_buckets(&item));
+        assert_ne!(unseeded.peek_buckets(&item), seed_1.peek_buckets(&item));
+        // Rotating the seed moves nearly every item
+        let moved = (0..1000u64)
+            .filter(|i| seed_1.peek_buckets(i) != seed_2.peek_buckets(i))
+            .count();
+        assert!(moved > 990, "{moved}");
+        // The same seed always agrees with itself
+        let again = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 1).unwrap();
+        assert!((0..1000u64).all(|i| seed_1.peek_buckets(&i) == again.peek_buckets(&i)));
+    }
+
+    // Murmur3 takes the seed into its own state, which places items differently from writing it in ahead of them
+    #[test]
+    fn seeded_hasher_uses_native_seed() {
+        let mut native = CuckooFilter::<Murmur3Hasher>::new_with_seeded_hasher(4096, 7).unwrap();
+        let written = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 7).unwrap();
+        let moved = (0..1000u64)
+            .filter(|i| native.peek_buckets(i) != written.peek_buckets(i))
+            .count();
+        assert!(moved > 990, "{moved}");
+        assert_ne!(native, written);
+        let mut hasher = Murmur3Hasher::with_seed(7);
+        "the cat says meow".hash(&mut hasher);
+        assert_eq!(
+            native.insert_returning_hash(&"the cat says meow"),
+            Ok(hasher.finish())
+        );
+        // A seed of 0 leaves Murmur3 unseeded
+        let zero = CuckooFilter::<Murmur3Hasher>::new_with_seeded_hasher(4096, 0).unwrap();
+        let unseeded = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        assert!((0..100u64).all(|i| zero.peek_buckets(&i) == unseeded.peek_buckets(&i)));
+        // Resized copies seed the same way
+        for i in 0..100u64 {
+            assert!(native.insert(&i).is_ok());
+        }
+        assert_eq!(native.grow(true), Ok(0));
+        assert!((0..100u64).all(|i| native.contains(&i)));
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&native.to_bytes()).unwrap();
+        assert!(!(0..100u64).all(|i| restored.contains(&i)));
+        let mut restored = restored;
+        restored.set_seeded_hasher();
+        assert_eq!(restored, native);
+        assert!((0..100u64).all(|i| restored.contains(&i)));
+    }
+
+    #[test]
+    fn resizing_keeps_seed() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 7).unwrap();
+        for i in 0..100u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        cf.shrink_to_fit(256);
+        assert_eq!(cf.seed(), 7);
+        assert!((0..100u64).all(|i| cf.contains(&i)));
+        assert!(cf.resize_from_items(1024, 0..100u64).is_ok());
+        assert_eq!(cf.seed(), 7);
+        assert!((0..100u64).all(|i| cf.contains(&i)));
+    }
+
+    #[test]
+    fn grow_keeps_items() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(512, 7).unwrap();
+        for i in 0..480u64 {
+            let _ = cf.insert(&i);
+        }
+        let stored: Vec<u64> = (0..480u64).filter(|i| cf.contains(i)).collect();
+        assert_eq!(cf.grow(true), Ok(0));
+        assert_eq!(cf.capacity(), 1024);
+        assert_eq!(cf.seed(), 7);
+        assert!(stored.iter().all(|i| cf.contains(i)));
+    }
+
+    #[test]
+    fn grow_unblocks_full_filter() {
+        for seed in 0..5 {
+            let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, seed).unwrap();
+            let mut i = 0u64;
+            while cf.insert(&i).is_ok() {
+                i += 1;
+            }
+            assert!(cf.is_full());
+            assert_eq!(cf.grow(true), Ok(0));
+            assert!(!cf.is_full(), "seed {seed}");
+            // Everything that went in is still there, including the item that hit the full filter
+            assert!((0..=i).all(|i| cf.contains(&i)));
+            assert!(cf.insert(&u64::MAX).is_ok());
+        }
+    }
+
+    #[test]
+    fn grow_reports_dropped_victim() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        // Every slot taken and a full cache: after splitting, every bucket is still full, so the victims' copies can only go in the new cache, which holds half of them
+        for bucket in cf.data.iter_mut() {
+            *bucket = [1, 2, 3, 4];
+        }
+        for bucket_index in 0..EVICTION_CACHE_CAPACITY as BucketIndex {
+            cf.eviction_cache.push(bucket_index, 5);
+        }
+        cf.recount();
+        assert_eq!(cf.grow(true), Err(CuckooFilterError::ResizeLoss));
+        assert_eq!(cf.capacity(), 64);
+        assert_eq!(cf.grow(false), Ok(EVICTION_CACHE_CAPACITY));
+        assert_eq!(cf.capacity(), 128);
+        assert!(cf.is_full());
+    }
+
+    #[test]
+    fn clone_shrunk_keeps_most_items() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 7).unwrap();
+        for i in 0..1000u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // 1000 items into 1024 slots: some can't fit
+        let copy = cf.clone_shrunk(1024).unwrap();
+        assert_eq!(copy.capacity(), 1024);
+        assert_eq!(copy.seed(), 7);
+        let found = (0..1000u64).filter(|i| copy.contains(i)).count();
+        assert!(found >= 900, "only {found} items survived");
+        // The source is untouched
+        assert!((0..1000u64).all(|i| cf.contains(&i)));
+        assert_eq!(cf.clone_shrunk(8192).unwrap().capacity(), 4096);
+    }
+
+    #[test]
+    fn insert_if_new_outcomes() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        assert_eq!(cf.insert_if_new(&0u64), InsertIfNewResult::Inserted);
+        assert_eq!(cf.insert_if_new(&0u64), InsertIfNewResult::AlreadyPresent);
+        // Keep admitting new items until one pushes something out: it takes the victim's place, so the count doesn't change
+        let newest = (1..1000u64)
+            .find(|i| {
+                let before = cf.len();
+                let displaced = matches!(cf.insert_if_new(i), InsertIfNewResult::Displaced(_));
+                assert!(!displaced || cf.len() == before);
+                displaced
+            })
+            .unwrap();
+        assert!(!cf.is_full());
+        assert!(cf.contains(&newest));
+        // A full filter still admits new items
+        assert!((5000..10_000u64).any(|i| cf.insert(&i).is_err()));
+        assert!(cf.is_full());
+        assert!(matches!(
+            cf.insert_if_new(&"brand new"),
+            InsertIfNewResult::Displaced(_)
+        ));
+        assert!(cf.contains(&"brand new"));
+    }
+
+    // A lazily deleted item reads as absent but still fills its collision cap, which mustn't cost a full filter its victim
+    #[test]
+    fn insert_if_new_refused_by_collision_cap() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        cf.set_max_bucket_collisions(Some(1));
+        assert_eq!(cf.insert_if_new(&"cat"), InsertIfNewResult::Inserted);
+        assert!(cf.lazy_delete(&"cat").is_ok());
+        let mut i = 0u64;
+        while !cf.is_full() {
+            let _ = cf.insert(&i);
+            i += 1;
+        }
+        let before = cf.len();
+        assert_eq!(cf.insert_if_new(&"cat"), InsertIfNewResult::Refused);
+        assert!(!cf.contains(&"cat"));
+        assert!(cf.is_full());
+        assert_eq!(cf.len(), before);
+    }
+
+    #[test]
+    fn items_never_get_the_empty_fingerprint() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        for _ in 0..20_000 {
+            let item: u64 = rng.gen();
+            assert_ne!(cf.buckets_from_item(&item).2, 0);
+        }
+        // Digests whose fingerprint bits are all zero get bumped
+        assert_eq!(cf.digest_to_buckets(0x0000_0000_ffff_ffff).2, 1);
+        assert!(cf.verify_no_zero_fingerprints());
+        cf.eviction_cache.push(0, 0);
+        assert!(!cf.verify_no_zero_fingerprints());
+    }
+
+    #[test]
+    fn sample_ring_keeps_most_recent() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert!(cf.recent_samples().is_empty());
+        for i in 0..100u64 {
+            assert!(cf.insert(&i).is_ok());
+            cf.record_sample();
+            let expected = (i as usize + 1).min(SAMPLE_HISTORY);
+            assert_eq!(cf.recent_samples().len(), expected);
+        }
+        // Oldest first: samples 37 through 100
+        let samples = cf.recent_samples();
+        assert!(samples
+            .iter()
+            .zip(37..=100usize)
+            .all(|(sample, len)| *sample == (len, len as f32 / 1024.0)));
+    }
+
+    #[test]
+    fn insert_from_byte_iter_matches_slice() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        // Short keys stay on the stack; the long one spills over
+        for length in [0usize, 1, 255, 256, 257, 1000] {
+            let key: Vec<u8> = (0..length).map(|i| (i * 7) as u8).collect();
+            assert!(cf
+                .insert_from_byte_iter(key.iter().copied(), murmur3_x86_64bit)
+                .is_ok());
+            assert!(cf.delete_stateless(&key, murmur3_x86_64bit).is_ok());
+        }
+        assert!(cf.is_empty());
+    }
+
+    #[test]
+    fn delete_locating_reports_cleared_slot() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        let mut positions = Vec::new();
+        for i in 0..30u64 {
+            positions.push(cf.insert_locating(&i).unwrap());
+        }
+        // Nothing was inserted after the last item, so its position is still accurate
+        let (bucket_index, slot) = positions[29];
+        assert_ne!(cf.data[bucket_index as usize][slot], 0);
+        assert_eq!(cf.delete_locating(&29u64), Ok((bucket_index, slot)));
+        assert_eq!(cf.data[bucket_index as usize][slot], 0);
+        assert_eq!(
+            cf.delete_locating(&29u64),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+        // An item sitting in the eviction cache
+        let (candidate_1, _, fingerprint) = cf.buckets_from_item(&"cached");
+        cf.eviction_cache.push(candidate_1, fingerprint);
+        assert_eq!(
+            cf.delete_locating(&"cached"),
+            Ok((candidate_1, EVICTION_CACHE_SLOT))
+        );
+        assert!(!cf.is_full());
+    }
+
+    #[test]
+    fn try_from_iter_rejects_overflow() {
+        let cf = CuckooFilter::<Murmur3Hasher>::try_from_iter(0..100u64, 128).unwrap();
+        assert!((0..100u64).all(|i| cf.contains(&i)));
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::try_from_iter(0..200u64, 128).unwrap_err(),
+            CuckooFilterError::OutOfSpace
+        );
+    }
+
+    #[test]
+    fn bulk_load_strings() {
+        let words: Vec<String> = (0..200).map(|i| std::format!("word-{i}")).collect();
+        let cf = CuckooFilter::<Murmur3Hasher>::try_from_iter(&words[..100], 128).unwrap();
+        assert!(words[..100].iter().all(|word| cf.contains(word)));
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::try_from_iter(&words, 128).unwrap_err(),
+            CuckooFilterError::OutOfSpace
+        );
+        // Borrowed and owned strings hash the same
+        let mut extended = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        extended.extend(&words[..50]);
+        extended.extend(words[50..100].iter().cloned());
+        assert_eq!(extended, cf);
+        // Everything up to and including the insert that fills the filter lands, the rest is dropped
+        let mut full = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        full.extend(&words);
+        assert!(full.is_full());
+        let mut expected = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let results = expected.insert_all(&words);
+        let landed = results.iter().filter(|result| result.is_ok()).count() + 1;
+        assert_eq!(full, expected);
+        assert_eq!(full.len(), landed);
+        assert!(words[..landed].iter().all(|word| full.contains(word)));
+    }
+
+    #[test]
+    fn membership_is_explicit() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(128, 7).unwrap();
+        assert!(cf.insert(&"cat").is_ok());
+        assert_eq!(cf.membership(&"cat"), Membership::ProbablyPresent);
+        assert_eq!(cf.membership(&"dog"), Membership::DefinitelyAbsent);
+        assert!(cf.delete(&"cat").is_ok());
+        assert_eq!(cf.membership(&"cat"), Membership::DefinitelyAbsent);
+    }
+
+    #[test]
+    fn max_bucket_collisions_rejects_pileups() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        cf.set_max_bucket_collisions(Some(2));
+        assert!(cf.insert(&"cat").is_ok());
+        assert!(cf.insert(&"cat").is_ok());
+        let before = cf.data.clone();
+        assert_eq!(cf.insert(&"cat"), Err(CuckooFilterError::TooManyCollisions));
+        assert_eq!(cf.data, before);
+        // Other items are unaffected, and lifting the limit lets the copy in
+        assert!(cf.insert(&"dog").is_ok());
+        cf.set_max_bucket_collisions(None);
+        assert!(cf.insert(&"cat").is_ok());
+    }
+
+    #[test]
+    fn recompute_after_raw_edits() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        for i in 0..10u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Empty the first occupied bucket by hand
+        let buckets = cf.raw_buckets_mut();
+        let bucket = buckets
+            .iter_mut()
+            .find(|b| b.iter().any(|&fp| fp != 0))
+            .unwrap();
+        let wiped = bucket.iter().filter(|&&fp| fp != 0).count();
+        *bucket = [0; BUCKET_SIZE];
+        cf.recompute_metadata();
+        assert_eq!(cf.len(), 10 - wiped);
+        // A pending delete for something that's been wiped is discarded
+        assert!(cf.lazy_delete(&9u64).is_ok());
+        cf.raw_buckets_mut().fill([0; BUCKET_SIZE]);
+        cf.recompute_metadata();
+        assert!(cf.pending_deletes.is_empty());
+        assert!(cf.is_empty());
+        // A valid cached fingerprint moves into the room made for it; a corrupt one is dropped
+        cf.eviction_cache.push(5, 9);
+        cf.recompute_metadata();
+        assert!(cf.eviction_cache.is_empty());
+        assert_eq!(cf.len(), 1);
+        cf.eviction_cache.push(1000, 9);
+        cf.recompute_metadata();
+        assert!(cf.eviction_cache.is_empty());
+        assert_eq!(cf.len(), 1);
+    }
+
+    #[test]
+    fn shared_lookups_across_threads() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(1024, 7).unwrap();
+        for i in 0..500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let shared = &cf;
+        std::thread::scope(|scope| {
+            let evens = scope.spawn(|| (0..500u64).step_by(2).all(|i| shared.contains(&i)));
+            let odds = scope.spawn(|| (1..500u64).step_by(2).all(|i| shared.contains(&i)));
+            assert!(evens.join().unwrap());
+            assert!(odds.join().unwrap());
+        });
+    }
+
+    // Regression: an item whose fingerprint byte hashes to 0 used to be stored as an empty slot and lost
+    #[test]
+    fn zero_fingerprint_byte_round_trips() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let item = (0..u64::MAX)
+            .find(|i| (cf.digest_of(i) >> 32) as u8 == 0)
+            .unwrap();
+        let (candidate_1, candidate_2, fingerprint) = cf.buckets_from_item(&item);
+        assert_eq!(fingerprint, 1);
+        // The bumped fingerprint still pairs the buckets up both ways
+        assert_eq!(
+            cf.bucket_from_evicted(candidate_1, fingerprint),
+            candidate_2
+        );
+        assert_eq!(
+            cf.bucket_from_evicted(candidate_2, fingerprint),
+            candidate_1
+        );
+        assert!(cf.insert(&item).is_ok());
+        assert!(cf.contains(&item));
+        assert_eq!(cf.len(), 1);
+        assert!(cf.delete(&item).is_ok());
+        assert!(!cf.contains(&item));
+    }
+
+    #[test]
+    fn insert_unique_refuses_duplicates() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(16, false).unwrap();
+        assert!(cf.insert_unique(&0u64).is_ok());
+        assert_eq!(
+            cf.insert_unique(&0u64),
+            Err(CuckooFilterError::ItemAlreadyExists)
+        );
+        // The stateless version agrees with the `Hash` version on what's a duplicate
+        assert!(cf
+            .insert_unique_stateless(b"cat", murmur3_x86_64bit)
+            .is_ok());
+        assert_eq!(
+            cf.insert_unique(&"cat"),
+            Err(CuckooFilterError::ItemAlreadyExists)
+        );
+        // A distinct item that collides with one already stored is refused as well
+        let colliding = (1..u64::MAX).find(|i| cf.contains(i)).unwrap();
+        assert_eq!(
+            cf.insert_unique(&colliding),
+            Err(CuckooFilterError::ItemAlreadyExists)
+        );
+        assert_eq!(cf.len(), 2);
+    }
+
+    #[cfg(debug_assertions)]
+    fn other_hash(bytes: &[u8]) -> u64 {
+        murmur3_x86_64bit(bytes).rotate_left(17)
+    }
+
+    #[test]
+    fn stored_hash_function_accepts_itself() {
+        let mut cf =
+            CuckooFilter::<Murmur3Hasher>::new_with_hasher_fn(128, murmur3_x86_64bit).unwrap();
+        assert!(cf.insert_stateless(b"cat", murmur3_x86_64bit).is_ok());
+        assert!(cf.contains_stateless(b"cat", murmur3_x86_64bit));
+        // Resizing keeps the stored function
+        cf.shrink_to_fit(16);
+        assert!(cf.contains_stateless(b"cat", murmur3_x86_64bit));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "different hash function")]
+    fn stored_hash_function_catches_mismatch() {
+        let mut cf =
+            CuckooFilter::<Murmur3Hasher>::new_with_hasher_fn(128, murmur3_x86_64bit).unwrap();
+        assert!(cf.insert_stateless(b"cat", murmur3_x86_64bit).is_ok());
+        cf.contains_stateless(b"cat", other_hash);
+    }
+
+    #[test]
+    fn block_checksums_pinpoint_changes() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..300u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let before = cf.block_checksums(16);
+        assert_eq!(before.len(), 16);
+        cf.data[37][0] ^= 0x55;
+        let after = cf.block_checksums(16);
+        let changed: Vec<usize> = (0..before.len())
+            .filter(|&i| before[i] != after[i])
+            .collect();
+        assert_eq!(changed, vec![37 / 16]);
+        // A block size that doesn't divide the bucket count leaves a short final block
+        assert_eq!(cf.block_checksums(100).len(), 3);
+    }
+
+    #[test]
+    fn split_mix_is_not_degenerate() {
+        let mut rng = SplitMix64::new(0);
+        let outputs: Vec<u64> = (0..1000).map(|_| rng.next_u64()).collect();
+        let mut distinct = outputs.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), outputs.len());
+        // Every slot gets picked a fair share of the time
+        let mut slot_counts = [0; BUCKET_SIZE];
+        for output in outputs {
+            slot_counts[(output % BUCKET_SIZE as u64) as usize] += 1;
+        }
+        assert!(slot_counts.iter().all(|&count| count > 200));
+        // Same seed, same sequence
+        assert_eq!(SplitMix64::new(5).next_u64(), SplitMix64::new(5).next_u64());
+        assert_ne!(SplitMix64::new(5).next_u64(), SplitMix64::new(6).next_u64());
+    }
+
+    // Picking the slot as `bucket_index % BUCKET_SIZE` always evicts the same slot of a given bucket, and stalls at about 72% full (2937 of 4096 slots). A random choice should get well past that, whichever seed drives it
+    #[test]
+    fn random_eviction_fills_further() {
+        const DETERMINISTIC_FILL: usize = 2937;
+        for seed in [0, 1, 7, 42] {
+            let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, seed).unwrap();
+            let mut inserted = 0;
+            while cf.insert(&inserted).is_ok() {
+                inserted += 1;
             }
+            assert!(
+                inserted as usize > DETERMINISTIC_FILL * 5 / 4,
+                "seed {seed}: {inserted}"
+            );
+            assert!(inserted as f32 / cf.capacity() as f32 > 0.9);
+            assert!((0..=inserted).all(|i| cf.contains(&i)));
         }
-        false
     }
 
-    /// Add item to filter. Returns Err if filter is full, or if item already exists.
-    // pub fn insert_unique(item: &Input) -> Result<(), CuckooFilterOpError> {
-    //     Ok(())
-    // }
+    #[test]
+    #[cfg(target_has_atomic = "ptr")]
+    fn cache_hits_are_counted() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(8, false).unwrap();
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!(cf.is_full());
+        assert_eq!(cf.cache_hit_count(), 0);
+        // The cache holds whichever fingerprints the chains ended with, so force a known item into it
+        let (candidate_1, _, fingerprint) = cf.buckets_from_item(&i);
+        cf.eviction_cache.victims[0] = (candidate_1, fingerprint);
+        assert!(cf.contains(&i));
+        assert_eq!(cf.cache_hit_count(), 1);
+        cf.reset_stats();
+        assert_eq!(cf.cache_hit_count(), 0);
+    }
 
-    /// Check if item is in filter
-    ///
-    /// ```
-    /// use cuckoo_filter::*;
-    ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    ///
-    /// let item = "hello, I am some data";
-    /// let _ = filter.insert(&item);
-    /// let was_found = filter.lookup(&item);
-    /// assert!(was_found);
-    /// ```
-    pub fn lookup<T: Hash>(&mut self, item: &T) -> bool {
-        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
-        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    #[test]
+    fn occupancy_bitmap_matches_len() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert!(cf.is_empty());
+        for i in 0..700u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let bitmap = cf.fingerprint_occupancy_bitmap();
+        assert_eq!(bitmap.len(), cf.capacity() / 8);
+        let popcount: u32 = bitmap.iter().map(|byte| byte.count_ones()).sum();
+        assert_eq!(popcount as usize, cf.len());
+        assert_eq!(cf.len(), 700);
+        // Bits line up with slots
+        for (slot_index, &fingerprint) in cf.data.iter().flatten().enumerate() {
+            assert_eq!(
+                bitmap[slot_index / 8] >> (slot_index % 8) & 1 == 1,
+                fingerprint != 0
+            );
+        }
     }
 
-    /// Check if item is in filter, but use a provided stateless hash function.
-    ///
-    /// ```
-    /// use cuckoo_filter::*;
-    ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    ///
-    /// let item = "hello, I am some data";
-    /// let _ = filter.insert_stateless(&item.as_bytes(), murmur3_x86_64bit);
-    /// let was_found = filter.lookup_stateless(&item.as_bytes(), murmur3_x86_64bit);
-    /// assert!(was_found);
-    /// ```
-    pub fn lookup_stateless(&self, item: &[u8], hash_function: fn(&[u8]) -> u64) -> bool {
-        let (candidate_1, candidate_2, fingerprint) =
-            self.buckets_from_item_stateless(item, hash_function);
-        self.internal_lookup(candidate_1, candidate_2, fingerprint)
+    #[test]
+    fn headroom_pads_capacity() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new_with_headroom(1000, 0.25).unwrap();
+        assert!(cf.capacity() >= 1250);
+        // Padding that lands just past a power of two still rounds up
+        let cf = CuckooFilter::<Murmur3Hasher>::new_with_headroom(1000, 0.025).unwrap();
+        assert!(cf.capacity() >= 1025);
+        let cf = CuckooFilter::<Murmur3Hasher>::new_with_headroom(1024, 0.0).unwrap();
+        assert_eq!(cf.capacity(), 1024);
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::new_with_headroom(ITEM_LIMIT, 0.5).unwrap_err(),
+            CuckooFilterError::CapacityExceedsItemLimit
+        );
     }
 
-    fn internal_delete(
-        &mut self,
-        candidate_1: u32,
-        candidate_2: u32,
-        fingerprint: u8,
-    ) -> Result<(), CuckooFilterError> {
-        // Check cache and clear if found
-        if self.eviction_cache.used
-            && fingerprint == self.eviction_cache.fingerprint
-            && (self.eviction_cache.index == candidate_1
-                || self.eviction_cache.index == candidate_2)
-        {
-            self.eviction_cache.reset();
-            return Ok(());
+    static COUNTED_HASHES: AtomicUsize = AtomicUsize::new(0);
+
+    // Counts how many digests it hands out
+    #[derive(Default)]
+    struct CountingHasher(Murmur3Hasher);
+
+    impl Hasher for CountingHasher {
+        fn finish(&self) -> u64 {
+            COUNTED_HASHES.fetch_add(1, Ordering::Relaxed);
+            self.0.finish()
         }
-        // Check buckets and clear if found
-        for &bucket_index in &[candidate_1, candidate_2] {
-            for entry in &mut self.data[bucket_index as usize] {
-                if *entry == fingerprint {
-                    *entry = 0;
-                    return Ok(());
-                }
-            }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes);
         }
-        Err(CuckooFilterError::ItemDoesNotExist)
     }
 
-    /// Delete an item from the filter
-    ///
-    /// ```
-    /// use cuckoo_filter::*;
-    ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    ///
-    /// let item = "hello, I am some data";
-    /// let _ = filter.insert(&item);
-    /// let was_found = filter.lookup(&item);
-    /// assert!(was_found);
-    ///
-    /// let was_deleted = filter.delete(&item);
-    /// assert!(was_deleted.is_ok());
-    /// ```
-    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
-        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
-        self.internal_delete(candidate_1, candidate_2, fingerprint)
+    #[test]
+    fn lookup_cached_skips_rehashing() {
+        let mut cf = CuckooFilter::<CountingHasher>::new(128, false).unwrap();
+        assert!(cf.insert(&7u64).is_ok());
+        let before = COUNTED_HASHES.load(Ordering::Relaxed);
+        assert!(cf.lookup_cached(&7u64));
+        assert!(cf.lookup_cached(&7u64));
+        assert_eq!(COUNTED_HASHES.load(Ordering::Relaxed), before + 1);
+        // Mutations don't invalidate the buckets, but they do change the answer
+        assert!(cf.delete(&7u64).is_ok());
+        assert!(!cf.lookup_cached(&7u64));
+        // A different item (or type) is hashed afresh
+        assert!(!cf.lookup_cached(&8u64));
+        assert!(!cf.lookup_cached(&7u32));
+        // So is anything after a resize
+        assert!(cf.insert(&7u64).is_ok());
+        assert!(cf.resize_from_items(1024, [7u64].into_iter()).is_ok());
+        assert!(cf.lookup_cached(&7u64));
     }
 
-    /// Delete an item from the filter, using a provided stateless hash function
-    ///
-    /// ```
-    /// use cuckoo_filter::*;
-    ///
-    /// let try_filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-    /// let mut filter = try_filter.unwrap();
-    ///
-    /// let item = "hello, I am some data";
-    /// let _ = filter.insert_stateless(&item.as_bytes(), murmur3_x86_64bit);
-    /// let was_found = filter.lookup_stateless(&item.as_bytes(), murmur3_x86_64bit);
-    /// assert!(was_found);
-    ///
-    /// let was_deleted = filter.delete_stateless(&item.as_bytes(), murmur3_x86_64bit);
-    /// assert!(was_deleted.is_ok());
-    /// ```
-    pub fn delete_stateless(
-        &mut self,
-        item: &[u8],
-        hash_function: fn(&[u8]) -> u64,
-    ) -> Result<(), CuckooFilterError> {
-        let (candidate_1, candidate_2, fingerprint) =
-            self.buckets_from_item_stateless(item, hash_function);
-        self.internal_delete(candidate_1, candidate_2, fingerprint)
+    #[test]
+    fn lookup_cached_rehashes_after_rekey() {
+        let mut cf = CuckooFilter::<crate::SipHasher13>::new_with_keyed_hasher(128, 1).unwrap();
+        assert!(cf.insert(&"x").is_ok());
+        assert!(cf.lookup_cached(&"x"));
+        // Under the new key "x" belongs somewhere else, and the cached buckets must not say otherwise
+        cf.set_hasher_key(2);
+        assert!(!cf.contains(&"x"));
+        assert!(!cf.lookup_cached(&"x"));
     }
-}
 
-/* -------------------- Unit Tests -------------------- */
+    #[test]
+    fn strict_delete_refuses_ambiguity() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let item = (0u64..)
+            .find(|i| {
+                let (candidate_1, candidate_2) = cf.peek_buckets(i);
+                candidate_1 != candidate_2
+            })
+            .unwrap();
+        let (candidate_1, candidate_2, fingerprint) = cf.buckets_from_item(&item);
+        cf.data[candidate_1 as usize][0] = fingerprint;
+        cf.data[candidate_2 as usize][0] = fingerprint;
+        cf.recount();
+        assert_eq!(
+            cf.strict_delete(&item),
+            Err(CuckooFilterError::AmbiguousDelete)
+        );
+        assert_eq!(occupied_slots(&cf), 2);
+        // Once it's down to one copy, it goes through
+        assert!(cf.delete(&item).is_ok());
+        assert!(cf.strict_delete(&item).is_ok());
+        assert_eq!(occupied_slots(&cf), 0);
+        assert_eq!(
+            cf.strict_delete(&item),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{murmur3_x86_64bit, Murmur3Hasher};
-    use rand::{distributions::Uniform, prelude::*};
-    use rand_chacha::ChaCha8Rng;
+    #[test]
+    fn occupancy_heatmap_shows_hot_region() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        // Only items whose first bucket falls in the first eighth of the filter
+        let hot_items: Vec<u64> = (0u64..)
+            .filter(|i| (cf.peek_buckets(i).0 as usize) < cf.num_buckets() / 8)
+            .take(200)
+            .collect();
+        for item in hot_items.iter() {
+            assert!(cf.insert(item).is_ok());
+        }
+        let heatmap = cf.occupancy_heatmap(8);
+        assert_eq!(heatmap.len(), 8);
+        assert!(heatmap[1..].iter().all(|&load| load < heatmap[0]));
+        // Cells are capped at the number of buckets
+        assert_eq!(cf.occupancy_heatmap(100_000).len(), cf.num_buckets());
+        assert!(cf.occupancy_heatmap(0).is_empty());
+    }
 
-    // Utility fns
-    fn get_random_string(rng: &mut ChaCha8Rng, len: usize) -> String {
-        rng.sample_iter::<char, _>(&rand::distributions::Standard)
-            .take(len)
-            .map(char::from)
-            .collect()
+    #[test]
+    fn iter_occupied_visits_every_fingerprint() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        assert_eq!(cf.iter_occupied().count(), 0);
+        // Lightly loaded, so nothing is kicked and every item keeps the slot it was placed in
+        let mut positions = Vec::new();
+        for i in 0..500u64 {
+            positions.push(cf.insert_locating(&i).unwrap());
+        }
+        let occupied: Vec<(BucketIndex, usize, Fingerprint)> = cf.iter_occupied().collect();
+        assert_eq!(occupied.len(), 500);
+        positions.sort();
+        let mut visited: Vec<(BucketIndex, usize)> = occupied
+            .iter()
+            .map(|&(bucket_index, slot, fingerprint)| {
+                assert_eq!(cf.data[bucket_index as usize][slot], fingerprint);
+                (bucket_index, slot)
+            })
+            .collect();
+        // Bucket by bucket, slot by slot
+        assert!(visited.is_sorted());
+        visited.dedup();
+        assert_eq!(visited, positions);
+        // Cached fingerprints come last, in the slot past the end of the bucket
+        cf.eviction_cache.push(7, 9);
+        cf.recount();
+        assert_eq!(cf.iter_occupied().last(), Some((7, EVICTION_CACHE_SLOT, 9)));
+        assert_eq!(cf.iter_occupied().count(), cf.len());
     }
 
     #[test]
-    fn make_filter_normal_conditions() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-        assert!(filter.is_ok());
-        let cf = filter.unwrap();
-        assert_eq!(cf.length_u32, 128 / 4);
-        assert_eq!(128 / 4, cf.data.len() as u32);
+    fn eviction_victim_once_full() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(8, false).unwrap();
+        assert_eq!(cf.eviction_victim(), None);
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!(cf.is_full());
+        let (bucket_index, fingerprint) = cf.eviction_victim().unwrap();
+        assert!(bucket_index < cf.length_u32);
+        assert_ne!(fingerprint, 0);
+        assert_eq!(
+            cf.iter_occupied().last(),
+            Some((bucket_index, EVICTION_CACHE_SLOT, fingerprint))
+        );
+        // It belongs to one of the inserted items, and goes once that item is deleted
+        let owner = (0..=i)
+            .find(|item| {
+                let (candidate_1, candidate_2, item_fingerprint) = cf.buckets_from_item(item);
+                item_fingerprint == fingerprint
+                    && (candidate_1 == bucket_index || candidate_2 == bucket_index)
+            })
+            .unwrap();
+        assert!(cf.delete(&owner).is_ok());
+        assert!(!cf.is_full());
+        while cf.eviction_victim().is_some() {
+            let (bucket_index, fingerprint) = cf.eviction_victim().unwrap();
+            let alternate = cf.bucket_from_evicted(bucket_index, fingerprint);
+            assert!(cf
+                .internal_delete(bucket_index, alternate, fingerprint)
+                .is_ok());
+        }
+        assert!(cf.eviction_cache.is_empty());
     }
 
-    // The filter should hold exactly the item limit but no more (error is around secondary checks relating to power of 2 rounding)
     #[test]
-    fn make_filter_item_limit_boundary() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(ITEM_LIMIT, false);
-        assert!(filter.is_ok());
-        let filter2 = CuckooFilter::<Murmur3Hasher>::new(ITEM_LIMIT + 1, false);
-        assert!(filter2.is_err());
+    fn eviction_cache_holds_several_victims() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let mut first_parked = None;
+        let mut parked = 0;
+        let mut i = 0u64;
+        loop {
+            let cached_before = cf.eviction_cache.len();
+            let result = cf.insert(&i);
+            if cf.eviction_cache.len() > cached_before {
+                parked += 1;
+                first_parked.get_or_insert(i);
+            }
+            if result.is_err() {
+                break;
+            }
+            i += 1;
+        }
+        // Only the insert that took the last cache slot reported running out of space
+        assert_eq!(parked, EVICTION_CACHE_CAPACITY);
+        assert!(first_parked.unwrap() < i);
+        assert!(cf.is_full());
+        assert_eq!(cf.insert(&(i + 1)), Err(CuckooFilterError::OutOfSpace));
+        assert!((0..=i).all(|i| cf.contains(&i)));
+        // Every cached fingerprint is found through the cache
+        let victims = cf.eviction_cache.victims().to_vec();
+        for &(index, fingerprint) in victims.iter() {
+            let alternate = cf.bucket_from_evicted(index, fingerprint);
+            assert!(cf.cache_matches(index, alternate, fingerprint));
+            assert!(cf.internal_lookup(index, alternate, fingerprint));
+        }
+        // Deleting any one of them makes room again
+        let (index, fingerprint) = victims[3];
+        let alternate = cf.bucket_from_evicted(index, fingerprint);
         assert_eq!(
-            CuckooFilterError::CapacityExceedsItemLimit,
-            filter2.unwrap_err()
+            cf.internal_delete(index, alternate, fingerprint),
+            Ok((index, BUCKET_SIZE))
         );
+        assert!(!cf.is_full());
+        assert_eq!(cf.eviction_cache.len(), EVICTION_CACHE_CAPACITY - 1);
+        assert_eq!(cf.len(), cf.stored_fingerprints().count());
     }
 
-    // Check that the comp time check throws
     #[test]
-    #[should_panic(expected = "cuckoo filter initialized with too many items")]
-    fn make_filter_comp_time_check() {
-        const TOO_MANY_ITEMS: usize = ITEM_LIMIT + 1;
-        let _filter = CuckooFilter::<Murmur3Hasher>::new(TOO_MANY_ITEMS, true);
+    fn insert_reclaims_cache_after_delete() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(256, false).unwrap();
+        let mut inserted: Vec<u64> = Vec::new();
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            inserted.push(i);
+            i += 1;
+        }
+        let victim_buckets: Vec<BucketIndex> = cf
+            .eviction_cache
+            .victims()
+            .iter()
+            .flat_map(|&(index, fingerprint)| [index, cf.bucket_from_evicted(index, fingerprint)])
+            .collect();
+        // Delete removes from the first bucket when it can, so this frees a slot the first cached fingerprint can use
+        let frees_victim_slot = inserted
+            .iter()
+            .position(|item| {
+                let (candidate_1, _, fingerprint) = cf.buckets_from_item(item);
+                victim_buckets[..2].contains(&candidate_1)
+                    && cf.data[candidate_1 as usize].contains(&fingerprint)
+            })
+            .unwrap();
+        let unrelated = inserted
+            .iter()
+            .position(|item| {
+                let (candidate_1, candidate_2) = cf.peek_buckets(item);
+                !victim_buckets.contains(&candidate_1) && !victim_buckets.contains(&candidate_2)
+            })
+            .unwrap();
+        assert!(cf.delete(&inserted[frees_victim_slot]).is_ok());
+        assert!(cf.delete(&inserted[unrelated]).is_ok());
+        assert!(cf.is_full());
+        // A cached fingerprint moves into the freed slot, which makes room for the insert
+        assert!(cf.insert(&inserted[unrelated]).is_ok());
+        assert!(!cf.is_full());
+        inserted.remove(frees_victim_slot);
+        assert!(inserted.iter().all(|item| cf.contains(item)));
+        assert!(cf.contains(&i));
     }
 
     #[test]
-    fn check_size() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-        let cf = filter.unwrap();
-        assert_eq!(cf.estimate_size(), 128);
+    fn lazy_delete_then_flush() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..20u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // A second copy of 0 survives a single lazy delete
+        assert!(cf.insert(&0u64).is_ok());
+        for i in 0..5u64 {
+            assert!(cf.lazy_delete(&i).is_ok());
+        }
+        assert!(cf.contains(&0u64));
+        assert!((1..5u64).all(|i| !cf.contains(&i)));
+        assert!((5..20u64).all(|i| cf.contains(&i)));
+        assert_eq!(
+            cf.lazy_delete(&1u64),
+            Err(CuckooFilterError::ItemDoesNotExist)
+        );
+        // Slots are only freed by the flush
+        assert_eq!(occupied_slots(&cf), 21);
+        cf.flush_deletes();
+        assert_eq!(occupied_slots(&cf), 16);
+        assert!(cf.contains(&0u64));
+        assert!((1..5u64).all(|i| !cf.contains(&i)));
+        assert!((5..20u64).all(|i| cf.contains(&i)));
     }
 
+    // An eager delete mustn't clear a slot that's already tombstoned, or the tombstone hides the next copy
     #[test]
-    fn check_bucket_equivalence() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(1, false);
-        let cf = filter.unwrap();
-        let (b1, b2, f) = cf.digest_to_buckets(murmur3_x86_64bit(&"test".as_bytes()));
-        let b2alt = cf.bucket_from_evicted(b1, f);
-        let b1alt = cf.bucket_from_evicted(b2, f);
-        assert_eq!(b1, b1alt);
-        assert_eq!(b2, b2alt);
+    fn delete_after_lazy_delete() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        assert!(cf.insert(&"x").is_ok());
+        assert!(cf.lazy_delete(&"x").is_ok());
+        assert_eq!(cf.delete(&"x"), Err(CuckooFilterError::ItemDoesNotExist));
+        assert!(!cf.take(&"x"));
+        assert!(cf.insert(&"x").is_ok());
+        assert!(cf.contains(&"x"));
+        cf.flush_deletes();
+        assert!(cf.contains(&"x"));
+        assert_eq!(cf.len(), 1);
+        // With one live copy left, an eager delete goes through
+        assert!(cf.insert(&"x").is_ok());
+        assert!(cf.lazy_delete(&"x").is_ok());
+        assert!(cf.delete(&"x").is_ok());
+        assert!(!cf.contains(&"x"));
+        cf.flush_deletes();
+        assert!(cf.is_empty());
     }
 
+    // Pending deletes hold bucket indices for the old size, so resizing has to apply them first
     #[test]
-    fn insert_bytes() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-        let mut cf = filter.unwrap();
-        let r = cf.insert(&[1, 2, 3, 4, 5]);
-        assert!(r.is_ok());
+    fn lazy_delete_then_shrink() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        for i in 0..20u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        for i in 0..10u64 {
+            assert!(cf.lazy_delete(&i).is_ok());
+        }
+        let copy = cf.clone_shrunk(256).unwrap();
+        assert_eq!(copy.len(), 10);
+        assert!((0..10u64).all(|i| !copy.contains(&i)));
+        assert!((10..20u64).all(|i| copy.contains(&i)));
+        // The source keeps its pending deletes
+        assert_eq!(cf.pending_deletes.len(), 10);
+        assert_eq!(occupied_slots(&cf), 20);
+        for i in 10..15u64 {
+            assert!(cf.lazy_delete(&i).is_ok());
+        }
+        cf.shrink_to_fit(64);
+        assert_eq!(cf.capacity(), 64);
+        assert_eq!(cf.len(), 5);
+        assert!((0..15u64).all(|i| !cf.contains(&i)));
+        assert!((15..20u64).all(|i| cf.contains(&i)));
+        cf.flush_deletes();
+        assert_eq!(occupied_slots(&cf), 5);
     }
 
     #[test]
-    fn insert_number() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-        let mut cf = filter.unwrap();
-        let r = cf.insert(&19384);
-        assert!(r.is_ok());
+    fn load_after_grow_halves_load() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..1000u64 {
+            if cf.insert(&i).is_err() {
+                break;
+            }
+        }
+        assert!(cf.is_full());
+        let current_load = cf.load_after_grow(1);
+        assert!(current_load > 0.5);
+        assert!((cf.load_after_grow(2) - current_load / 2.0).abs() < 0.01);
+        assert!((cf.load_after_grow(4) - current_load / 4.0).abs() < 0.01);
     }
 
     #[test]
-    fn insert_string() {
-        let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
-        let mut cf = filter.unwrap();
-        let r = cf.insert(&"hello");
-        assert!(r.is_ok());
+    fn insert_with_policy_changes_eviction() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..600u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // A short chain without the cache gives up early, and must leave everything as it was when it does
+        let quick = InsertPolicy {
+            max_kicks: 4,
+            balanced: true,
+            use_cache: false,
+        };
+        let mut refused = 0;
+        for i in 600..800u64 {
+            let snapshot = cf.data.clone();
+            if cf.insert_with_policy(&i, quick).is_err() {
+                refused += 1;
+                assert_eq!(cf.data, snapshot);
+            }
+            #[cfg(feature = "diagnostics")]
+            assert!(*cf.eviction_counts.last().unwrap() <= 4);
+        }
+        assert!(refused > 0);
+        assert!(!cf.is_full());
+        // The default budget gets further, and kicks for longer to do it
+        for i in 800..1000u64 {
+            if cf.insert_with_policy(&i, InsertPolicy::default()).is_err() {
+                break;
+            }
+        }
+        #[cfg(feature = "diagnostics")]
+        assert!(cf.eviction_counts[800..].iter().any(|&kicks| kicks > 4));
+        assert!((0..600u64).all(|i| cf.contains(&i)));
     }
 
     #[test]
-    fn retrieve_item() {
+    fn low_eviction_cap_fills_sooner() {
+        let fill = |max_evictions: u16| {
+            let mut cf =
+                CuckooFilter::<Murmur3Hasher>::new_with_params(4096, max_evictions, false).unwrap();
+            (0u64..).take_while(|i| cf.insert(i).is_ok()).count()
+        };
+        let capped = fill(2);
+        let default = fill(MAX_EVICTIONS);
+        assert!(capped < default, "{capped} vs {default}");
+        assert!(default > 4096 * 95 / 100);
+        // The cap carries over when resizing
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_params(128, 2, false).unwrap();
+        assert!(cf.grow(true).is_ok());
+        assert_eq!(cf.max_evictions, 2);
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::new_with_params(128, 0, false).unwrap_err(),
+            CuckooFilterError::ZeroMaxEvictions
+        );
+    }
+
+    #[test]
+    fn backup_takes_overflow() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        let mut backup = CuckooFilter::<Murmur3Hasher>::new(256, false).unwrap();
+        for i in 0..100u64 {
+            assert!(cf.insert_with_backup(&i, &mut backup).is_ok());
+        }
+        assert!(cf.is_full());
+        assert!(occupied_slots(&backup) > 0);
+        assert!((0..100u64).all(|i| cf.lookup_with_backup(&i, &backup)));
+        // Overflow items are only in the backup
+        assert!((0..100u64).any(|i| !cf.contains(&i) && backup.contains(&i)));
+    }
+
+    #[test]
+    fn insert_outcome_reports_evictions() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert_eq!(cf.insert_outcome(&0u64), Ok(InsertOutcome::DirectPlacement));
+        for i in 1..600u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let mut evicted = 0;
+        for i in 600..650u64 {
+            match cf.insert_outcome(&i) {
+                Ok(InsertOutcome::Evicted { kicks }) => {
+                    assert!(kicks > 0);
+                    #[cfg(feature = "diagnostics")]
+                    assert_eq!(*cf.eviction_counts.last().unwrap(), kicks);
+                    evicted += 1;
+                }
+                Ok(InsertOutcome::DirectPlacement) => {}
+                Err(error) => panic!("unexpected {error:?}"),
+            }
+        }
+        assert!(evicted > 0);
+    }
+
+    #[test]
+    fn insert_prefer_picks_bucket() {
+        let mut first = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let mut second = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        // Find an item whose candidate buckets differ
+        let item = (0u64..)
+            .find(|i| {
+                let (candidate_1, candidate_2) = first.peek_buckets(i);
+                candidate_1 != candidate_2
+            })
+            .unwrap();
+        let (candidate_1, candidate_2) = first.peek_buckets(&item);
+        assert!(first.insert_prefer(&item, false).is_ok());
+        assert!(second.insert_prefer(&item, true).is_ok());
+        let occupied = |cf: &CuckooFilter<Murmur3Hasher>, bucket_index: BucketIndex| {
+            cf.data[bucket_index as usize].iter().any(|&fp| fp != 0)
+        };
+        assert!(occupied(&first, candidate_1) && !occupied(&first, candidate_2));
+        assert!(occupied(&second, candidate_2) && !occupied(&second, candidate_1));
+        assert!(first.contains(&item) && second.contains(&item));
+    }
+
+    #[test]
+    fn insert_returning_hash_matches_murmur3() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..100 {
+            let item = format!("item {i}");
+            let hash = cf.insert_returning_hash(&item.as_str()).unwrap();
+            assert_eq!(hash, murmur3_x86_64bit(item.as_bytes()));
+            assert!(cf.contains(&item.as_str()));
+        }
+    }
+
+    #[test]
+    fn insert_locating_reports_position() {
         let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
         let mut cf = filter.unwrap();
-        let item = [1u8, 2, 3, 4, 5];
-        let r = cf.insert(&item);
-        assert!(r.is_ok());
-        let is_found = cf.lookup(&item);
-        assert!(is_found);
+        let item = "hello";
+        let (_, _, fingerprint) = cf.buckets_from_item(&item);
+        let (bucket_index, slot) = cf.insert_locating(&item).unwrap();
+        assert_eq!(cf.data[bucket_index as usize][slot], fingerprint);
+        // Positions stay accurate when inserts start evicting
+        for i in 0..120u64 {
+            let (_, _, fingerprint) = cf.buckets_from_item(&i);
+            if let Ok((bucket_index, slot)) = cf.insert_locating(&i) {
+                assert_eq!(cf.data[bucket_index as usize][slot], fingerprint);
+            }
+        }
     }
 
     #[test]
-    fn delete_item() {
+    fn tiered_lookup_reports_tier() {
+        let mut hot = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let mut cold = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let item = "cold item";
+        assert!(cold.insert(&item).is_ok());
+        assert_eq!(tiered_lookup(&hot, &cold, &item), TierResult::ColdHit);
+        // Promote it
+        assert!(hot.insert(&item).is_ok());
+        assert_eq!(tiered_lookup(&hot, &cold, &item), TierResult::HotHit);
+        assert_eq!(tiered_lookup(&hot, &cold, &"missing"), TierResult::NotFound);
+    }
+
+    // Items inserted via the `Hash` path should be visible to the stateless path (and vice versa) when using Murmur3
+    #[test]
+    fn hash_trait_vs_stateless_consistency() {
         let filter = CuckooFilter::<Murmur3Hasher>::new(128, false);
         let mut cf = filter.unwrap();
-        let item = [1u8, 2, 3, 4, 5];
-        let r = cf.insert(&item);
-        assert!(r.is_ok());
-        let is_found = cf.lookup(&item);
-        assert!(is_found);
-        let d = cf.delete(&item);
-        assert!(d.is_ok());
-        // Check that the item is no longer present
-        assert!(!cf.lookup(&item));
+        let item = "the cat says meow";
+        assert!(cf.insert(&item).is_ok());
+        assert!(cf.contains_stateless(item.as_bytes(), murmur3_x86_64bit));
+        let bytes = [1u8, 2, 3, 4, 5];
+        assert!(cf.insert_stateless(&bytes, murmur3_x86_64bit).is_ok());
+        assert!(cf.contains(&bytes));
+        assert!(cf.contains(&bytes.to_vec()));
+        // Deleting through one path removes what the other path inserted
+        assert!(cf
+            .delete_stateless(item.as_bytes(), murmur3_x86_64bit)
+            .is_ok());
+        assert!(!cf.contains(&item));
     }
 
     // LOAD TESTS: realistically, the filter will fail to fill due to hash collisions before it's "theoretically" full - but we should be able to fill most of it! This is disabled by default due to load
@@ -609,6 +5136,35 @@ mod tests {
         assert!((success_count as f32 / SIZE as f32) > 0.95f32);
     }
 
+    // Throughput of `lookup_all` against a plain `contains` loop on a filter far bigger than cache. Disabled by default due to load
+    #[test]
+    #[ignore]
+    fn bench_lookup_all_10m() {
+        const SIZE: usize = 10_000_000;
+        let mut filter = CuckooFilter::<Murmur3Hasher>::new(SIZE, false).unwrap();
+        for i in 0..(SIZE as u64 * 9 / 10) {
+            let _ = filter.insert(&i);
+        }
+        // Half hits, half (mostly) misses, in random order
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let queries: Vec<u64> = (0..SIZE)
+            .map(|_| rng.gen_range(0..SIZE as u64 * 9 / 5))
+            .collect();
+
+        let start = std::time::Instant::now();
+        let naive: Vec<bool> = queries
+            .iter()
+            .map(|q| filter.lookup_hash(filter.digest_of(q)))
+            .collect();
+        let naive_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let batched = filter.lookup_all(&queries);
+        let batched_time = start.elapsed();
+
+        println!("naive: {naive_time:?} / lookup_all: {batched_time:?}");
+        assert_eq!(naive, batched);
+    }
+
     #[test]
     fn load_test_ten_thousand_str() {
         // Initialize
@@ -626,7 +5182,7 @@ mod tests {
             if r.is_ok() {
                 success_count += 1;
                 // Check that the random string is present
-                assert!(filter.lookup(&random_string));
+                assert!(filter.contains(&random_string));
                 cache.push(random_string);
             }
         }
@@ -640,7 +5196,7 @@ mod tests {
         // Try to find every item that we inserted
         let mut check_count: usize = 0;
         for i in cache.iter() {
-            if filter.lookup(i) {
+            if filter.contains(i) {
                 check_count += 1;
             }
         }
@@ -661,44 +5217,52 @@ mod tests {
         // Insert random strings
         for i in 0..SIZE {
             let random_string = get_random_string(&mut rng, (i % 12) + 1);
-            let r = filter.insert_stateless(&random_string.as_bytes(), murmur3_x86_64bit);
+            let r = filter.insert_stateless(random_string.as_bytes(), murmur3_x86_64bit);
             if r.is_ok() {
                 success_count += 1;
                 // Check that the random string is present
-                assert!(filter.lookup_stateless(&random_string.as_bytes(), murmur3_x86_64bit));
+                assert!(filter.contains_stateless(random_string.as_bytes(), murmur3_x86_64bit));
                 cache.push(random_string);
             }
         }
 
         println!("successes: {success_count} / trials: {SIZE}");
-        println!(
-            "number of items that required swaps {}",
-            filter.swap_counts.iter().filter(|x| **x > 0).count()
-        );
-        println!(
-            "total kicks: {}",
-            filter.eviction_counts.iter().sum::<u16>()
-        );
+        #[cfg(feature = "diagnostics")]
+        {
+            println!(
+                "number of items that required swaps {}",
+                filter.swap_counts.iter().filter(|x| **x > 0).count()
+            );
+            println!(
+                "total kicks: {}",
+                filter.eviction_counts.iter().sum::<u16>()
+            );
+        }
         // Check that at least 95% of writes succeeded (before running out of space)
         assert!((success_count as f32 / SIZE as f32) > 0.95f32);
         // Consistency check
         assert_eq!(cache.len(), success_count);
         // Compute cumulative evictions
-        let mut cumulative_evicts: Vec<usize> = Vec::with_capacity(filter.eviction_counts.len());
-        let mut running_total: usize = 0;
-        for i in filter.eviction_counts.iter() {
-            running_total += *i as usize;
-            cumulative_evicts.push(running_total);
-        }
+        #[cfg(feature = "diagnostics")]
+        let cumulative_evicts: Vec<usize> = filter
+            .eviction_counts
+            .iter()
+            .scan(0, |running_total, &kicks| {
+                *running_total += kicks as usize;
+                Some(*running_total)
+            })
+            .collect();
 
         // Try to find every item that we inserted
         let mut check_count: usize = 0;
         for (index, i) in cache.iter().enumerate() {
-            if filter.lookup_stateless(i.as_bytes(), murmur3_x86_64bit) {
+            if filter.contains_stateless(i.as_bytes(), murmur3_x86_64bit) {
                 check_count += 1;
             } else {
+                println!("{index}th item not found");
+                #[cfg(feature = "diagnostics")]
                 println!(
-                    "{index}th item not found: {} kicks, {} swaps, {} cumulative kicks",
+                    "{} kicks, {} swaps, {} cumulative kicks",
                     filter.eviction_counts[index],
                     filter.swap_counts[index],
                     cumulative_evicts[index]
@@ -0,0 +1,168 @@
+//! Builder for configuring a Cuckoo Filter one setting at a time
+
+use super::{CuckooFilter, CuckooFilterError, SplitMix64, BUCKET_SIZE, MAX_EVICTIONS};
+use core::hash::Hasher;
+use core::marker::PhantomData;
+
+/// Collects the settings for a new `CuckooFilter`, then builds it
+///
+/// Anything left unset gets the same value `new` uses: no capacity check at compile time, a kick limit of 500, and no seed. The capacity defaults to 0, which gives the smallest possible filter (one bucket), so you'll almost always want to set it.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilterBuilder::<Murmur3Hasher>::new()
+///     .capacity(1024)
+///     .max_evictions(1000)
+///     .seed(7)
+///     .build()
+///     .unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CuckooFilterBuilder<H: Hasher + Default, const B: usize = BUCKET_SIZE> {
+    capacity: usize,
+    max_evictions: u16,
+    seed: u32,
+    compile_time_check: bool,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default, const B: usize> Default for CuckooFilterBuilder<H, B> {
+    fn default() -> Self {
+        CuckooFilterBuilder {
+            capacity: 0,
+            max_evictions: MAX_EVICTIONS,
+            seed: 0,
+            compile_time_check: false,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H: Hasher + Default, const B: usize> CuckooFilterBuilder<H, B> {
+    /// Start from the defaults
+    pub fn new() -> CuckooFilterBuilder<H, B> {
+        CuckooFilterBuilder::default()
+    }
+
+    /// How many items the filter should hold (rounded as in `CuckooFilter::new`)
+    pub fn capacity(mut self, max_items: usize) -> CuckooFilterBuilder<H, B> {
+        self.capacity = max_items;
+        self
+    }
+
+    /// The most fingerprints an insert may kick before giving up (see `CuckooFilter::new_with_params`)
+    pub fn max_evictions(mut self, max_evictions: u16) -> CuckooFilterBuilder<H, B> {
+        self.max_evictions = max_evictions;
+        self
+    }
+
+    /// Seed the hashes and eviction slot choices (see `CuckooFilter::new_with_seed`)
+    pub fn seed(mut self, seed: u32) -> CuckooFilterBuilder<H, B> {
+        self.seed = seed;
+        self
+    }
+
+    /// Panic instead of returning an error when the capacity is over the item limit, which turns into a compile error when the capacity is a `const` (see `CuckooFilter::new`)
+    pub fn compile_time_check(mut self, compile_time_check: bool) -> CuckooFilterBuilder<H, B> {
+        self.compile_time_check = compile_time_check;
+        self
+    }
+
+    /// Build the filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::ZeroMaxEvictions`: `max_evictions` was set to 0
+    pub fn build(self) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let mut filter = CuckooFilter::<H, B>::new_with_params(
+            self.capacity,
+            self.max_evictions,
+            self.compile_time_check,
+        )?;
+        filter.seed = self.seed;
+        filter.slot_rng = SplitMix64::new(self.seed as u64);
+        Ok(filter)
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::ITEM_LIMIT;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn defaults_match_new() {
+        let built = CuckooFilterBuilder::<Murmur3Hasher>::new()
+            .capacity(1024)
+            .build()
+            .unwrap();
+        let made = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert_eq!(built.capacity(), made.capacity());
+        assert_eq!(built.max_evictions, made.max_evictions);
+        assert_eq!(built.seed(), made.seed());
+        assert_eq!(
+            CuckooFilterBuilder::<Murmur3Hasher>::new()
+                .build()
+                .unwrap()
+                .num_buckets(),
+            1
+        );
+    }
+
+    #[test]
+    fn each_option_is_applied() {
+        let mut cf = CuckooFilterBuilder::<Murmur3Hasher>::new()
+            .capacity(4096)
+            .max_evictions(3)
+            .seed(7)
+            .build()
+            .unwrap();
+        assert_eq!(cf.capacity(), 4096);
+        assert_eq!(cf.max_evictions, 3);
+        assert_eq!(cf.seed(), 7);
+        // Seeded the same way as `new_with_seed`, so both place items identically
+        let mut seeded = CuckooFilter::<Murmur3Hasher>::new_with_seed(4096, 7).unwrap();
+        for i in 0..1000u64 {
+            assert!(cf.insert(&i).is_ok());
+            assert!(seeded.insert(&i).is_ok());
+        }
+        assert_eq!(cf.data, seeded.data);
+        // Other bucket sizes come along too
+        let wide = CuckooFilterBuilder::<Murmur3Hasher, 8>::new()
+            .capacity(1024)
+            .build()
+            .unwrap();
+        assert_eq!(wide.num_buckets(), 128);
+        // Errors come from `build`
+        assert_eq!(
+            CuckooFilterBuilder::<Murmur3Hasher>::new()
+                .max_evictions(0)
+                .build()
+                .unwrap_err(),
+            CuckooFilterError::ZeroMaxEvictions
+        );
+        assert_eq!(
+            CuckooFilterBuilder::<Murmur3Hasher>::new()
+                .capacity(ITEM_LIMIT + 1)
+                .build()
+                .unwrap_err(),
+            CuckooFilterError::CapacityExceedsItemLimit
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "too many items")]
+    fn compile_time_check_panics() {
+        let _ = CuckooFilterBuilder::<Murmur3Hasher>::new()
+            .capacity(ITEM_LIMIT)
+            .compile_time_check(true)
+            .build();
+    }
+}
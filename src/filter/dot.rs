@@ -0,0 +1,92 @@
+//! Graphviz export of the cuckoo graph
+//!
+//! Every bucket is a node, and every stored fingerprint is an edge from the bucket it sits in to its other candidate bucket. Long eviction chains and collision clusters show up as densely connected knots.
+
+use super::{BucketIndex, CuckooFilter};
+use core::hash::Hasher;
+use core::ops::Range;
+use std::fmt::Write;
+use std::string::String;
+
+impl<H: Hasher + Default, const B: usize> CuckooFilter<H, B> {
+    /// Render the buckets in `buckets` (and the edges leaving them) as a Graphviz `digraph`
+    ///
+    /// Each node is labelled with its bucket index and contents, and each edge with the fingerprint that links the two buckets. Edges may point at buckets outside the range, which Graphviz draws as bare nodes. The range is clamped to the filter, so pass a narrow one for big filters to keep the output readable.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(16, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let dot = filter.to_dot(0..4);
+    /// assert!(dot.starts_with("digraph cuckoo {"));
+    /// ```
+    pub fn to_dot(&self, buckets: Range<BucketIndex>) -> String {
+        let end = buckets.end.min(self.length_u32);
+        let mut dot = String::from("digraph cuckoo {\n");
+        // Writing to a `String` can't fail, so the `fmt::Result`s are ignored
+        for bucket_index in buckets.start..end {
+            let bucket = &self.data[bucket_index as usize];
+            let _ = write!(dot, "    b{bucket_index} [label=\"{bucket_index}:");
+            for fingerprint in bucket {
+                let _ = write!(dot, " {fingerprint}");
+            }
+            dot.push_str("\"];\n");
+            for &fingerprint in bucket.iter().filter(|&&fp| fp != 0) {
+                let alternate_index = self.bucket_from_evicted(bucket_index, fingerprint);
+                let _ = writeln!(
+                    dot,
+                    "    b{bucket_index} -> b{alternate_index} [label=\"{fingerprint}\"];"
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn dot_has_a_node_per_bucket_and_an_edge_per_fingerprint() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(16, false).unwrap();
+        for i in 0..6u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let dot = cf.to_dot(0..cf.length_u32);
+        assert!(dot.starts_with("digraph cuckoo {\n"));
+        assert!(dot.ends_with("}\n"));
+        let nodes = dot
+            .lines()
+            .filter(|line| line.contains("[label=\"") && !line.contains("->"));
+        assert_eq!(nodes.count(), 4);
+        assert_eq!(dot.matches("->").count(), 6);
+        // Ranges past the end are clamped
+        assert_eq!(
+            cf.to_dot(2..100).lines().count(),
+            cf.to_dot(2..4).lines().count()
+        );
+    }
+
+    // Labels list the whole bucket, whatever its size
+    #[test]
+    fn dot_labels_follow_the_bucket_size() {
+        let mut narrow = CuckooFilter::<Murmur3Hasher, 2>::new(16, false).unwrap();
+        assert!(narrow.insert(&1u64).is_ok());
+        let mut wide = CuckooFilter::<Murmur3Hasher, 8>::new(64, false).unwrap();
+        assert!(wide.insert(&1u64).is_ok());
+        for (dot, bucket_size) in [(narrow.to_dot(0..1), 2), (wide.to_dot(0..1), 8)] {
+            let label = dot.lines().nth(1).unwrap();
+            assert!(label.starts_with("    b0 [label=\"0:"), "{label}");
+            let contents = label.split(':').nth(1).unwrap().trim_end_matches("\"];");
+            assert_eq!(contents.split_whitespace().count(), bucket_size, "{label}");
+        }
+    }
+}
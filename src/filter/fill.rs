@@ -0,0 +1,100 @@
+//! Random filling for load tests and benchmarks
+
+use super::{CuckooFilter, CuckooFilterError};
+use core::hash::Hasher;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+impl<H: Hasher + Default, const B: usize> CuckooFilter<H, B> {
+    /// Insert random `u64`s until `load_factor()` reaches `target_load` or the filter runs out of space, returning `(inserted, failed)`
+    ///
+    /// The items come from an RNG seeded with `rng_seed`, so a run is reproducible. `failed` is at most 1: the insert that filled the filter. (Its fingerprint still went into the eviction cache, so it counts towards the load.)
+    ///
+    /// Requires the `rand` feature.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+    /// let (inserted, failed) = filter.fill_random(0.5, 42);
+    /// assert_eq!((inserted, failed), (512, 0));
+    /// ```
+    pub fn fill_random(&mut self, target_load: f32, rng_seed: u64) -> (usize, usize) {
+        let mut rng = ChaCha8Rng::seed_from_u64(rng_seed);
+        // Track the load ourselves rather than rescanning the filter after every insert
+        let mut occupied = self.len();
+        let capacity = self.capacity() as f32;
+        let mut inserted = 0;
+        while (occupied as f32 / capacity) < target_load {
+            let item: u64 = rng.gen();
+            match self.insert(&item) {
+                Ok(()) => {
+                    inserted += 1;
+                    occupied += 1;
+                }
+                Err(CuckooFilterError::OutOfSpace) => return (inserted, 1),
+                // Only possible with `set_max_bucket_collisions`: skip this item and draw another
+                Err(CuckooFilterError::TooManyCollisions) => {}
+                Err(_) => unreachable!("insert only fails with OutOfSpace or TooManyCollisions"),
+            }
+        }
+        (inserted, 0)
+    }
+
+    /// Empty the filter, then fill it with random `u64`s until it runs out of space, returning `(theoretical slot count, items inserted)`
+    ///
+    /// The crate docs say a filter usually fills to about 95% before reporting full; this measures that gap for your `Hasher` (and the filter's seed). It's destructive: whatever was in the filter is gone, and it's left full of random items. `rng_seed` picks the items, as in `fill_random`.
+    ///
+    /// Requires the `rand` feature.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+    /// let (theoretical, practical) = filter.measure_practical_capacity(42);
+    /// assert_eq!(theoretical, 1024);
+    /// assert!(practical <= theoretical);
+    /// ```
+    pub fn measure_practical_capacity(&mut self, rng_seed: u64) -> (usize, usize) {
+        let capacity = self.capacity();
+        self.clear_and_resize(capacity)
+            .expect("the current capacity is always allowed");
+        let (inserted, _) = self.fill_random(1.0, rng_seed);
+        (capacity, inserted)
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn fill_random_reaches_target() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let (inserted, failed) = cf.fill_random(0.8, 7);
+        assert_eq!(failed, 0);
+        assert!((cf.load_factor() - 0.8).abs() < 0.01);
+        assert_eq!(inserted, cf.len());
+        // Same seed, same items
+        let mut again = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        again.fill_random(0.8, 7);
+        assert_eq!(again.data, cf.data);
+        // An unreachable target stops at the first failure
+        let (_, failed) = cf.fill_random(1.0, 8);
+        assert_eq!(failed, 1);
+        assert!(cf.is_full());
+    }
+
+    #[test]
+    fn practical_capacity_is_close_to_theoretical() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(16384, false).unwrap();
+        let _ = cf.insert(&"this gets wiped");
+        let (theoretical, practical) = cf.measure_practical_capacity(7);
+        assert_eq!(theoretical, 16384);
+        assert!(practical as f32 / theoretical as f32 > 0.9);
+        assert!(cf.is_full());
+    }
+}
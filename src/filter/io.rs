@@ -0,0 +1,155 @@
+//! Streaming the binary format (see the serialize module) through `std::io`
+//!
+//! The header is small and goes through a buffer, but the buckets are written straight from the filter and read straight into it, so a big filter never needs a second copy in memory.
+
+use super::serialize::{header_len, Header, PREFIX_LEN};
+use super::{CuckooFilter, CuckooFilterError, Fingerprint};
+use core::hash::Hasher;
+use std::io::{self, Read, Write};
+use std::vec;
+use std::vec::Vec;
+
+/// How many bytes of buckets `read_from` allocates before it has read them
+const READ_CHUNK_BYTES: usize = 64 * 1024;
+
+impl<H: Hasher + Default, const B: usize> CuckooFilter<H, B> {
+    /// Write the CF to `writer` in the same layout as `to_bytes`
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// Whatever `writer` returns. The output is incomplete if writing fails partway.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    /// use std::io::Cursor;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let mut file = Cursor::new(Vec::new());
+    /// filter.write_to(&mut file).unwrap();
+    /// file.set_position(0);
+    /// let restored = CuckooFilter::<Murmur3Hasher>::read_from(&mut file).unwrap();
+    /// assert!(restored.contains(&"hello, I am some data"));
+    /// ```
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.encode_header(0, 0))?;
+        writer.write_all(self.as_bytes())
+    }
+
+    /// Read a CF written by `write_to` (or `to_bytes`, or `compact_serialize`) from `reader`
+    ///
+    /// Reads exactly as many bytes as the filter takes up, so the reader can hold more data after it. The same caveats as `from_bytes` apply: query the filter with the `Hasher` it was built with.
+    ///
+    /// Requires the `std` feature.
+    ///
+    /// # Errors
+    ///
+    /// - `io::ErrorKind::UnexpectedEof`: the input ends before the filter does
+    /// - `io::ErrorKind::InvalidData`: the input isn't a serialized filter this build can read (the cases `from_bytes` reports as `InvalidEncoding` or `UnsupportedFormatVersion`)
+    /// - Whatever else `reader` returns
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<CuckooFilter<H, B>> {
+        let mut header = vec![0; PREFIX_LEN];
+        reader.read_exact(&mut header)?;
+        let header_len = header_len(&header).map_err(invalid_data)?;
+        header.resize(header_len, 0);
+        reader.read_exact(&mut header[PREFIX_LEN..])?;
+        let header = Header::decode::<B>(&header).map_err(invalid_data)?;
+        // The header isn't trusted yet, so grow the buckets as the bytes actually arrive instead of allocating its length up front
+        let stored_buckets = header.stored_buckets();
+        let mut data: Vec<[Fingerprint; B]> = Vec::new();
+        while data.len() < stored_buckets {
+            let filled = data.len();
+            let chunk = (stored_buckets - filled).min(READ_CHUNK_BYTES / B.max(1));
+            data.resize(filled + chunk, [0; B]);
+            reader.read_exact(data[filled..].as_flattened_mut())?;
+        }
+        CuckooFilter::from_header(header, data).map_err(invalid_data)
+    }
+}
+
+fn invalid_data(error: CuckooFilterError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+    use std::io::Cursor;
+
+    // Hands out at most a few bytes per call, like a socket might
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(1024, 7).unwrap();
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        let mut cursor = Cursor::new(Vec::new());
+        cf.write_to(&mut cursor).unwrap();
+        assert_eq!(cursor.get_ref(), &cf.to_bytes());
+        // Something else after the filter is left alone
+        cursor.get_mut().extend_from_slice(b"more");
+        cursor.set_position(0);
+        let restored = CuckooFilter::<Murmur3Hasher>::read_from(&mut cursor).unwrap();
+        assert_eq!(restored, cf);
+        assert_eq!(restored.seed(), 7);
+        assert!(restored.is_full());
+        assert!((0..=i).all(|i| restored.contains(&i)));
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"more");
+        // Short reads are fine, and compacted output reads back too
+        let compact = cf.compact_serialize();
+        let restored = CuckooFilter::<Murmur3Hasher>::read_from(&mut Trickle(&compact)).unwrap();
+        assert_eq!(restored, cf);
+    }
+
+    #[test]
+    fn bad_input_is_an_io_error() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        assert!(cf.insert(&1u64).is_ok());
+        let bytes = cf.to_bytes();
+        // Cut off in the header, and in the buckets
+        for len in [0, 3, 20, bytes.len() - 1] {
+            let error = CuckooFilter::<Murmur3Hasher>::read_from(&mut &bytes[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "{len}");
+        }
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        let mut newer = bytes.clone();
+        newer[4] = 99;
+        let mut wrong_bucket_size = bytes;
+        wrong_bucket_size[9] = 8;
+        for bad in [wrong_magic, newer, wrong_bucket_size] {
+            let error = CuckooFilter::<Murmur3Hasher>::read_from(&mut bad.as_slice()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    // A header claiming 2^31 buckets with nothing after it runs out of input, not memory
+    #[test]
+    fn huge_truncated_input_is_eof() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let mut bytes = cf.to_bytes();
+        bytes[5..9].copy_from_slice(&(1u32 << 31).to_le_bytes());
+        bytes.truncate(bytes.len() - 1);
+        let error = CuckooFilter::<Murmur3Hasher>::read_from(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,223 @@
+//! A read-only layout for sharing a finished filter between processes
+//!
+//! One process builds a filter and writes `finalize_to_mmap_layout` to a file. Others memory-map that file and query it through `CuckooFilterRef` without copying or deserializing anything. The layout is a fixed header followed by the buckets:
+//!
+//! | offset | bytes | field |
+//! |--------|-------|-------|
+//! | 0 | 4 | magic, `CKMM` |
+//! | 4 | 4 | number of buckets (`length_u32`), little endian |
+//! | 8 | 4 | hash seed, little endian |
+//! | 12 | 1 | number of fingerprints in the eviction cache (up to 8) |
+//! | 13 | 3 | padding (zeros) |
+//! | 16 | 32 | eviction cache bucket indices, 8 of them, little endian (unused ones zeroed) |
+//! | 48 | 8 | eviction cache fingerprints (unused ones zeroed) |
+//! | 56 | 8 | padding (zeros) |
+//! | 64 | n | buckets, 4 fingerprints each |
+//!
+//! A bucket is a `[u8; 4]`, which has an alignment of 1, so the bucket region can be borrowed as `&[[u8; 4]]` wherever the mapping lands. The header is padded to 64 bytes anyway, so a page-aligned mapping keeps the buckets 8-byte aligned for tools that read them as wider integers.
+
+use super::{
+    buckets_for, seeded_hasher, CuckooFilter, CuckooFilterError, EvictionCache, Fingerprint,
+    BUCKET_SIZE, EVICTION_CACHE_CAPACITY,
+};
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+const MAGIC: &[u8; 4] = b"CKMM";
+
+/// Offset of the bucket region
+const HEADER_LEN: usize = 64;
+
+/// Offset of the eviction cache's bucket indices (its fingerprints follow them)
+const CACHE_OFFSET: usize = 16;
+
+impl<H: Hasher + Default> CuckooFilter<H> {
+    /// Write the CF in the read-only layout described in the module docs, for `CuckooFilterRef::from_mmap`
+    ///
+    /// Pending lazy deletes aren't applied, so call `flush_deletes` first if there are any.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let bytes = filter.finalize_to_mmap_layout();
+    /// let shared = CuckooFilterRef::<Murmur3Hasher>::from_mmap(&bytes).unwrap();
+    /// assert!(shared.contains(&"hello, I am some data"));
+    /// ```
+    pub fn finalize_to_mmap_layout(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.data.len() * BUCKET_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.length_u32.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.eviction_cache.len() as u8);
+        bytes.resize(CACHE_OFFSET, 0);
+        let victims = &self.eviction_cache.victims;
+        for &(bucket_index, _) in victims.iter() {
+            bytes.extend_from_slice(&bucket_index.to_le_bytes());
+        }
+        bytes.extend(victims.iter().map(|&(_, fingerprint)| fingerprint));
+        bytes.resize(HEADER_LEN, 0);
+        for bucket in self.data.iter() {
+            bytes.extend_from_slice(bucket);
+        }
+        bytes
+    }
+}
+
+/// A read-only Cuckoo Filter borrowed straight out of bytes from `finalize_to_mmap_layout` (typically a memory-mapped file)
+///
+/// It answers the same lookups as the filter it was built from, and never copies the buckets.
+#[derive(Debug)]
+pub struct CuckooFilterRef<'a, H: Hasher + Default> {
+    data: &'a [[Fingerprint; BUCKET_SIZE]],
+    length_u32: u32,
+    seed: u32,
+    eviction_cache: EvictionCache,
+    phantom: PhantomData<H>,
+}
+
+impl<'a, H: Hasher + Default> CuckooFilterRef<'a, H> {
+    /// Check the header of `bytes` and borrow its bucket region
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::InvalidEncoding`: the bytes are truncated, have trailing data, don't start with the magic, or describe a filter this build can't represent (zero buckets, or an eviction cache that's overfull, points outside the filter or holds the empty fingerprint)
+    pub fn from_mmap(bytes: &'a [u8]) -> Result<CuckooFilterRef<'a, H>, CuckooFilterError> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        let (header, buckets) = bytes.split_at(HEADER_LEN);
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let length_u32 = read_u32(4);
+        let cached = header[12] as usize;
+        if cached > EVICTION_CACHE_CAPACITY {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        let mut eviction_cache = EvictionCache::new();
+        for victim in 0..cached {
+            eviction_cache.push(
+                read_u32(CACHE_OFFSET + victim * 4),
+                header[CACHE_OFFSET + EVICTION_CACHE_CAPACITY * 4 + victim],
+            );
+        }
+        let (data, remainder) = buckets.as_chunks::<BUCKET_SIZE>();
+        if length_u32 == 0
+            || !remainder.is_empty()
+            || data.len() != length_u32 as usize
+            // A cached fingerprint can't be the empty-slot value
+            || eviction_cache
+                .victims()
+                .iter()
+                .any(|&(bucket_index, fingerprint)| bucket_index >= length_u32 || fingerprint == 0)
+        {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        Ok(CuckooFilterRef {
+            data,
+            length_u32,
+            seed: read_u32(8),
+            eviction_cache,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Check if item is in the filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let mut hasher: H = seeded_hasher(self.seed);
+        item.hash(&mut hasher);
+        self.lookup_hash(hasher.finish())
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Check for an item by a 64-bit hash you computed yourself, like `CuckooFilter::lookup_hash`
+    pub fn lookup_hash(&self, hash_value: u64) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = buckets_for(hash_value, self.length_u32);
+        self.eviction_cache
+            .position(candidate_1, candidate_2, fingerprint)
+            .is_some()
+            || self.data[candidate_1 as usize].contains(&fingerprint)
+            || self.data[candidate_2 as usize].contains(&fingerprint)
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn mmap_ref_matches_filter() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(1024, 3).unwrap();
+        for i in 0..700u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let bytes = cf.finalize_to_mmap_layout();
+        assert_eq!(bytes.len(), HEADER_LEN + 1024);
+        let shared = CuckooFilterRef::<Murmur3Hasher>::from_mmap(&bytes).unwrap();
+        // The bucket region is borrowed, not copied
+        assert_eq!(
+            shared.data.as_ptr() as *const u8,
+            bytes[HEADER_LEN..].as_ptr()
+        );
+        for i in 0..2000u64 {
+            assert_eq!(shared.contains(&i), cf.contains(&i));
+        }
+    }
+
+    #[test]
+    fn mmap_ref_rejects_malformed_bytes() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let bytes = cf.finalize_to_mmap_layout();
+        let parse = |bytes: &[u8]| CuckooFilterRef::<Murmur3Hasher>::from_mmap(bytes).map(|_| ());
+        assert_eq!(parse(&bytes), Ok(()));
+        assert_eq!(
+            parse(&bytes[..bytes.len() - 1]),
+            Err(CuckooFilterError::InvalidEncoding)
+        );
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(parse(&bad_magic), Err(CuckooFilterError::InvalidEncoding));
+        let mut overfull = bytes.clone();
+        overfull[12] = EVICTION_CACHE_CAPACITY as u8 + 1;
+        assert_eq!(parse(&overfull), Err(CuckooFilterError::InvalidEncoding));
+        let mut zero_victim = bytes.clone();
+        zero_victim[12] = 1;
+        assert_eq!(parse(&zero_victim), Err(CuckooFilterError::InvalidEncoding));
+        let mut outside = zero_victim.clone();
+        outside[48] = 7;
+        assert_eq!(parse(&outside), Ok(()));
+        outside[16..20].copy_from_slice(&32u32.to_le_bytes());
+        assert_eq!(parse(&outside), Err(CuckooFilterError::InvalidEncoding));
+    }
+
+    // Every cached fingerprint has to make it into the layout, or it becomes a false negative
+    #[test]
+    fn mmap_ref_keeps_eviction_cache() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(64, false).unwrap();
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert_eq!(cf.eviction_cache.len(), EVICTION_CACHE_CAPACITY);
+        let bytes = cf.finalize_to_mmap_layout();
+        let shared = CuckooFilterRef::<Murmur3Hasher>::from_mmap(&bytes).unwrap();
+        assert_eq!(shared.eviction_cache.victims(), cf.eviction_cache.victims());
+        assert!((0..=i).all(|i| shared.contains(&i)));
+    }
+}
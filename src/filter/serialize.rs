@@ -0,0 +1,570 @@
+//! Binary serialization for the Cuckoo Filter
+//!
+//! The layout is a small fixed-size header followed by the raw fingerprint array:
+//!
+//! | bytes | field |
+//! |-------|-------|
+//! | 4 | magic bytes, `CKCF` |
+//! | 1 | format version (currently 2) |
+//! | 4 | number of buckets (`length_u32`), little endian |
+//! | 1 | bucket size (`B`) |
+//! | 4 | hash seed, little endian |
+//! | 4 | number of trailing empty buckets left out (always 0 from `to_bytes`), little endian |
+//! | 1 | number of fingerprints in the eviction cache (up to 8) |
+//! | 40 | eviction cache, 8 entries of a bucket index (4 bytes, little endian) then a fingerprint (1 byte), unused entries zeroed |
+//! | n | fingerprints, bucket by bucket |
+//!
+//! Version 1 had room for a single cached fingerprint, stored as its bucket index, fingerprint and a used flag (0 or 1) between the bucket size and the seed. `from_bytes` still reads it.
+//!
+//! The eviction cache has to round-trip exactly: if it didn't, a full filter would come back as not-full and the cached items would become false negatives. The seed has to round-trip too, since every stored fingerprint was placed by a seeded hash.
+//!
+//! `compact_serialize` leaves out any run of empty buckets at the end of the filter and records how many it dropped, and `from_bytes` fills them back in with zeros. Since that makes a short input expand to a big filter, `from_bytes_with_limit` caps the number of buckets it will build.
+//!
+//! With the `std` feature, `write_to` and `read_from` stream this layout through `std::io` instead of building it in memory.
+//!
+//! For callers with their own wire format, `as_bytes` and `from_raw_parts` deal in just the fingerprint array, with no header at all.
+
+use super::{CuckooFilter, CuckooFilterError, EvictionCache, Fingerprint, EVICTION_CACHE_CAPACITY};
+use alloc::vec::Vec;
+use core::hash::Hasher;
+
+/// Marks the start of a serialized filter
+const MAGIC: &[u8; 4] = b"CKCF";
+
+/// Bumped whenever the layout changes, so old readers refuse new data instead of misreading it
+const FORMAT_VERSION: u8 = 2;
+
+/// The magic bytes and format version, which say how long the rest of the header is
+pub(super) const PREFIX_LEN: usize = 4 + 1;
+
+/// Size of the header that precedes the fingerprints
+const HEADER_LEN: usize = 4 + 1 + 4 + 1 + 4 + 4 + 1 + EVICTION_CACHE_CAPACITY * 5;
+
+/// Size of the version 1 header, which only had room for one cached fingerprint
+const V1_HEADER_LEN: usize = 4 + 1 + 4 + 1 + 4 + 1 + 1 + 4 + 4;
+
+/// The fields of a serialized filter's header, from either format version
+pub(super) struct Header {
+    length_u32: u32,
+    seed: u32,
+    trailing_empty: u32,
+    eviction_cache: EvictionCache,
+}
+
+/// How long the header is, going by the magic bytes and format version at the start of `bytes`
+pub(super) fn header_len(bytes: &[u8]) -> Result<usize, CuckooFilterError> {
+    if bytes.len() < PREFIX_LEN || &bytes[..4] != MAGIC {
+        return Err(CuckooFilterError::InvalidEncoding);
+    }
+    match bytes[4] {
+        1 => Ok(V1_HEADER_LEN),
+        FORMAT_VERSION => Ok(HEADER_LEN),
+        _ => Err(CuckooFilterError::UnsupportedFormatVersion),
+    }
+}
+
+impl Header {
+    /// Read a whole header (as long as `header_len` says), checking it describes a filter with bucket size `B`
+    pub(super) fn decode<const B: usize>(header: &[u8]) -> Result<Header, CuckooFilterError> {
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let length_u32 = read_u32(5);
+        let bucket_size = header[9] as usize;
+        let mut eviction_cache = EvictionCache::new();
+        let (seed, trailing_empty) = if header.len() == V1_HEADER_LEN {
+            match header[15] {
+                0 => {}
+                1 => eviction_cache.push(read_u32(10), header[14]),
+                _ => return Err(CuckooFilterError::InvalidEncoding),
+            }
+            (read_u32(16), read_u32(20))
+        } else {
+            let cached = header[18] as usize;
+            if cached > EVICTION_CACHE_CAPACITY {
+                return Err(CuckooFilterError::InvalidEncoding);
+            }
+            for entry in header[19..].chunks_exact(5).take(cached) {
+                eviction_cache.push(
+                    u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                    entry[4],
+                );
+            }
+            (read_u32(10), read_u32(14))
+        };
+        if !length_u32.is_power_of_two()
+            || bucket_size != B
+            || trailing_empty > length_u32
+            || eviction_cache
+                .victims()
+                .iter()
+                .any(|&(bucket_index, _)| bucket_index >= length_u32)
+        {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        Ok(Header {
+            length_u32,
+            seed,
+            trailing_empty,
+            eviction_cache,
+        })
+    }
+
+    /// How many buckets follow the header (the rest were an empty tail that got left out)
+    pub(super) fn stored_buckets(&self) -> usize {
+        (self.length_u32 - self.trailing_empty) as usize
+    }
+}
+
+impl<H: Hasher + Default, const B: usize> CuckooFilter<H, B> {
+    /// Serialize the CF into a compact binary layout (see the module docs), including the eviction cache
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let bytes = filter.to_bytes();
+    /// let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&bytes).unwrap();
+    /// assert!(restored.contains(&"hello, I am some data"));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode(0)
+    }
+
+    /// Serialize the CF like `to_bytes`, but leave out the run of empty buckets at the end of the filter
+    ///
+    /// A lightly loaded filter (one built with lots of headroom, say) can have a long empty tail, and this keeps it out of the output. `from_bytes` reads both forms.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let bytes = filter.compact_serialize();
+    /// assert!(bytes.len() <= filter.to_bytes().len());
+    /// let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&bytes).unwrap();
+    /// assert!(restored.contains(&"hello, I am some data"));
+    /// ```
+    pub fn compact_serialize(&self) -> Vec<u8> {
+        let trailing_empty = self
+            .data
+            .iter()
+            .rev()
+            .take_while(|bucket| bucket.iter().all(|&fp| fp == 0))
+            .count();
+        self.encode(trailing_empty)
+    }
+
+    /// Write the header and every bucket except the last `trailing_empty` (which must all be empty)
+    fn encode(&self, trailing_empty: usize) -> Vec<u8> {
+        let kept = &self.data[..self.data.len() - trailing_empty];
+        let mut bytes = self.encode_header(trailing_empty, kept.len() * B);
+        bytes.extend_from_slice(kept.as_flattened());
+        bytes
+    }
+
+    /// The current version's header for a filter missing its last `trailing_empty` buckets, with room reserved for `extra` more bytes
+    pub(super) fn encode_header(&self, trailing_empty: usize, extra: usize) -> Vec<u8> {
+        const { assert!(B <= u8::MAX as usize, "bucket size doesn't fit the header") };
+        let mut bytes = Vec::with_capacity(HEADER_LEN + extra);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.length_u32.to_le_bytes());
+        bytes.push(B as u8);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(trailing_empty as u32).to_le_bytes());
+        bytes.push(self.eviction_cache.len() as u8);
+        // All of the entries, so the header is always the same size
+        for &(bucket_index, fingerprint) in self.eviction_cache.victims.iter() {
+            bytes.extend_from_slice(&bucket_index.to_le_bytes());
+            bytes.push(fingerprint);
+        }
+        bytes
+    }
+
+    /// Rebuild a CF from the output of `to_bytes` (or `compact_serialize`)
+    ///
+    /// The filter must be queried with the same `Hasher` it was built with, or lookups will be meaningless. The seed is restored from the header, so a seeded filter doesn't need to be rebuilt with `new_with_seed`.
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::InvalidEncoding`: the bytes don't start with the magic bytes, are truncated, have trailing data, or describe a filter this build can't represent (wrong bucket size, a bucket count that isn't a power of two, or an eviction cache that's overfull, points outside the filter or holds the empty fingerprint)
+    /// - `CuckooFilterError::UnsupportedFormatVersion`: the bytes were written with a format version this build doesn't know
+    ///
+    /// A `compact_serialize` header says how many empty buckets were left off the end, and they're all allocated again here, so a few dozen bytes can ask for gigabytes. Use `from_bytes_with_limit` for bytes you don't trust.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        CuckooFilter::from_bytes_with_limit(bytes, u32::MAX)
+    }
+
+    /// Rebuild a CF like `from_bytes`, but refuse one with more than `max_buckets` buckets before allocating anything
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let filter = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+    /// let bytes = filter.compact_serialize();
+    /// assert!(CuckooFilter::<Murmur3Hasher>::from_bytes_with_limit(&bytes, 1024).is_ok());
+    /// assert_eq!(
+    ///     CuckooFilter::<Murmur3Hasher>::from_bytes_with_limit(&bytes, 512).unwrap_err(),
+    ///     CuckooFilterError::CapacityExceedsItemLimit
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit`: the header describes a filter with more than `max_buckets` buckets
+    /// - Anything `from_bytes` can return
+    pub fn from_bytes_with_limit(
+        bytes: &[u8],
+        max_buckets: u32,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        let header_len = header_len(bytes)?;
+        if bytes.len() < header_len {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        let (header, fingerprints) = bytes.split_at(header_len);
+        let header = Header::decode::<B>(header)?;
+        if header.length_u32 > max_buckets {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        if fingerprints.len() != header.stored_buckets() * B {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        let data = fingerprints
+            .chunks_exact(B)
+            .map(|chunk| {
+                let mut bucket = [0; B];
+                bucket.copy_from_slice(chunk);
+                bucket
+            })
+            .collect();
+        CuckooFilter::from_header(header, data)
+    }
+
+    /// Put a filter back together from its decoded header and the buckets that were stored (the empty tail is filled back in)
+    pub(super) fn from_header(
+        header: Header,
+        mut data: Vec<[Fingerprint; B]>,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        data.resize(header.length_u32 as usize, [0; B]);
+        let mut filter = CuckooFilter::<H, B>::new_with_seed(0, header.seed)?;
+        filter.data = data;
+        filter.length_u32 = header.length_u32;
+        filter.eviction_cache = header.eviction_cache;
+        filter.recount();
+        if !filter.verify_no_zero_fingerprints() {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        Ok(filter)
+    }
+
+    /// The fingerprint array as it sits in memory, bucket by bucket, with no header (`num_buckets() * B` bytes)
+    ///
+    /// For shipping a filter in a wire format of your own without copying it. Only the buckets are here: the eviction cache and the seed are left out, so send the seed separately if the filter has one, and expect items in the cache to be lost (see `from_raw_parts`). Use `to_bytes` to keep everything.
+    ///
+    /// ```
+    /// use cuckoo_filter::*;
+    ///
+    /// let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    /// let _ = filter.insert(&"hello, I am some data");
+    /// let restored =
+    ///     CuckooFilter::<Murmur3Hasher>::from_raw_parts(filter.as_bytes(), filter.num_buckets() as u32)
+    ///         .unwrap();
+    /// assert!(restored.contains(&"hello, I am some data"));
+    /// ```
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_flattened()
+    }
+
+    /// Rebuild a CF from the output of `as_bytes` and its number of buckets, copying the bytes
+    ///
+    /// The new filter is unseeded and starts with an empty eviction cache, so anything that was in the original's cache isn't found anymore (check `is_full` on the sender if that matters). It must be queried with the same `Hasher` the original was built with.
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::InvalidEncoding`: `length_u32` isn't a power of two, or `bytes` isn't exactly `length_u32 * B` bytes long
+    pub fn from_raw_parts(
+        bytes: &[u8],
+        length_u32: u32,
+    ) -> Result<CuckooFilter<H, B>, CuckooFilterError> {
+        if !length_u32.is_power_of_two() || bytes.len() != length_u32 as usize * B {
+            return Err(CuckooFilterError::InvalidEncoding);
+        }
+        let mut filter = CuckooFilter::<H, B>::new(0, false)?;
+        filter.data = bytes
+            .chunks_exact(B)
+            .map(|chunk| {
+                let mut bucket = [0; B];
+                bucket.copy_from_slice(chunk);
+                bucket
+            })
+            .collect();
+        filter.length_u32 = length_u32;
+        filter.recount();
+        Ok(filter)
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::BUCKET_SIZE;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..50u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let bytes = cf.to_bytes();
+        assert_eq!(bytes.len(), HEADER_LEN + 128);
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored, cf);
+        assert!((0..50u64).all(|i| restored.contains(&i)));
+    }
+
+    #[test]
+    fn compact_round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        for i in 0..20u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // Make sure there's an empty tail to trim, wherever the items landed
+        for bucket in cf.data[768..].iter_mut() {
+            *bucket = [0; BUCKET_SIZE];
+        }
+        let compact = cf.compact_serialize();
+        assert!(compact.len() <= HEADER_LEN + 768 * BUCKET_SIZE);
+        assert!(compact.len() < cf.to_bytes().len());
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&compact).unwrap();
+        assert_eq!(restored.data, cf.data);
+        assert_eq!(restored.to_bytes(), cf.to_bytes());
+        // Claiming more trimmed buckets than there are is rejected
+        let mut bad_trim = compact.clone();
+        bad_trim[14..18].copy_from_slice(&5000u32.to_le_bytes());
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&bad_trim).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+    }
+
+    // A bare header can claim 2^31 trimmed buckets, which from_bytes would allocate
+    #[test]
+    fn compact_expansion_is_bounded() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new(4, false).unwrap();
+        let mut huge = cf.compact_serialize();
+        assert_eq!(huge.len(), HEADER_LEN);
+        huge[5..9].copy_from_slice(&(1u32 << 31).to_le_bytes());
+        huge[14..18].copy_from_slice(&(1u32 << 31).to_le_bytes());
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes_with_limit(&huge, 1 << 20).unwrap_err(),
+            CuckooFilterError::CapacityExceedsItemLimit
+        );
+        let small = cf.compact_serialize();
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes_with_limit(&small, 1 << 20).unwrap(),
+            cf
+        );
+    }
+
+    // A full filter must come back full, and the item stuck in the cache must still be found
+    #[test]
+    fn raw_parts_round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..700u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let bytes = cf.as_bytes();
+        assert_eq!(bytes.len(), cf.num_buckets() * BUCKET_SIZE);
+        // A view over the buckets, not a copy
+        assert_eq!(bytes.as_ptr(), cf.data.as_ptr() as *const u8);
+        let restored = CuckooFilter::<Murmur3Hasher>::from_raw_parts(bytes, cf.length_u32).unwrap();
+        assert_eq!(restored, cf);
+        assert_eq!(restored.len(), 700);
+        assert!((0..700u64).all(|i| restored.contains(&i)));
+        // The eviction cache doesn't come along
+        cf.eviction_cache.push(3, 9);
+        let restored =
+            CuckooFilter::<Murmur3Hasher>::from_raw_parts(cf.as_bytes(), cf.length_u32).unwrap();
+        assert!(!restored.is_full());
+        assert_eq!(restored.eviction_cache.victims(), &[]);
+        // Bucket counts that aren't powers of two, and lengths that don't match
+        for (length, bytes_len) in [(0, 0), (3, 3 * BUCKET_SIZE), (256, 256 * BUCKET_SIZE - 1)] {
+            assert_eq!(
+                CuckooFilter::<Murmur3Hasher>::from_raw_parts(&vec![0; bytes_len], length)
+                    .unwrap_err(),
+                CuckooFilterError::InvalidEncoding
+            );
+        }
+    }
+
+    #[test]
+    fn estimate_count_after_deserializing() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        let mut inserted = 0;
+        while cf.insert(&inserted).is_ok() {
+            inserted += 1;
+        }
+        // The insert that filled the cache still stored its item
+        inserted += 1;
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&cf.to_bytes()).unwrap();
+        assert_eq!(restored.estimate_count(), inserted);
+        assert_eq!(restored.len(), inserted);
+        // The raw array loses the cache, and only the cache
+        let raw =
+            CuckooFilter::<Murmur3Hasher>::from_raw_parts(cf.as_bytes(), cf.length_u32).unwrap();
+        assert_eq!(raw.estimate_count(), inserted - EVICTION_CACHE_CAPACITY);
+        // Edits the count doesn't know about yet
+        cf.raw_buckets_mut()[0] = [0; BUCKET_SIZE];
+        let lost = cf.len() - cf.estimate_count();
+        assert!(lost > 0);
+        cf.recompute_metadata();
+        assert_eq!(cf.len(), inserted - lost);
+    }
+
+    #[test]
+    fn eviction_cache_round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(8, false).unwrap();
+        let mut attempted: Vec<u64> = Vec::new();
+        for i in 0..100u64 {
+            attempted.push(i);
+            if cf.insert(&i).is_err() {
+                break;
+            }
+        }
+        assert!(cf.is_full());
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&cf.to_bytes()).unwrap();
+        assert!(restored.is_full());
+        assert_eq!(
+            restored.eviction_cache.victims(),
+            cf.eviction_cache.victims()
+        );
+        assert!(attempted.iter().all(|i| restored.contains(i)));
+    }
+
+    // Filters written before the cache grew past one fingerprint still load
+    #[test]
+    fn reads_version_1() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        for i in 0..50u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let mut v1 = Vec::new();
+        v1.extend_from_slice(MAGIC);
+        v1.push(1);
+        v1.extend_from_slice(&cf.length_u32.to_le_bytes());
+        v1.push(BUCKET_SIZE as u8);
+        v1.extend_from_slice(&3u32.to_le_bytes());
+        v1.push(42);
+        v1.push(1);
+        v1.extend_from_slice(&0u32.to_le_bytes());
+        v1.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(v1.len(), V1_HEADER_LEN);
+        for bucket in cf.data.iter() {
+            v1.extend_from_slice(bucket);
+        }
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&v1).unwrap();
+        assert_eq!(restored.data, cf.data);
+        assert_eq!(restored.eviction_cache.victims(), &[(3, 42)]);
+        assert_eq!(restored.len(), 51);
+        assert!((0..50u64).all(|i| restored.contains(&i)));
+        // The used flag is still checked
+        v1[15] = 2;
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&v1).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+    }
+
+    // Items placed under a seed are only findable if the seed comes back with them
+    #[test]
+    fn seeded_round_trip() {
+        let mut cf = CuckooFilter::<Murmur3Hasher>::new_with_seed(1024, 7).unwrap();
+        for i in 0..500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        let restored = CuckooFilter::<Murmur3Hasher>::from_bytes(&cf.to_bytes()).unwrap();
+        assert_eq!(restored.seed(), 7);
+        assert!((0..500u64).all(|i| restored.contains(&i)));
+        // The seed really does move items around: an unseeded copy misses plenty of them
+        let mut unseeded = CuckooFilter::<Murmur3Hasher>::from_bytes(&cf.to_bytes()).unwrap();
+        unseeded.seed = 0;
+        assert!((0..500u64).filter(|i| !unseeded.contains(i)).count() > 100);
+    }
+
+    #[test]
+    fn reject_malformed_bytes() {
+        let cf = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+        let bytes = cf.to_bytes();
+        // Truncated
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&bytes[..3]).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // Wrong bucket size
+        let mut wrong_bucket_size = bytes.clone();
+        wrong_bucket_size[9] = 8;
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&wrong_bucket_size).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // Eviction cache in use, but holding the empty fingerprint
+        let mut zero_victim = bytes.clone();
+        zero_victim[18] = 1;
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&zero_victim).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // Eviction cache pointing outside the filter
+        let mut outside = zero_victim.clone();
+        outside[23] = 7;
+        assert!(CuckooFilter::<Murmur3Hasher>::from_bytes(&outside).is_ok());
+        outside[19..23].copy_from_slice(&32u32.to_le_bytes());
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&outside).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // More cached fingerprints than the cache holds
+        let mut overfull = bytes.clone();
+        overfull[18] = EVICTION_CACHE_CAPACITY as u8 + 1;
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&overfull).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // Not a serialized filter at all
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&wrong_magic).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+        // Written by a future version
+        let mut future_version = bytes.clone();
+        future_version[4] = FORMAT_VERSION + 1;
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&future_version).unwrap_err(),
+            CuckooFilterError::UnsupportedFormatVersion
+        );
+        // Bucket count that isn't a power of two (the fingerprints are padded to match, so only the count is wrong)
+        let mut odd_length = bytes.clone();
+        odd_length[5..9].copy_from_slice(&33u32.to_le_bytes());
+        odd_length.extend_from_slice(&[0; BUCKET_SIZE]);
+        assert_eq!(
+            CuckooFilter::<Murmur3Hasher>::from_bytes(&odd_length).unwrap_err(),
+            CuckooFilterError::InvalidEncoding
+        );
+    }
+}
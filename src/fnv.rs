@@ -0,0 +1,147 @@
+//! FNV-1a hash, a lighter alternative to Murmur3 for short keys
+//!
+//! FNV-1a handles one byte at a time with an XOR and a multiply, with no block buffering and no finalization step, so hashing a `u32` or `u64` ID is only a handful of instructions.
+
+/// The standard 64-bit FNV offset basis
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The standard 64-bit FNV prime
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a, as a `Hasher` for use with `CuckooFilter`
+///
+/// Bytes are streamed in as they're written, and integers are written little endian so digests don't depend on the platform.
+///
+/// ### Weaker mixing than Murmur3
+///
+/// FNV-1a has weaker avalanche than `Murmur3Hasher`: the low bits of the digest only depend on the low bits of each input byte. The filter picks buckets from the low half of the digest, so inputs that mostly differ in the high bits of their bytes land in fewer buckets than they should, which means more collisions, a filter that reports itself full sooner, and a somewhat higher false positive rate. Ordinary IDs are fine, but use a keyed `SipHasher13` (see `CuckooFilter::new_with_keyed_hasher`) if the keys may be chosen by an adversary.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilter::<Fnv1aHasher>::new(1024, false).unwrap();
+/// let _ = filter.insert(&42u64);
+/// assert!(filter.contains(&42u64));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fnv1aHasher {
+    state: u64,
+}
+
+impl Fnv1aHasher {
+    /// Create a new instance, starting from the offset basis
+    pub fn new() -> Self {
+        Fnv1aHasher {
+            state: OFFSET_BASIS,
+        }
+    }
+}
+
+impl Default for Fnv1aHasher {
+    fn default() -> Self {
+        Fnv1aHasher::new()
+    }
+}
+
+impl core::hash::Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write(&(i as u64).to_le_bytes());
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CuckooFilter;
+    use core::hash::{Hash, Hasher};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    // Same settings as the Murmur3 collision test
+    const NUM_SAMPLES: usize = 10000;
+    const ACCEPTABLE_COLLISION_RATE: f32 = 0.01;
+
+    #[test]
+    fn fnv1a_known_digests() {
+        // Reference values for 64-bit FNV-1a
+        let digest_of = |bytes: &[u8]| {
+            let mut hasher = Fnv1aHasher::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(digest_of(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(digest_of(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(digest_of(b"foobar"), 0x8594_4171_f739_67e8);
+        // Integers are little endian on every platform
+        let mut hasher = Fnv1aHasher::new();
+        0x0102_0304u32.hash(&mut hasher);
+        assert_eq!(hasher.finish(), digest_of(&[4, 3, 2, 1]));
+    }
+
+    // Check implementation of hash function by counting the number of hash collisions for some random data
+    #[test]
+    fn collision_rate_fnv1a() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut input_set: HashSet<String> = HashSet::with_capacity(NUM_SAMPLES);
+        let mut output_set: HashSet<u64> = HashSet::with_capacity(NUM_SAMPLES);
+        for i in 0..NUM_SAMPLES {
+            let random_string: String = (&mut rng)
+                .sample_iter::<char, _>(&rand::distributions::Standard)
+                .take(i % 12)
+                .collect();
+            let mut hasher = Fnv1aHasher::default();
+            random_string.hash(&mut hasher);
+            _ = input_set.insert(random_string);
+            _ = output_set.insert(hasher.finish());
+        }
+        assert!(
+            input_set.len() - output_set.len()
+                < (ACCEPTABLE_COLLISION_RATE * NUM_SAMPLES as f32) as usize
+        );
+    }
+
+    #[test]
+    fn works_as_filter_hasher() {
+        let mut cf = CuckooFilter::<Fnv1aHasher>::new(4096, false).unwrap();
+        for i in 0..3500u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((0..3500u64).all(|i| cf.contains(&i)));
+        let false_positives = (1_000_000..1_100_000u64).filter(|i| cf.contains(i)).count();
+        assert!(false_positives < 100_000 * 5 / 100, "{false_positives}");
+        for i in 0..3500u64 {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert!(cf.is_empty());
+    }
+}
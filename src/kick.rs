@@ -0,0 +1,74 @@
+//! The insert path shared by the filter variants built around a single-entry eviction cache
+//!
+//! `LargeCuckooFilter`, `CuckooFilter16`, `PackedCuckooFilter`, `CountingCuckooFilter` and `AdaptiveCuckooFilter` number their buckets and fill their slots differently, but place an entry the same way: into a free slot of either candidate bucket, or else by kicking entries along a chain of up to `MAX_EVICTIONS` buckets, parking whatever is still homeless at the end in the eviction cache. Each one describes its table through `KickTable` and gets `place` from it. (`CuckooFilter` has its own version, with insert policies and a bigger cache.)
+
+use crate::filter::{CuckooFilterError, SplitMix64, BUCKET_SIZE, MAX_EVICTIONS};
+
+/// A table of `BUCKET_SIZE`-slot buckets plus a one-entry eviction cache, which `place` inserts into
+pub(crate) trait KickTable {
+    /// How buckets are numbered
+    type Index: Copy;
+    /// What a slot holds: a fingerprint, plus anything that has to move along with it
+    type Entry: Copy;
+
+    /// Put an entry into the first free slot of a bucket, if there is one
+    fn try_insert_at_bucket(&mut self, bucket_index: Self::Index, entry: Self::Entry) -> bool;
+
+    /// Put an entry into one slot of a bucket, handing back whatever was there
+    fn swap_slot(
+        &mut self,
+        bucket_index: Self::Index,
+        slot: usize,
+        entry: Self::Entry,
+    ) -> Self::Entry;
+
+    /// The other candidate bucket for an entry sitting in `bucket_index`
+    fn other_bucket(&self, bucket_index: Self::Index, entry: &Self::Entry) -> Self::Index;
+
+    /// Does an eviction chain for this entry start at its first candidate bucket? (It does when the fingerprint is even)
+    fn kicks_from_first(entry: &Self::Entry) -> bool;
+
+    fn eviction_cache(&mut self) -> &mut Option<(Self::Index, Self::Entry)>;
+
+    fn slot_rng(&mut self) -> &mut SplitMix64;
+
+    /// Put a new entry into a free slot in one of its buckets, evicting along a chain if both are full (the same scheme as `CuckooFilter`)
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the eviction cache was already taken, or the chain ran out of kicks (the entry left homeless then takes the cache)
+    fn place(
+        &mut self,
+        candidate_1: Self::Index,
+        candidate_2: Self::Index,
+        entry: Self::Entry,
+    ) -> Result<(), CuckooFilterError> {
+        if self.eviction_cache().is_some() {
+            return Err(CuckooFilterError::OutOfSpace);
+        }
+        for bucket_index in [candidate_1, candidate_2] {
+            if self.try_insert_at_bucket(bucket_index, entry) {
+                return Ok(());
+            }
+        }
+        // If both buckets are full, begin eviction process
+        let mut target_bucket_index = if Self::kicks_from_first(&entry) {
+            candidate_1
+        } else {
+            candidate_2
+        };
+        // The entry currently looking for a home (starts as the new one, then whatever it displaced)
+        let mut homeless = entry;
+        for kick in 0..MAX_EVICTIONS {
+            // If kick == 0, we already tried inserting into a bucket
+            if kick > 0 && self.try_insert_at_bucket(target_bucket_index, homeless) {
+                return Ok(());
+            }
+            let slot = (self.slot_rng().next_u64() % BUCKET_SIZE as u64) as usize;
+            homeless = self.swap_slot(target_bucket_index, slot, homeless);
+            target_bucket_index = self.other_bucket(target_bucket_index, &homeless);
+        }
+        *self.eviction_cache() = Some((target_bucket_index, homeless));
+        Err(CuckooFilterError::OutOfSpace)
+    }
+}
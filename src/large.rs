@@ -0,0 +1,294 @@
+//! Cuckoo Filter with 64-bit bucket indices, for more items than `CuckooFilter` can index
+//!
+//! `CuckooFilter` numbers its buckets with a `u32`, which caps it at about 8.5 billion items (`ITEM_LIMIT`). That keeps its bookkeeping small, and it's plenty for most uses, so it stays the default. This type numbers buckets with a `u64` instead, for corpora beyond that.
+//!
+//! The catch is the digest. `CuckooFilter` takes the bucket from the low 32 bits of the 64-bit digest and the fingerprint from the next 8. With more than 2^32 buckets, the bucket needs more of the digest than that, so here the fingerprint comes from the top byte and the bucket from everything below it. That allows up to 2^56 buckets, and means the hasher must spread entropy over all 64 bits of its output (as `Murmur3Hasher` and `SipHasher13` do, and a hasher that only fills the low 32 bits does not).
+
+use crate::filter::{
+    CuckooFilterError, Fingerprint, SplitMix64, BUCKET_SIZE, FINGERPRINT_MULTIPLIER,
+};
+use crate::kick::KickTable;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// Identifies a bucket in a `LargeCuckooFilter`
+pub type BucketIndex64 = u64;
+
+/// The most buckets a `LargeCuckooFilter` can have: the fingerprint takes the top byte of the digest, leaving 56 bits for the bucket
+const MAX_BUCKETS: u64 = 1 << 56;
+
+/// The largest capacity `LargeCuckooFilter::new` accepts (2^58 with the default bucket size)
+pub(crate) const LARGE_ITEM_LIMIT: u64 = MAX_BUCKETS * BUCKET_SIZE as u64;
+
+/// A Cuckoo Filter whose buckets are numbered with a `u64`, so it can hold more than `ITEM_LIMIT` items
+///
+/// Otherwise it works like `CuckooFilter` (same bucket size, byte fingerprints, and alternate bucket relation), but only has the basic operations. Small ones work fine too, though a `CuckooFilter` of the same capacity is the better choice.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = LargeCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug)]
+pub struct LargeCuckooFilter<H: Hasher + Default> {
+    eviction_cache: Option<(BucketIndex64, Fingerprint)>,
+    data: Vec<[Fingerprint; BUCKET_SIZE]>,
+    length_u64: u64,
+    slot_rng: SplitMix64,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> LargeCuckooFilter<H> {
+    /// Try to create a new filter, sized the same way as `CuckooFilter::new`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `LARGE_ITEM_LIMIT` (2^58), or more buckets than this platform can address
+    pub fn new(max_items: u64) -> Result<LargeCuckooFilter<H>, CuckooFilterError> {
+        if max_items > LARGE_ITEM_LIMIT {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let length_u64 = (max_items / BUCKET_SIZE as u64).next_power_of_two();
+        let number_of_buckets =
+            usize::try_from(length_u64).map_err(|_| CuckooFilterError::CapacityExceedsItemLimit)?;
+        Ok(LargeCuckooFilter {
+            eviction_cache: None,
+            data: vec![[0; BUCKET_SIZE]; number_of_buckets],
+            length_u64,
+            slot_rng: SplitMix64::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    /// How many fingerprint slots this filter has in total
+    pub fn capacity(&self) -> u64 {
+        self.length_u64 * BUCKET_SIZE as u64
+    }
+
+    /// Is the filter full of items (practically speaking)? See `CuckooFilter::is_full`
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_some()
+    }
+
+    /// Add item to filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.place(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.cache_matches(candidate_1, candidate_2, fingerprint)
+            || self.data[candidate_1 as usize].contains(&fingerprint)
+            || self.data[candidate_2 as usize].contains(&fingerprint)
+    }
+
+    /// Delete an item from the filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.cache_matches(candidate_1, candidate_2, fingerprint) {
+            self.eviction_cache = None;
+            return Ok(());
+        }
+        for &bucket_index in &[candidate_1, candidate_2] {
+            if let Some(slot) = self.data[bucket_index as usize]
+                .iter_mut()
+                .find(|slot| **slot == fingerprint)
+            {
+                *slot = 0;
+                return Ok(());
+            }
+        }
+        Err(CuckooFilterError::ItemDoesNotExist)
+    }
+
+    /// Calculate the buckets and fingerprint given a `Hash`able item
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex64, BucketIndex64, Fingerprint) {
+        let mut hasher = H::default();
+        item.hash(&mut hasher);
+        digest_to_buckets(hasher.finish(), self.length_u64)
+    }
+
+    fn cache_matches(
+        &self,
+        candidate_1: BucketIndex64,
+        candidate_2: BucketIndex64,
+        fingerprint: Fingerprint,
+    ) -> bool {
+        self.eviction_cache.is_some_and(|(index, cached)| {
+            cached == fingerprint && (index == candidate_1 || index == candidate_2)
+        })
+    }
+}
+
+impl<H: Hasher + Default> KickTable for LargeCuckooFilter<H> {
+    type Index = BucketIndex64;
+    type Entry = Fingerprint;
+
+    fn try_insert_at_bucket(
+        &mut self,
+        bucket_index: BucketIndex64,
+        fingerprint: Fingerprint,
+    ) -> bool {
+        match self.data[bucket_index as usize]
+            .iter_mut()
+            .find(|slot| **slot == 0)
+        {
+            Some(slot) => {
+                *slot = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn swap_slot(
+        &mut self,
+        bucket_index: BucketIndex64,
+        slot: usize,
+        fingerprint: Fingerprint,
+    ) -> Fingerprint {
+        core::mem::replace(&mut self.data[bucket_index as usize][slot], fingerprint)
+    }
+
+    fn other_bucket(
+        &self,
+        bucket_index: BucketIndex64,
+        fingerprint: &Fingerprint,
+    ) -> BucketIndex64 {
+        alternate_bucket(bucket_index, *fingerprint, self.length_u64)
+    }
+
+    fn kicks_from_first(fingerprint: &Fingerprint) -> bool {
+        fingerprint.is_multiple_of(2)
+    }
+
+    fn eviction_cache(&mut self) -> &mut Option<(BucketIndex64, Fingerprint)> {
+        &mut self.eviction_cache
+    }
+
+    fn slot_rng(&mut self) -> &mut SplitMix64 {
+        &mut self.slot_rng
+    }
+}
+
+/// The two candidate buckets and fingerprint for a digest, out of `length_u64` buckets
+///
+/// The same as `buckets_for`, except that the fingerprint is the top byte of the digest (bumped to 1 if zero) and the first bucket is the rest of it modulo `length_u64`.
+const fn digest_to_buckets(
+    hash_value: u64,
+    length_u64: u64,
+) -> (BucketIndex64, BucketIndex64, Fingerprint) {
+    let mut fingerprint = (hash_value >> 56) as Fingerprint;
+    // Zero marks an empty slot, so it can't be a fingerprint
+    if fingerprint == 0 {
+        fingerprint = 1;
+    }
+    let bucket_1 = (hash_value & (MAX_BUCKETS - 1)) % length_u64;
+    (
+        bucket_1,
+        alternate_bucket(bucket_1, fingerprint, length_u64),
+        fingerprint,
+    )
+}
+
+/// The other candidate bucket for a fingerprint. Like `CuckooFilter`'s, applying it twice gets back to the start
+const fn alternate_bucket(
+    bucket_index: BucketIndex64,
+    fingerprint: Fingerprint,
+    length_u64: u64,
+) -> BucketIndex64 {
+    (bucket_index ^ (fingerprint as u64).wrapping_mul(FINGERPRINT_MULTIPLIER as u64)) % length_u64
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buckets_for, CuckooFilter, Murmur3Hasher};
+
+    #[test]
+    fn bucket_math_past_u32() {
+        let hash_value = 0xab00_00f1_2345_6789;
+        // 2^40 buckets: the first bucket keeps bits a `u32` index would drop
+        let (candidate_1, candidate_2, fingerprint) = digest_to_buckets(hash_value, 1 << 40);
+        assert_eq!(candidate_1, 0xf1_2345_6789);
+        assert!(candidate_1 > u32::MAX as u64);
+        assert_eq!(fingerprint, 0xab);
+        assert!(candidate_2 < 1 << 40);
+        assert_eq!(
+            alternate_bucket(candidate_2, fingerprint, 1 << 40),
+            candidate_1
+        );
+        // 2^33 buckets: wraps at the new modulus, not at 2^32
+        let (candidate_1, candidate_2, _) = digest_to_buckets(hash_value, 1 << 33);
+        assert_eq!(candidate_1, 0x1_2345_6789);
+        assert!(candidate_2 < 1 << 33);
+        assert_eq!(
+            alternate_bucket(candidate_2, fingerprint, 1 << 33),
+            candidate_1
+        );
+        // The most buckets there can be, where the fingerprint byte is all that's left over
+        let (candidate_1, candidate_2, _) = digest_to_buckets(u64::MAX, MAX_BUCKETS);
+        assert_eq!(candidate_1, MAX_BUCKETS - 1);
+        assert_eq!(
+            alternate_bucket(candidate_2, 0xff, MAX_BUCKETS),
+            candidate_1
+        );
+        // Up to 2^32 buckets, the first bucket matches the default filter's
+        assert_eq!(
+            digest_to_buckets(hash_value, 1 << 10).0,
+            buckets_for(hash_value, 1 << 10).0 as u64
+        );
+    }
+
+    #[test]
+    fn insert_lookup_delete_until_full() {
+        let mut cf = LargeCuckooFilter::<Murmur3Hasher>::new(1024).unwrap();
+        assert_eq!(cf.capacity(), 1024);
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!(cf.is_full());
+        assert!(i > cf.capacity() * 9 / 10);
+        assert!((0..=i).all(|i| cf.contains(&i)));
+        assert_eq!(cf.insert(&(i + 1)), Err(CuckooFilterError::OutOfSpace));
+        for i in 0..=i {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert!(!cf.is_full());
+        assert!(cf.data.iter().flatten().all(|&slot| slot == 0));
+        // About the same false positive rate as the default filter at the same load
+        let mut byte_filter = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        for i in 0..900u64 {
+            assert!(cf.insert(&i).is_ok());
+            assert!(byte_filter.insert(&i).is_ok());
+        }
+        let negatives = 1_000_000..1_100_000u64;
+        let hits = negatives.clone().filter(|i| cf.contains(i)).count();
+        let byte_hits = negatives.filter(|i| byte_filter.contains(i)).count();
+        assert!(
+            hits.abs_diff(byte_hits) < byte_hits / 5,
+            "{hits} vs {byte_hits}"
+        );
+        assert_eq!(
+            LargeCuckooFilter::<Murmur3Hasher>::new(LARGE_ITEM_LIMIT + 1).unwrap_err(),
+            CuckooFilterError::CapacityExceedsItemLimit
+        );
+    }
+}
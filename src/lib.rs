@@ -6,11 +6,13 @@
 //!
 //! This implementation supports `![no_std]`, but it does require `alloc` (to use a Vector).
 //!
+//! Every public method works with only `core` and `alloc` (the `tests/no_std.rs` integration test checks this). Anything that does need `std` is gated behind the `std` feature and says so in its documentation.
+//!
 //! ### Using this Cuckoo Filter
-//! There are three primary APIs for the filter: `insert`, `lookup`, and `delete` (this follows the paper's naming convention).
+//! There are three primary APIs for the filter: `insert`, `contains`, and `delete` (the paper calls the middle one `lookup`, which still works as a deprecated alias).
 //!
 //! - `insert` places an item into the filter (well, it places the item's "fingerprint" into the filter)
-//! - `lookup` checks if the item is in the filter, and returns `true` if found, or `false` if not found
+//! - `contains` checks if the item is in the filter, and returns `true` if it's probably there (a false positive is possible), or `false` if it's definitely not
 //! - `delete` removes an item from the filter
 //!
 //! ```rust
@@ -28,28 +30,69 @@
 //! assert!(insertion.is_ok());
 //!
 //! // Lookups cannot fail - returns True or False
-//! let is_found = filter.lookup(&item);
+//! let is_found = filter.contains(&item);
 //! assert!(is_found);
 //!
 //! // Deletion can fail if you try to delete something not in the filter
 //! let deletion = filter.delete(&item);
 //! assert!(deletion.is_ok());
 //! // Check that the item is no longer present
-//! assert!(!filter.lookup(&item));
+//! assert!(!filter.contains(&item));
 //! ```
 //!
 //! The Cuckoo Filter may report that it is full, despite there being empty slots left. This occurs when there are too many hash collisions on the data. You may want to create the filter with a bit of headroom to mitigate the risk of this. Unit testing indicates that this _usually_ doesn't happen until the filter is well over 95% full, but your luck may vary.
 
-// We use the standard library in tests only, not for runtime
-#![cfg_attr(not(test), no_std)]
+// We use the standard library in tests (and for the `std` feature) only, not for runtime
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 extern crate alloc;
 
 // REMINDER for self: code test coverage here https://lib.rs/crates/cargo-llvm-cov
 
+mod adaptive;
+#[cfg(feature = "const_capacity")]
+mod const_capacity;
+mod counting;
+mod crc32;
 mod filter;
+mod fnv;
+mod kick;
+mod large;
 mod murmur3;
+mod packed;
+mod scalable;
+mod siphash;
+mod wide;
+mod xxhash;
 
+pub use adaptive::AdaptiveCuckooFilter;
+#[cfg(feature = "const_capacity")]
+pub use const_capacity::ConstCuckooFilter;
+pub use counting::CountingCuckooFilter;
+pub use filter::buckets_for;
+pub use filter::tiered_lookup;
+pub use filter::BucketIndex;
 pub use filter::CuckooFilter;
+pub use filter::CuckooFilterBuilder;
 pub use filter::CuckooFilterError;
+pub use filter::CuckooFilterRef;
+pub use filter::FilterStats;
+pub use filter::Fingerprint;
+pub use filter::InsertIfNewResult;
+pub use filter::InsertOutcome;
+pub use filter::InsertPolicy;
+pub use filter::KeyedHasher;
+pub use filter::Membership;
+pub use filter::SeededHasher;
+pub use filter::TierResult;
+pub use filter::EVICTION_CACHE_SLOT;
+pub use fnv::Fnv1aHasher;
+pub use large::BucketIndex64;
+pub use large::LargeCuckooFilter;
 pub use murmur3::murmur3_x86_64bit;
 pub use murmur3::Murmur3Hasher;
+pub use packed::PackedCuckooFilter;
+pub use scalable::ScalableCuckooFilter;
+pub use siphash::SipHasher13;
+pub use wide::CuckooFilter16;
+pub use wide::Fingerprint16;
+pub use xxhash::XxHash64;
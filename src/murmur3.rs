@@ -2,6 +2,7 @@
 //!
 //! This is modified from the `murmur3` package <https://docs.rs/murmur3/latest/murmur3/>. See `NOTICE` file for copyright information.
 
+use crate::filter::SeededHasher;
 use core::hash::Hasher;
 use core::ops::Shl;
 
@@ -33,8 +34,8 @@ fn fmix32(k: u32) -> u32 {
 
 /// Murmur3 hash function, modified from the `murmur3` package <https://docs.rs/murmur3/latest/murmur3/>. See `NOTICE` file for copyright information.
 ///
-/// This function has been modified to remove its dependency on the standard library.
-pub fn _murmur3_x86_128(source: &[u8], seed: u32) -> u128 {
+/// This is the shared core behind both `_murmur3_x86_128` and `Murmur3Hasher`: it mixes `source` into a running state (h1-h4) and returns the finalized state. Routing both paths through here guarantees that hashing bytes via the `Hash` trait gives the same digest as calling `murmur3_x86_64bit` on them.
+fn murmur3_x86_128_core(source: &[u8], state: [u32; 4]) -> [u32; 4] {
     const C1: u32 = 0x239b_961b;
     const C2: u32 = 0xab0e_9789;
     const C3: u32 = 0x38b3_4ae5;
@@ -45,10 +46,7 @@ pub fn _murmur3_x86_128(source: &[u8], seed: u32) -> u128 {
     const C8: u32 = 0x32ac_3b17;
     const M: u32 = 5;
 
-    let mut h1: u32 = seed;
-    let mut h2: u32 = seed;
-    let mut h3: u32 = seed;
-    let mut h4: u32 = seed;
+    let [mut h1, mut h2, mut h3, mut h4] = state;
 
     let mut buf: [u8; 16] = [0; 16];
     let mut processed: usize = 0;
@@ -108,8 +106,7 @@ pub fn _murmur3_x86_128(source: &[u8], seed: u32) -> u128 {
             h2 = h2.wrapping_add(h1);
             h3 = h3.wrapping_add(h1);
             h4 = h4.wrapping_add(h1);
-            let x = ((h4 as u128) << 96) | ((h3 as u128) << 64) | ((h2 as u128) << 32) | h1 as u128;
-            return x;
+            return [h1, h2, h3, h4];
         } else {
             let mut k1 = 0;
             let mut k2 = 0;
@@ -175,7 +172,15 @@ pub fn _murmur3_x86_128(source: &[u8], seed: u32) -> u128 {
     unreachable!("The loop should always return in the last block")
 }
 
-/// 64
+/// Murmur3 hash function, modified from the `murmur3` package <https://docs.rs/murmur3/latest/murmur3/>. See `NOTICE` file for copyright information.
+///
+/// This function has been modified to remove its dependency on the standard library.
+pub fn _murmur3_x86_128(source: &[u8], seed: u32) -> u128 {
+    let [h1, h2, h3, h4] = murmur3_x86_128_core(source, [seed; 4]);
+    ((h4 as u128) << 96) | ((h3 as u128) << 64) | ((h2 as u128) << 32) | h1 as u128
+}
+
+/// 64 bit Murmur3 (the lower half of the 128 bit digest), for use with the `_stateless` APIs
 pub fn murmur3_x86_64bit(source: &[u8]) -> u64 {
     _murmur3_x86_128(source, 0u32) as u64
 }
@@ -185,25 +190,44 @@ pub fn murmur3_x86_64bit(source: &[u8]) -> u64 {
 /// h1-h4 are moved into registers to support accumulation over byte chunks (such as strings)
 ///
 /// IMPORTANT! A `thinner` wrapper which calls the _murmur3 function above will FAIL for strings that are evaluated chunk by chunk (but work for numbers, leading to a nasty bug during runtime)
-#[derive(Debug, Default)]
+///
+/// ### Byte-like inputs
+///
+/// The standard library frames byte-like data when hashing it: `str` writes a trailing `0xff` terminator, and slices (`[u8]`, `Vec<u8>`, arrays) write a length prefix before their bytes. When the whole item is one `str` or byte slice, this hasher drops that framing, so a `&str` or `&[u8]` hashed through the `Hash` trait produces exactly `murmur3_x86_64bit` of the same bytes. That's what lets an item inserted with `insert` be found with `contains_stateless` (and vice versa).
+///
+/// Anything else (a tuple or struct with a string in it, say) is hashed exactly as written, framing included. Dropping framing inside a bigger item would make different items collide: `(b"a" as &[u8], 255u8)` would hash the same as `"a"`.
+#[derive(Debug, Default, Clone)]
 pub struct Murmur3Hasher {
     h1: u32,
     h2: u32,
     h3: u32,
     h4: u32,
+    /// Whether what's been written so far could still be a lone `str` or byte slice
+    framing: Framing,
 }
 
-impl Murmur3Hasher {
-    const C1: u32 = 0x239b_961b;
-    const C2: u32 = 0xab0e_9789;
-    const C3: u32 = 0x38b3_4ae5;
-    const C4: u32 = 0xa1e3_8b93;
-    const C5: u32 = 0x561c_cd1b;
-    const C6: u32 = 0x0bca_a747;
-    const C7: u32 = 0x96cd_1c35;
-    const C8: u32 = 0x32ac_3b17;
-    const M: u32 = 5;
+/// Tracks whether an item is a lone `str` or byte slice, one write at a time
+///
+/// h1-h4 always hold everything as written. While the item could still turn out to be byte-like, we also keep the state with the framing left out, and `finish` uses that one if the item is complete.
+#[derive(Debug, Default, Clone, Copy)]
+enum Framing {
+    /// Nothing written yet
+    #[default]
+    Start,
+    /// Only a `usize`, which may be a slice length prefix, along with the state from before it
+    Length(usize, [u32; 4]),
+    /// One chunk of bytes, after a length prefix matching it if there was one
+    Bytes {
+        unframed: [u32; 4],
+        length_prefixed: bool,
+    },
+    /// One chunk of bytes followed by the `0xff` terminator
+    Str([u32; 4]),
+    /// Anything else, which is hashed as written
+    Other,
+}
 
+impl Murmur3Hasher {
     /// Create a new instance. The default is to ignore the seed, so you must call `seed()` if you want to set it.
     pub fn new() -> Self {
         Murmur3Hasher {
@@ -211,175 +235,106 @@ impl Murmur3Hasher {
             h2: 0u32,
             h3: 0u32,
             h4: 0u32,
+            framing: Framing::Start,
         }
     }
 
     /// Optional, if you want to provide a seed to Murmur3
+    ///
+    /// `CuckooFilter::new_with_seeded_hasher` seeds through this (see `SeededHasher`). `CuckooFilter::new_with_seed` doesn't: it works with any `Hasher` by writing the seed in ahead of each item.
     pub fn seed(&mut self, seed_value: u32) {
         self.h1 = seed_value;
         self.h2 = seed_value;
         self.h3 = seed_value;
         self.h4 = seed_value;
     }
+
+    fn state(&self) -> [u32; 4] {
+        [self.h1, self.h2, self.h3, self.h4]
+    }
+
+    /// Run bytes through the shared Murmur3 core, carrying our state along
+    fn mix(&mut self, bytes: &[u8]) {
+        [self.h1, self.h2, self.h3, self.h4] = murmur3_x86_128_core(bytes, self.state());
+    }
+
+    /// Mix in a fixed-width integer (little endian, so digests don't depend on the platform)
+    fn write_integer(&mut self, bytes: &[u8]) {
+        self.mix(bytes);
+        self.framing = Framing::Other;
+    }
+}
+
+impl SeededHasher for Murmur3Hasher {
+    fn with_seed(seed: u32) -> Self {
+        let mut hasher = Murmur3Hasher::new();
+        hasher.seed(seed);
+        hasher
+    }
 }
 
 impl Hasher for Murmur3Hasher {
     fn finish(&self) -> u64 {
-        let x = ((self.h4 as u128) << 96)
-            | ((self.h3 as u128) << 64)
-            | ((self.h2 as u128) << 32)
-            | self.h1 as u128;
+        let [h1, h2, h3, h4] = match self.framing {
+            Framing::Bytes { unframed, .. } | Framing::Str(unframed) => unframed,
+            _ => self.state(),
+        };
+        let x = ((h4 as u128) << 96) | ((h3 as u128) << 64) | ((h2 as u128) << 32) | h1 as u128;
         x as u64
     }
 
     fn write(&mut self, bytes: &[u8]) {
-        let mut buf: [u8; 16] = [0; 16];
-        let mut processed: usize = 0;
-        let mut done: bool = false;
-        while processed <= bytes.len() && !done {
-            let remaining = bytes.len() - processed;
-            let read = remaining.min(16);
-            buf[..read].copy_from_slice(&bytes[processed..processed + read]);
-            processed += read;
-
-            if read == 16 {
-                let k1 = u32::from_le_bytes(copy_into_array(&buf[0..4]));
-                let k2 = u32::from_le_bytes(copy_into_array(&buf[4..8]));
-                let k3 = u32::from_le_bytes(copy_into_array(&buf[8..12]));
-                let k4 = u32::from_le_bytes(copy_into_array(&buf[12..16]));
-                self.h1 ^= k1
-                    .wrapping_mul(Murmur3Hasher::C1)
-                    .rotate_left(15)
-                    .wrapping_mul(Murmur3Hasher::C2);
-                self.h1 = self
-                    .h1
-                    .rotate_left(19)
-                    .wrapping_add(self.h2)
-                    .wrapping_mul(Murmur3Hasher::M)
-                    .wrapping_add(Murmur3Hasher::C5);
-                self.h2 ^= k2
-                    .wrapping_mul(Murmur3Hasher::C2)
-                    .rotate_left(16)
-                    .wrapping_mul(Murmur3Hasher::C3);
-                self.h2 = self
-                    .h2
-                    .rotate_left(17)
-                    .wrapping_add(self.h3)
-                    .wrapping_mul(Murmur3Hasher::M)
-                    .wrapping_add(Murmur3Hasher::C6);
-                self.h3 ^= k3
-                    .wrapping_mul(Murmur3Hasher::C3)
-                    .rotate_left(17)
-                    .wrapping_mul(Murmur3Hasher::C4);
-                self.h3 = self
-                    .h3
-                    .rotate_left(15)
-                    .wrapping_add(self.h4)
-                    .wrapping_mul(Murmur3Hasher::M)
-                    .wrapping_add(Murmur3Hasher::C7);
-                self.h4 ^= k4
-                    .wrapping_mul(Murmur3Hasher::C4)
-                    .rotate_left(18)
-                    .wrapping_mul(Murmur3Hasher::C1);
-                self.h4 = self
-                    .h4
-                    .rotate_left(13)
-                    .wrapping_add(self.h1)
-                    .wrapping_mul(Murmur3Hasher::M)
-                    .wrapping_add(Murmur3Hasher::C8);
-            } else if read == 0 {
-                self.h1 ^= processed as u32;
-                self.h2 ^= processed as u32;
-                self.h3 ^= processed as u32;
-                self.h4 ^= processed as u32;
-                self.h1 = self.h1.wrapping_add(self.h2);
-                self.h1 = self.h1.wrapping_add(self.h3);
-                self.h1 = self.h1.wrapping_add(self.h4);
-                self.h2 = self.h2.wrapping_add(self.h1);
-                self.h3 = self.h3.wrapping_add(self.h1);
-                self.h4 = self.h4.wrapping_add(self.h1);
-                self.h1 = fmix32(self.h1);
-                self.h2 = fmix32(self.h2);
-                self.h3 = fmix32(self.h3);
-                self.h4 = fmix32(self.h4);
-                self.h1 = self.h1.wrapping_add(self.h2);
-                self.h1 = self.h1.wrapping_add(self.h3);
-                self.h1 = self.h1.wrapping_add(self.h4);
-                self.h2 = self.h2.wrapping_add(self.h1);
-                self.h3 = self.h3.wrapping_add(self.h1);
-                self.h4 = self.h4.wrapping_add(self.h1);
-                done = true;
-            } else {
-                let mut k1 = 0;
-                let mut k2 = 0;
-                let mut k3 = 0;
-                let mut k4 = 0;
-                if read >= 15 {
-                    k4 ^= (buf[14] as u32).shl(16);
-                }
-                if read >= 14 {
-                    k4 ^= (buf[13] as u32).shl(8);
-                }
-                if read >= 13 {
-                    k4 ^= buf[12] as u32;
-                    k4 = k4
-                        .wrapping_mul(Murmur3Hasher::C4)
-                        .rotate_left(18)
-                        .wrapping_mul(Murmur3Hasher::C1);
-                    self.h4 ^= k4;
-                }
-                if read >= 12 {
-                    k3 ^= (buf[11] as u32).shl(24);
-                }
-                if read >= 11 {
-                    k3 ^= (buf[10] as u32).shl(16);
-                }
-                if read >= 10 {
-                    k3 ^= (buf[9] as u32).shl(8);
-                }
-                if read >= 9 {
-                    k3 ^= buf[8] as u32;
-                    k3 = k3
-                        .wrapping_mul(Murmur3Hasher::C3)
-                        .rotate_left(17)
-                        .wrapping_mul(Murmur3Hasher::C4);
-                    self.h3 ^= k3;
-                }
-                if read >= 8 {
-                    k2 ^= (buf[7] as u32).shl(24);
-                }
-                if read >= 7 {
-                    k2 ^= (buf[6] as u32).shl(16);
-                }
-                if read >= 6 {
-                    k2 ^= (buf[5] as u32).shl(8);
-                }
-                if read >= 5 {
-                    k2 ^= buf[4] as u32;
-                    k2 = k2
-                        .wrapping_mul(Murmur3Hasher::C2)
-                        .rotate_left(16)
-                        .wrapping_mul(Murmur3Hasher::C3);
-                    self.h2 ^= k2;
-                }
-                if read >= 4 {
-                    k1 ^= (buf[3] as u32).shl(24);
-                }
-                if read >= 3 {
-                    k1 ^= (buf[2] as u32).shl(16);
-                }
-                if read >= 2 {
-                    k1 ^= (buf[1] as u32).shl(8);
-                }
-                if read >= 1 {
-                    k1 ^= buf[0] as u32;
-                }
-                k1 = k1.wrapping_mul(Murmur3Hasher::C1);
-                k1 = k1.rotate_left(15);
-                k1 = k1.wrapping_mul(Murmur3Hasher::C2);
-                self.h1 ^= k1;
-            }
-        }
+        self.mix(bytes);
+        self.framing = match self.framing {
+            Framing::Start => Framing::Bytes {
+                unframed: self.state(),
+                length_prefixed: false,
+            },
+            // A length prefix followed by exactly that many bytes is slice framing
+            Framing::Length(length, start) if length == bytes.len() => Framing::Bytes {
+                unframed: murmur3_x86_128_core(bytes, start),
+                length_prefixed: true,
+            },
+            _ => Framing::Other,
+        };
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.mix(&[i]);
+        // `str` hashes as its bytes followed by `0xff`
+        self.framing = match self.framing {
+            Framing::Bytes {
+                unframed,
+                length_prefixed: false,
+            } if i == 0xff => Framing::Str(unframed),
+            _ => Framing::Other,
+        };
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_integer(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_integer(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write_integer(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write_integer(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        let start = self.state();
+        self.mix(&(i as u64).to_le_bytes());
+        // Only the first write can be a slice length prefix
+        self.framing = match self.framing {
+            Framing::Start => Framing::Length(i, start),
+            _ => Framing::Other,
+        };
     }
 }
 
@@ -401,7 +356,6 @@ mod tests {
     fn get_random_string(rng: &mut ChaCha8Rng, len: usize) -> String {
         rng.sample_iter::<char, _>(&rand::distributions::Standard)
             .take(len)
-            .map(char::from)
             .collect()
     }
 
@@ -473,4 +427,51 @@ mod tests {
         let h2 = hasher.finish();
         assert_eq!(h1, h2);
     }
+
+    // The `Hash` path must agree with the raw byte function for byte-like inputs, otherwise `insert` and `contains_stateless` disagree
+    #[test]
+    fn murmur3_hasher_matches_raw_function_for_bytes() {
+        let text = "the cat says meow";
+        let expected = murmur3_x86_64bit(text.as_bytes());
+        let digest_of = |item: &dyn Fn(&mut Murmur3Hasher)| {
+            let mut hasher = Murmur3Hasher::default();
+            item(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(digest_of(&|h| text.hash(h)), expected);
+        assert_eq!(digest_of(&|h| text.to_string().hash(h)), expected);
+        assert_eq!(digest_of(&|h| text.as_bytes().hash(h)), expected);
+        assert_eq!(digest_of(&|h| text.as_bytes().to_vec().hash(h)), expected);
+        let array = [1u8, 2, 3, 4, 5];
+        assert_eq!(digest_of(&|h| array.hash(h)), murmur3_x86_64bit(&array));
+        assert_eq!(digest_of(&|h| "".hash(h)), murmur3_x86_64bit(&[]));
+    }
+
+    // Framing is only dropped from a lone `str` or byte slice, so items that merely contain one can't collide with it
+    #[test]
+    fn murmur3_hasher_keeps_framing_inside_other_items() {
+        let digest_of = |item: &dyn Fn(&mut Murmur3Hasher)| {
+            let mut hasher = Murmur3Hasher::default();
+            item(&mut hasher);
+            hasher.finish()
+        };
+        assert_ne!(
+            digest_of(&|h| (b"a" as &[u8], 255u8).hash(h)),
+            digest_of(&|h| "a".hash(h))
+        );
+        assert_ne!(
+            digest_of(&|h| (5usize, "abcde").hash(h)),
+            digest_of(&|h| "abcde".hash(h))
+        );
+        assert_ne!(
+            digest_of(&|h| (3usize, b"abc" as &[u8]).hash(h)),
+            digest_of(&|h| (b"abc" as &[u8]).hash(h))
+        );
+        // A bare `usize` is data, not a length prefix
+        assert_ne!(
+            digest_of(&|h| 1usize.hash(h)),
+            digest_of(&|h| 2usize.hash(h))
+        );
+        assert_ne!(digest_of(&|h| 1usize.hash(h)), digest_of(&|_| {}));
+    }
 }
@@ -0,0 +1,339 @@
+//! Cuckoo Filter with a fingerprint width chosen at runtime
+//!
+//! `CuckooFilter` spends a whole byte on each fingerprint. This variant packs fingerprints of any width from 4 to 16 bits back to back, so the same type spans the false positive rate / memory tradeoff: each extra bit roughly halves the false positive rate, and costs `capacity / 8` bytes.
+
+use crate::filter::{
+    alternate_bucket, BucketIndex, CuckooFilterError, SplitMix64, BUCKET_SIZE, ITEM_LIMIT,
+};
+use crate::kick::KickTable;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// Narrowest supported fingerprint, in bits
+const MIN_FP_BITS: u32 = 4;
+/// Widest supported fingerprint, in bits
+const MAX_FP_BITS: u32 = 16;
+
+/// A Cuckoo Filter storing `fp_bits`-wide fingerprints, bit-packed
+///
+/// Buckets and fingerprints come from the digest the same way as in `CuckooFilter` (the fingerprint is the low `fp_bits` of the upper 32 bits, bumped to 1 if zero), so an 8-bit packed filter places items exactly where a `CuckooFilter` of the same size would.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(128, 12).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug)]
+pub struct PackedCuckooFilter<H: Hasher + Default> {
+    eviction_cache: Option<(BucketIndex, u16)>,
+    // Slot `i` lives at bits `i * fp_bits..(i + 1) * fp_bits`, least significant first. Two bytes of padding at the end let every slot be read as a 3-byte window
+    data: Vec<u8>,
+    fp_bits: u32,
+    length_u32: u32,
+    slot_rng: SplitMix64,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> PackedCuckooFilter<H> {
+    /// Try to create a new filter holding `max_items` (rounded as in `CuckooFilter::new`), with `fp_bits`-wide fingerprints
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    /// - `CuckooFilterError::UnsupportedFingerprintBits`: `fp_bits` is outside 4 to 16
+    pub fn new_with_fp_bits(
+        max_items: usize,
+        fp_bits: u32,
+    ) -> Result<PackedCuckooFilter<H>, CuckooFilterError> {
+        if !(MIN_FP_BITS..=MAX_FP_BITS).contains(&fp_bits) {
+            return Err(CuckooFilterError::UnsupportedFingerprintBits);
+        }
+        if max_items > ITEM_LIMIT {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let number_of_buckets: usize = (max_items / BUCKET_SIZE).next_power_of_two();
+        let total_bits = number_of_buckets * BUCKET_SIZE * fp_bits as usize;
+        Ok(PackedCuckooFilter {
+            eviction_cache: None,
+            data: vec![0; total_bits.div_ceil(8) + 2],
+            fp_bits,
+            length_u32: number_of_buckets as u32,
+            slot_rng: SplitMix64::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Width of each fingerprint, in bits
+    pub fn fp_bits(&self) -> u32 {
+        self.fp_bits
+    }
+
+    /// How many fingerprint slots this filter has in total
+    pub fn capacity(&self) -> usize {
+        self.length_u32 as usize * BUCKET_SIZE
+    }
+
+    /// Is the filter full of items (practically speaking)? See `CuckooFilter::is_full`
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_some()
+    }
+
+    /// Add item to filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.place(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.cache_matches(candidate_1, candidate_2, fingerprint)
+            || self
+                .find_slot(candidate_1, candidate_2, fingerprint)
+                .is_some()
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Delete an item from the filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.cache_matches(candidate_1, candidate_2, fingerprint) {
+            self.eviction_cache = None;
+            return Ok(());
+        }
+        let slot_index = self
+            .find_slot(candidate_1, candidate_2, fingerprint)
+            .ok_or(CuckooFilterError::ItemDoesNotExist)?;
+        self.set_slot(slot_index, 0);
+        Ok(())
+    }
+
+    /// Calculate the buckets and `fp_bits`-wide fingerprint given a `Hash`able item
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex, u16) {
+        let mut hasher = H::default();
+        item.hash(&mut hasher);
+        let hash_value = hasher.finish();
+        let mut fingerprint = ((hash_value >> 32) as u32 & self.fingerprint_mask()) as u16;
+        // Zero marks an empty slot, so it can't be a fingerprint
+        if fingerprint == 0 {
+            fingerprint = 1;
+        }
+        let bucket_1 = hash_value as u32 % self.length_u32;
+        (
+            bucket_1,
+            self.alternate_bucket(bucket_1, fingerprint),
+            fingerprint,
+        )
+    }
+
+    /// The other candidate bucket for a fingerprint (the same relation as `CuckooFilter` uses)
+    fn alternate_bucket(&self, bucket_index: BucketIndex, fingerprint: u16) -> BucketIndex {
+        // For widths up to 8 bits this is exactly `CuckooFilter`'s relation; wider fingerprints fold their high bits in first
+        let folded = (fingerprint ^ (fingerprint >> 8)) as u8;
+        alternate_bucket(bucket_index, folded, self.length_u32)
+    }
+
+    fn fingerprint_mask(&self) -> u32 {
+        (1 << self.fp_bits) - 1
+    }
+
+    /// Read the fingerprint in a slot (0 if empty)
+    fn get_slot(&self, slot_index: usize) -> u16 {
+        let bit = slot_index * self.fp_bits as usize;
+        let byte = bit / 8;
+        let window =
+            u32::from_le_bytes([self.data[byte], self.data[byte + 1], self.data[byte + 2], 0]);
+        ((window >> (bit % 8)) & self.fingerprint_mask()) as u16
+    }
+
+    /// Overwrite the fingerprint in a slot, leaving its neighbours alone
+    fn set_slot(&mut self, slot_index: usize, fingerprint: u16) {
+        let bit = slot_index * self.fp_bits as usize;
+        let byte = bit / 8;
+        let shift = bit % 8;
+        let mut window =
+            u32::from_le_bytes([self.data[byte], self.data[byte + 1], self.data[byte + 2], 0]);
+        window &= !(self.fingerprint_mask() << shift);
+        window |= (fingerprint as u32) << shift;
+        self.data[byte..byte + 3].copy_from_slice(&window.to_le_bytes()[..3]);
+    }
+
+    /// The first slot in either bucket holding this fingerprint
+    fn find_slot(&self, candidate_1: u32, candidate_2: u32, fingerprint: u16) -> Option<usize> {
+        [candidate_1, candidate_2]
+            .iter()
+            .flat_map(|&bucket_index| {
+                let first_slot = bucket_index as usize * BUCKET_SIZE;
+                first_slot..first_slot + BUCKET_SIZE
+            })
+            .find(|&slot_index| self.get_slot(slot_index) == fingerprint)
+    }
+
+    fn cache_matches(&self, candidate_1: u32, candidate_2: u32, fingerprint: u16) -> bool {
+        self.eviction_cache.is_some_and(|(index, cached)| {
+            cached == fingerprint && (index == candidate_1 || index == candidate_2)
+        })
+    }
+}
+
+impl<H: Hasher + Default> KickTable for PackedCuckooFilter<H> {
+    type Index = BucketIndex;
+    type Entry = u16;
+
+    fn try_insert_at_bucket(&mut self, bucket_index: BucketIndex, fingerprint: u16) -> bool {
+        let first_slot = bucket_index as usize * BUCKET_SIZE;
+        match (first_slot..first_slot + BUCKET_SIZE).find(|&slot| self.get_slot(slot) == 0) {
+            Some(slot_index) => {
+                self.set_slot(slot_index, fingerprint);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn swap_slot(&mut self, bucket_index: BucketIndex, slot: usize, fingerprint: u16) -> u16 {
+        let slot_index = bucket_index as usize * BUCKET_SIZE + slot;
+        let displaced = self.get_slot(slot_index);
+        self.set_slot(slot_index, fingerprint);
+        displaced
+    }
+
+    fn other_bucket(&self, bucket_index: BucketIndex, fingerprint: &u16) -> BucketIndex {
+        self.alternate_bucket(bucket_index, *fingerprint)
+    }
+
+    fn kicks_from_first(fingerprint: &u16) -> bool {
+        fingerprint.is_multiple_of(2)
+    }
+
+    fn eviction_cache(&mut self) -> &mut Option<(BucketIndex, u16)> {
+        &mut self.eviction_cache
+    }
+
+    fn slot_rng(&mut self) -> &mut SplitMix64 {
+        &mut self.slot_rng
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{buckets_for, CuckooFilter, Murmur3Hasher};
+
+    fn false_positive_rate(fp_bits: u32) -> f64 {
+        let mut cf = PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(4096, fp_bits).unwrap();
+        for i in 0..3000u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        assert!((0..3000u64).all(|i| cf.contains(&i)));
+        let false_positives = (1_000_000..1_100_000u64).filter(|i| cf.contains(i)).count();
+        false_positives as f64 / 100_000.0
+    }
+
+    #[test]
+    fn wider_fingerprints_have_fewer_false_positives() {
+        let rate_8 = false_positive_rate(8);
+        let rate_12 = false_positive_rate(12);
+        assert!(rate_8 > 0.01);
+        assert!(rate_12 < rate_8 / 8.0);
+    }
+
+    #[test]
+    fn packed_slots_round_trip() {
+        for fp_bits in [4, 6, 8, 12, 16] {
+            let mut cf =
+                PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(64, fp_bits).unwrap();
+            let mask = (1u32 << fp_bits) - 1;
+            for slot_index in 0..cf.capacity() {
+                cf.set_slot(
+                    slot_index,
+                    ((slot_index as u32).wrapping_mul(2654435761) & mask) as u16,
+                );
+            }
+            for slot_index in 0..cf.capacity() {
+                assert_eq!(
+                    cf.get_slot(slot_index),
+                    ((slot_index as u32).wrapping_mul(2654435761) & mask) as u16
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn six_bit_packing_against_byte_filter() {
+        let mut unpacked = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let mut packed_8 = PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(4096, 8).unwrap();
+        let mut packed_6 = PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(4096, 6).unwrap();
+        for i in 0..3000u64 {
+            assert!(unpacked.insert(&i).is_ok());
+            assert!(packed_8.insert(&i).is_ok());
+            assert!(packed_6.insert(&i).is_ok());
+        }
+        // Four 6-bit fingerprints per 3 bytes, plus the padding
+        assert_eq!(packed_6.data.len(), 4096 * 3 / 4 + 2);
+        assert!((0..3000u64).all(|i| packed_6.contains(&i)));
+        // At 8 bits the packed filter answers exactly like the byte filter
+        let negatives = 1_000_000..1_100_000u64;
+        assert!(negatives
+            .clone()
+            .all(|i| unpacked.contains(&i) == packed_8.contains(&i)));
+        // Two fewer bits means about 4 times the false positives
+        let unpacked_hits = negatives.clone().filter(|i| unpacked.contains(i)).count();
+        let packed_hits = negatives.filter(|i| packed_6.contains(i)).count();
+        assert!(
+            packed_hits > unpacked_hits * 3 && packed_hits < unpacked_hits * 5,
+            "{packed_hits} vs {unpacked_hits}"
+        );
+        for i in 0..3000u64 {
+            assert!(packed_6.delete(&i).is_ok());
+        }
+        assert!((0..packed_6.capacity()).all(|slot_index| packed_6.get_slot(slot_index) == 0));
+    }
+
+    #[test]
+    fn packed_insert_lookup_delete() {
+        for fp_bits in [8, 12] {
+            let mut cf =
+                PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(1024, fp_bits).unwrap();
+            for i in 0..800u64 {
+                assert!(cf.insert(&i).is_ok());
+            }
+            for i in 0..400u64 {
+                assert!(cf.delete(&i).is_ok());
+            }
+            assert!((400..800u64).all(|i| cf.contains(&i)));
+        }
+        // At 8 bits, placement matches the byte filter's math
+        let cf = PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(1024, 8).unwrap();
+        let mut hasher = Murmur3Hasher::default();
+        7u64.hash(&mut hasher);
+        let (candidate_1, candidate_2, fingerprint) = buckets_for(hasher.finish(), 256);
+        assert_eq!(
+            cf.buckets_from_item(&7u64),
+            (candidate_1, candidate_2, fingerprint as u16)
+        );
+        assert_eq!(
+            PackedCuckooFilter::<Murmur3Hasher>::new_with_fp_bits(1024, 17).unwrap_err(),
+            CuckooFilterError::UnsupportedFingerprintBits
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Scalable Cuckoo Filter, which adds sub-filters instead of running out of space
+//!
+//! For streams where the final item count isn't known up front: when the newest sub-filter fills up, another one twice its size is added, and inserts carry on there. Lookups have to check every sub-filter, so each one added costs a little lookup speed and adds its own false positives (see `ScalableCuckooFilter`).
+
+use crate::filter::{CuckooFilter, CuckooFilterError, ITEM_LIMIT};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+/// A chain of Cuckoo Filters that grows by appending a bigger one whenever the newest is full
+///
+/// ### False positives
+///
+/// A lookup is a false positive if any sub-filter gives one, so the rates add up: with `k` sub-filters that are each nearly full, expect about `k` times the rate of a single full `CuckooFilter` (around 3% each). Sizes double, so `k` only grows with the logarithm of the item count, but starting with a realistic `initial_capacity` keeps it small.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = ScalableCuckooFilter::<Murmur3Hasher>::new(64).unwrap();
+/// for i in 0..1000 {
+///     assert!(filter.insert(&i).is_ok());
+/// }
+/// assert!(filter.contains(&500));
+/// assert!(filter.num_filters() > 1);
+/// ```
+#[derive(Debug)]
+pub struct ScalableCuckooFilter<H: Hasher + Default> {
+    // Oldest first; inserts go to the last one
+    filters: Vec<CuckooFilter<H>>,
+}
+
+impl<H: Hasher + Default> ScalableCuckooFilter<H> {
+    /// Try to create a new Scalable Cuckoo Filter, starting with one sub-filter sized for `initial_capacity` items (rounded as in `CuckooFilter::new`)
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new(initial_capacity: usize) -> Result<ScalableCuckooFilter<H>, CuckooFilterError> {
+        Ok(ScalableCuckooFilter {
+            filters: vec![CuckooFilter::new(initial_capacity, false)?],
+        })
+    }
+
+    /// How many sub-filters there are
+    pub fn num_filters(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Total fingerprint slots across all sub-filters
+    pub fn capacity(&self) -> usize {
+        self.filters.iter().map(|filter| filter.capacity()).sum()
+    }
+
+    /// How many fingerprints are stored across all sub-filters (see `CuckooFilter::len`)
+    pub fn len(&self) -> usize {
+        self.filters.iter().map(|filter| filter.len()).sum()
+    }
+
+    /// Does the filter hold nothing at all?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Add item to filter, adding a sub-filter first if the newest one is full
+    ///
+    /// An insert that fills a sub-filter still succeeds: like a plain `CuckooFilter`, the sub-filter keeps the homeless fingerprint in its eviction cache, so the item is findable without being inserted again (and stored twice). The next insert starts the new sub-filter.
+    ///
+    /// This doesn't fail (sub-filters have no collision limit, and there's always room for another); it returns a `Result` to match `CuckooFilter::insert`.
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        if self.active().is_full() {
+            let next_capacity = (self.active().capacity() * 2).min(ITEM_LIMIT);
+            self.filters.push(CuckooFilter::new(next_capacity, false)?);
+        }
+        match self.active_mut().insert(item) {
+            // The item went in, whatever got pushed into the eviction cache to make room
+            Ok(()) | Err(CuckooFilterError::OutOfSpace) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Check if item is in any sub-filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        self.filters.iter().any(|filter| filter.contains(item))
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Delete an item from the filter, checking the newest sub-filter first
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: no sub-filter holds the item
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        self.filters
+            .iter_mut()
+            .rev()
+            .find_map(|filter| filter.delete(item).ok())
+            .ok_or(CuckooFilterError::ItemDoesNotExist)
+    }
+
+    fn active(&self) -> &CuckooFilter<H> {
+        self.filters
+            .last()
+            .expect("there's always at least one sub-filter")
+    }
+
+    fn active_mut(&mut self) -> &mut CuckooFilter<H> {
+        self.filters
+            .last_mut()
+            .expect("there's always at least one sub-filter")
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Murmur3Hasher;
+
+    #[test]
+    fn never_runs_out_of_space() {
+        let mut cf = ScalableCuckooFilter::<Murmur3Hasher>::new(128).unwrap();
+        for i in 0..20_000u64 {
+            assert!(cf.insert(&i).is_ok());
+        }
+        // 128 + 256 + ... doubling until 20k items fit
+        assert!(cf.num_filters() >= 8);
+        assert!(cf.capacity() >= 20_000);
+        assert_eq!(cf.len(), 20_000);
+        assert!((0..20_000u64).all(|i| cf.contains(&i)));
+        for i in 0..10_000u64 {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert_eq!(cf.len(), 10_000);
+        assert!((10_000..20_000u64).all(|i| cf.contains(&i)));
+        // About 8 sub-filters' worth of false positives, at most
+        let false_positives = (1_000_000..1_100_000u64).filter(|i| cf.contains(i)).count();
+        assert!(false_positives < 100_000 * 8 * 3 / 100);
+    }
+}
@@ -0,0 +1,287 @@
+//! SipHash-1-3, a keyed hash for filters that may face adversarial input
+//!
+//! Murmur3 and FNV-1a are unkeyed, so anyone can compute where an item lands and craft a batch of items that all share the same two buckets, filling the filter long before its capacity. SipHash mixes a secret 128-bit key into every digest, so without the key an attacker can't predict bucket placement. This is the same construction (and round count) the standard library uses for `HashMap`.
+
+use crate::filter::KeyedHasher;
+
+/// SipHash-1-3 with a 128-bit key, as a `Hasher`
+///
+/// The key's little endian bytes are the 16 key bytes of the SipHash specification. `Default` uses an all-zero key, which is no better than an unkeyed hash: use `CuckooFilter::new_with_keyed_hasher` to give a filter a secret key. Integers are written little endian so digests don't depend on the platform.
+///
+/// This makes bucket placement unpredictable to anyone without the key. It doesn't make the fingerprints cryptographically strong: a fingerprint is still 8 bits, so false positives happen at the usual rate and can be found by trial and error through lookups.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let key = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+/// let mut filter = CuckooFilter::<SipHasher13>::new_with_keyed_hasher(1024, key).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SipHasher13 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// Bytes written so far that don't yet fill a whole 8 byte word, packed little endian
+    tail: u64,
+    tail_len: usize,
+    /// Total bytes written (only the low byte makes it into the digest)
+    length: usize,
+}
+
+impl SipHasher13 {
+    /// Create a hasher keyed with `key`
+    pub fn new_with_key(key: u128) -> Self {
+        let k0 = key as u64;
+        let k1 = (key >> 64) as u64;
+        SipHasher13 {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            tail_len: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13);
+        self.v1 ^= self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16);
+        self.v3 ^= self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21);
+        self.v3 ^= self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17);
+        self.v1 ^= self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    /// Mix in one 8 byte word (a single compression round, the "1" in 1-3)
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Default for SipHasher13 {
+    fn default() -> Self {
+        SipHasher13::new_with_key(0)
+    }
+}
+
+impl KeyedHasher for SipHasher13 {
+    fn with_key(key: u128) -> Self {
+        SipHasher13::new_with_key(key)
+    }
+}
+
+impl core::hash::Hasher for SipHasher13 {
+    fn finish(&self) -> u64 {
+        // Finalize a copy, so more bytes can still be written afterwards
+        let mut state = self.clone();
+        let last_word = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(last_word);
+        state.v2 ^= 0xff;
+        // Three finalization rounds, the "3" in 1-3
+        for _ in 0..3 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+        let mut bytes = bytes;
+        // Top up a partial word first
+        while self.tail_len > 0 && !bytes.is_empty() {
+            self.tail |= (bytes[0] as u64) << (8 * self.tail_len);
+            self.tail_len += 1;
+            bytes = &bytes[1..];
+            if self.tail_len == 8 {
+                let word = self.tail;
+                self.compress(word);
+                self.tail = 0;
+                self.tail_len = 0;
+            }
+        }
+        let (words, remainder) = bytes.as_chunks::<8>();
+        for word in words {
+            self.compress(u64::from_le_bytes(*word));
+        }
+        for &byte in remainder {
+            self.tail |= (byte as u64) << (8 * self.tail_len);
+            self.tail_len += 1;
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write(&(i as u64).to_le_bytes());
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CuckooFilter;
+    use core::hash::{Hash, Hasher};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    // Same settings as the Murmur3 collision test
+    const NUM_SAMPLES: usize = 10000;
+    const ACCEPTABLE_COLLISION_RATE: f32 = 0.01;
+
+    // The SipHash-1-3 test vectors (as published alongside the reference implementation): the key is bytes 0 to 15, and vector `i` is the digest of bytes 0 to `i - 1`, little endian
+    const REFERENCE_VECTORS: [[u8; 8]; 64] = [
+        [0xdc, 0xc4, 0x0f, 0x05, 0x58, 0x01, 0xac, 0xab],
+        [0x93, 0xca, 0x57, 0x7d, 0xf3, 0x9b, 0xf4, 0xc9],
+        [0x4d, 0xd4, 0xc7, 0x4d, 0x02, 0x9b, 0xcb, 0x82],
+        [0xfb, 0xf7, 0xdd, 0xe7, 0xb8, 0x0a, 0xf8, 0x8b],
+        [0x28, 0x83, 0xd3, 0x88, 0x60, 0x57, 0x75, 0xcf],
+        [0x67, 0x3b, 0x53, 0x49, 0x2f, 0xd5, 0xf9, 0xde],
+        [0xa7, 0x22, 0x9f, 0xc5, 0x50, 0x2b, 0x0d, 0xc5],
+        [0x40, 0x11, 0xb1, 0x9b, 0x98, 0x7d, 0x92, 0xd3],
+        [0x8e, 0x9a, 0x29, 0x8d, 0x11, 0x95, 0x90, 0x36],
+        [0xe4, 0x3d, 0x06, 0x6c, 0xb3, 0x8e, 0xa4, 0x25],
+        [0x7f, 0x09, 0xff, 0x92, 0xee, 0x85, 0xde, 0x79],
+        [0x52, 0xc3, 0x4d, 0xf9, 0xc1, 0x18, 0xc1, 0x70],
+        [0xa2, 0xd9, 0xb4, 0x57, 0xb1, 0x84, 0xa3, 0x78],
+        [0xa7, 0xff, 0x29, 0x12, 0x0c, 0x76, 0x6f, 0x30],
+        [0x34, 0x5d, 0xf9, 0xc0, 0x11, 0xa1, 0x5a, 0x60],
+        [0x56, 0x99, 0x51, 0x2a, 0x6d, 0xd8, 0x20, 0xd3],
+        [0x66, 0x8b, 0x90, 0x7d, 0x1a, 0xdd, 0x4f, 0xcc],
+        [0x0c, 0xd8, 0xdb, 0x63, 0x90, 0x68, 0xf2, 0x9c],
+        [0x3e, 0xe6, 0x73, 0xb4, 0x9c, 0x38, 0xfc, 0x8f],
+        [0x1c, 0x7d, 0x29, 0x8d, 0xe5, 0x9d, 0x1f, 0xf2],
+        [0x40, 0xe0, 0xcc, 0xa6, 0x46, 0x2f, 0xdc, 0xc0],
+        [0x44, 0xf8, 0x45, 0x2b, 0xfe, 0xab, 0x92, 0xb9],
+        [0x2e, 0x87, 0x20, 0xa3, 0x9b, 0x7b, 0xfe, 0x7f],
+        [0x23, 0xc1, 0xe6, 0xda, 0x7f, 0x0e, 0x5a, 0x52],
+        [0x8c, 0x9c, 0x34, 0x67, 0xb2, 0xae, 0x64, 0xf4],
+        [0x79, 0x09, 0x5b, 0x70, 0x28, 0x59, 0xcd, 0x45],
+        [0xa5, 0x13, 0x99, 0xca, 0xe3, 0x35, 0x3e, 0x3a],
+        [0x35, 0x3b, 0xde, 0x4a, 0x4e, 0xc7, 0x1d, 0xa9],
+        [0x0d, 0xd0, 0x6c, 0xef, 0x02, 0xed, 0x0b, 0xfb],
+        [0xf4, 0xe1, 0xb1, 0x4a, 0xb4, 0x3c, 0xd9, 0x88],
+        [0x63, 0xe6, 0xc5, 0x43, 0xd6, 0x11, 0x0f, 0x54],
+        [0xbc, 0xd1, 0x21, 0x8c, 0x1f, 0xdd, 0x70, 0x23],
+        [0x0d, 0xb6, 0xa7, 0x16, 0x6c, 0x7b, 0x15, 0x81],
+        [0xbf, 0xf9, 0x8f, 0x7a, 0xe5, 0xb9, 0x54, 0x4d],
+        [0x3e, 0x75, 0x2a, 0x1f, 0x78, 0x12, 0x9f, 0x75],
+        [0x91, 0x6b, 0x18, 0xbf, 0xbe, 0xa3, 0xa1, 0xce],
+        [0x06, 0x62, 0xa2, 0xad, 0xd3, 0x08, 0xf5, 0x2c],
+        [0x57, 0x30, 0xc3, 0xa3, 0x2d, 0x1c, 0x10, 0xb6],
+        [0xa1, 0x36, 0x3a, 0xae, 0x96, 0x74, 0xf4, 0xb3],
+        [0x92, 0x83, 0x10, 0x7b, 0x54, 0x57, 0x6b, 0x62],
+        [0x31, 0x15, 0xe4, 0x99, 0x32, 0x36, 0xd2, 0xc1],
+        [0x44, 0xd9, 0x1a, 0x3f, 0x92, 0xc1, 0x7c, 0x66],
+        [0x25, 0x88, 0x13, 0xc8, 0xfe, 0x4f, 0x70, 0x65],
+        [0xa6, 0x49, 0x89, 0xc2, 0xd1, 0x80, 0xf2, 0x24],
+        [0x6b, 0x87, 0xf8, 0xfa, 0xed, 0x1c, 0xca, 0xc2],
+        [0x96, 0x21, 0x04, 0x9f, 0xfc, 0x4b, 0x16, 0xc2],
+        [0x23, 0xd6, 0xb1, 0x68, 0x93, 0x9c, 0x6e, 0xa1],
+        [0xfd, 0x14, 0x51, 0x8b, 0x9c, 0x16, 0xfb, 0x49],
+        [0x46, 0x4c, 0x07, 0xdf, 0xf8, 0x43, 0x31, 0x9f],
+        [0xb3, 0x86, 0xcc, 0x12, 0x24, 0xaf, 0xfd, 0xc6],
+        [0x8f, 0x09, 0x52, 0x0a, 0xd1, 0x49, 0xaf, 0x7e],
+        [0x9a, 0x2f, 0x29, 0x9d, 0x55, 0x13, 0xf3, 0x1c],
+        [0x12, 0x1f, 0xf4, 0xa2, 0xdd, 0x30, 0x4a, 0xc4],
+        [0xd0, 0x1e, 0xa7, 0x43, 0x89, 0xe9, 0xfa, 0x36],
+        [0xe6, 0xbc, 0xf0, 0x73, 0x4c, 0xb3, 0x8f, 0x31],
+        [0x80, 0xe9, 0xa7, 0x70, 0x36, 0xbf, 0x7a, 0xa2],
+        [0x75, 0x6d, 0x3c, 0x24, 0xdb, 0xc0, 0xbc, 0xb4],
+        [0x13, 0x15, 0xb7, 0xfd, 0x52, 0xd8, 0xf8, 0x23],
+        [0x08, 0x8a, 0x7d, 0xa6, 0x4d, 0x5f, 0x03, 0x8f],
+        [0x48, 0xf1, 0xe8, 0xb7, 0xe5, 0xd0, 0x9c, 0xd8],
+        [0xee, 0x44, 0xa6, 0xf7, 0xbc, 0xe6, 0xf4, 0xf6],
+        [0xf2, 0x37, 0x18, 0x0f, 0xd8, 0x9a, 0xc5, 0xae],
+        [0xe0, 0x94, 0x66, 0x4b, 0x15, 0xf6, 0xb2, 0xc3],
+        [0xa8, 0xb3, 0xbb, 0xb7, 0x62, 0x90, 0x19, 0x9d],
+    ];
+
+    #[test]
+    fn matches_reference_vectors() {
+        let key = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+        let message: Vec<u8> = (0..64).collect();
+        for (length, expected) in REFERENCE_VECTORS.iter().enumerate() {
+            let mut ours = SipHasher13::new_with_key(key);
+            ours.write(&message[..length]);
+            assert_eq!(ours.finish().to_le_bytes(), *expected, "length {length}");
+        }
+        // Writing in pieces gives the same digest as writing all at once
+        let mut pieces = SipHasher13::new_with_key(7);
+        for chunk in message.chunks(3) {
+            pieces.write(chunk);
+        }
+        let mut whole = SipHasher13::new_with_key(7);
+        whole.write(&message);
+        assert_eq!(pieces.finish(), whole.finish());
+    }
+
+    // Check implementation of hash function by counting the number of hash collisions for some random data
+    #[test]
+    fn collision_rate_siphash13() {
+        let mut rng = ChaCha8Rng::seed_from_u64(42);
+        let mut input_set: HashSet<String> = HashSet::with_capacity(NUM_SAMPLES);
+        let mut output_set: HashSet<u64> = HashSet::with_capacity(NUM_SAMPLES);
+        for i in 0..NUM_SAMPLES {
+            let random_string: String = (&mut rng)
+                .sample_iter::<char, _>(&rand::distributions::Standard)
+                .take(i % 12)
+                .collect();
+            let mut hasher = SipHasher13::new_with_key(0x1234_5678);
+            random_string.hash(&mut hasher);
+            _ = input_set.insert(random_string);
+            _ = output_set.insert(hasher.finish());
+        }
+        assert!(
+            input_set.len() - output_set.len()
+                < (ACCEPTABLE_COLLISION_RATE * NUM_SAMPLES as f32) as usize
+        );
+    }
+
+    #[test]
+    fn keys_change_bucket_placement() {
+        let mut cf_a = CuckooFilter::<SipHasher13>::new_with_keyed_hasher(4096, 1).unwrap();
+        let mut cf_b = CuckooFilter::<SipHasher13>::new_with_keyed_hasher(4096, 2).unwrap();
+        let moved = (0..1000u64)
+            .filter(|i| cf_a.peek_buckets(i) != cf_b.peek_buckets(i))
+            .count();
+        // Equal placement (same buckets and fingerprint) by chance is vanishingly rare
+        assert!(moved > 990, "{moved}");
+        for i in 0..3000u64 {
+            assert!(cf_a.insert(&i).is_ok());
+            assert!(cf_b.insert(&i).is_ok());
+        }
+        assert!((0..3000u64).all(|i| cf_a.contains(&i) && cf_b.contains(&i)));
+        assert_ne!(cf_a, cf_b);
+    }
+}
@@ -0,0 +1,248 @@
+//! Cuckoo Filter with 16-bit fingerprints
+//!
+//! With byte fingerprints, a lookup compares against 8 slots that each match by chance with probability 1/255, which puts the false positive rate near 3% once the filter is full. That's too high for some uses (such as the cryptographic protocols mentioned in the crate docs). Two-byte fingerprints cut it by a factor of about 256, for twice the memory.
+//!
+//! `PackedCuckooFilter` can also store 16-bit fingerprints, but it pays for its flexibility with bit twiddling on every slot access. This type keeps plain `u16` slots.
+
+use crate::filter::{
+    BucketIndex, CuckooFilterError, SplitMix64, BUCKET_SIZE, FINGERPRINT_MULTIPLIER, ITEM_LIMIT,
+};
+use crate::kick::KickTable;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+
+/// A fingerprint two bytes wide. 0 marks an empty slot
+pub type Fingerprint16 = u16;
+
+/// A Cuckoo Filter with 16-bit fingerprints, for a much lower false positive rate than `CuckooFilter`
+///
+/// The buckets come from the digest the same way as in `CuckooFilter`. The fingerprint is the low 16 bits of the upper half of the digest (so its low byte is `CuckooFilter`'s fingerprint), bumped to 1 if zero, and the alternate bucket uses the same multiply-and-XOR relation with the whole 16 bits.
+///
+/// ```
+/// use cuckoo_filter::*;
+///
+/// let mut filter = CuckooFilter16::<Murmur3Hasher>::new(128).unwrap();
+/// let _ = filter.insert(&"hello, I am some data");
+/// assert!(filter.contains(&"hello, I am some data"));
+/// ```
+#[derive(Debug)]
+pub struct CuckooFilter16<H: Hasher + Default> {
+    eviction_cache: Option<(BucketIndex, Fingerprint16)>,
+    data: Vec<[Fingerprint16; BUCKET_SIZE]>,
+    length_u32: u32,
+    slot_rng: SplitMix64,
+    phantom: PhantomData<H>,
+}
+
+impl<H: Hasher + Default> CuckooFilter16<H> {
+    /// Try to create a new filter, sized the same way as `CuckooFilter::new`
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::CapacityExceedsItemLimit` you tried to request a filter with a capacity larger than `ITEM_LIMIT`
+    pub fn new(max_items: usize) -> Result<CuckooFilter16<H>, CuckooFilterError> {
+        if max_items > ITEM_LIMIT {
+            return Err(CuckooFilterError::CapacityExceedsItemLimit);
+        }
+        let number_of_buckets: usize = (max_items / BUCKET_SIZE).next_power_of_two();
+        Ok(CuckooFilter16 {
+            eviction_cache: None,
+            data: vec![[0; BUCKET_SIZE]; number_of_buckets],
+            length_u32: number_of_buckets as u32,
+            slot_rng: SplitMix64::new(0),
+            phantom: PhantomData,
+        })
+    }
+
+    /// How many fingerprint slots this filter has in total
+    pub fn capacity(&self) -> usize {
+        self.data.len() * BUCKET_SIZE
+    }
+
+    /// Is the filter full of items (practically speaking)? See `CuckooFilter::is_full`
+    pub fn is_full(&self) -> bool {
+        self.eviction_cache.is_some()
+    }
+
+    /// Add item to filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::OutOfSpace`: the filter is "practically" full and will no longer accept items
+    pub fn insert<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.place(candidate_1, candidate_2, fingerprint)
+    }
+
+    /// Check if item is in filter
+    pub fn contains<T: Hash>(&self, item: &T) -> bool {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        self.cache_matches(candidate_1, candidate_2, fingerprint)
+            || self.data[candidate_1 as usize].contains(&fingerprint)
+            || self.data[candidate_2 as usize].contains(&fingerprint)
+    }
+
+    /// Check if item is in filter. The paper's name for `contains`
+    #[deprecated(note = "use `contains`, which this forwards to")]
+    pub fn lookup<T: Hash>(&self, item: &T) -> bool {
+        self.contains(item)
+    }
+
+    /// Delete an item from the filter
+    ///
+    /// # Errors
+    ///
+    /// - `CuckooFilterError::ItemDoesNotExist`: the item isn't in the filter
+    pub fn delete<T: Hash>(&mut self, item: &T) -> Result<(), CuckooFilterError> {
+        let (candidate_1, candidate_2, fingerprint) = self.buckets_from_item(item);
+        if self.cache_matches(candidate_1, candidate_2, fingerprint) {
+            self.eviction_cache = None;
+            return Ok(());
+        }
+        for &bucket_index in &[candidate_1, candidate_2] {
+            if let Some(slot) = self.data[bucket_index as usize]
+                .iter_mut()
+                .find(|slot| **slot == fingerprint)
+            {
+                *slot = 0;
+                return Ok(());
+            }
+        }
+        Err(CuckooFilterError::ItemDoesNotExist)
+    }
+
+    /// Calculate the buckets and 16-bit fingerprint given a `Hash`able item
+    fn buckets_from_item<T: Hash>(&self, item: &T) -> (BucketIndex, BucketIndex, Fingerprint16) {
+        let mut hasher = H::default();
+        item.hash(&mut hasher);
+        let hash_value = hasher.finish();
+        let mut fingerprint = (hash_value >> 32) as Fingerprint16;
+        // Zero marks an empty slot, so it can't be a fingerprint
+        if fingerprint == 0 {
+            fingerprint = 1;
+        }
+        let bucket_1 = hash_value as u32 % self.length_u32;
+        (
+            bucket_1,
+            self.alternate_bucket(bucket_1, fingerprint),
+            fingerprint,
+        )
+    }
+
+    /// The other candidate bucket for a fingerprint. Like `CuckooFilter`'s, applying it twice gets back to the start
+    fn alternate_bucket(
+        &self,
+        bucket_index: BucketIndex,
+        fingerprint: Fingerprint16,
+    ) -> BucketIndex {
+        (bucket_index ^ (fingerprint as u32).wrapping_mul(FINGERPRINT_MULTIPLIER)) % self.length_u32
+    }
+
+    fn cache_matches(
+        &self,
+        candidate_1: u32,
+        candidate_2: u32,
+        fingerprint: Fingerprint16,
+    ) -> bool {
+        self.eviction_cache.is_some_and(|(index, cached)| {
+            cached == fingerprint && (index == candidate_1 || index == candidate_2)
+        })
+    }
+}
+
+impl<H: Hasher + Default> KickTable for CuckooFilter16<H> {
+    type Index = BucketIndex;
+    type Entry = Fingerprint16;
+
+    fn try_insert_at_bucket(
+        &mut self,
+        bucket_index: BucketIndex,
+        fingerprint: Fingerprint16,
+    ) -> bool {
+        match self.data[bucket_index as usize]
+            .iter_mut()
+            .find(|slot| **slot == 0)
+        {
+            Some(slot) => {
+                *slot = fingerprint;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn swap_slot(
+        &mut self,
+        bucket_index: BucketIndex,
+        slot: usize,
+        fingerprint: Fingerprint16,
+    ) -> Fingerprint16 {
+        core::mem::replace(&mut self.data[bucket_index as usize][slot], fingerprint)
+    }
+
+    fn other_bucket(&self, bucket_index: BucketIndex, fingerprint: &Fingerprint16) -> BucketIndex {
+        self.alternate_bucket(bucket_index, *fingerprint)
+    }
+
+    fn kicks_from_first(fingerprint: &Fingerprint16) -> bool {
+        fingerprint.is_multiple_of(2)
+    }
+
+    fn eviction_cache(&mut self) -> &mut Option<(BucketIndex, Fingerprint16)> {
+        &mut self.eviction_cache
+    }
+
+    fn slot_rng(&mut self) -> &mut SplitMix64 {
+        &mut self.slot_rng
+    }
+}
+
+/* -------------------- Unit Tests -------------------- */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CuckooFilter, Murmur3Hasher};
+
+    #[test]
+    fn fewer_false_positives_than_byte_fingerprints() {
+        let mut narrow = CuckooFilter::<Murmur3Hasher>::new(4096, false).unwrap();
+        let mut wide = CuckooFilter16::<Murmur3Hasher>::new(4096).unwrap();
+        for i in 0..3800u64 {
+            assert!(narrow.insert(&i).is_ok());
+            assert!(wide.insert(&i).is_ok());
+        }
+        assert!((0..3800u64).all(|i| wide.contains(&i)));
+        let negatives = 1_000_000..1_100_000u64;
+        let narrow_hits = negatives.clone().filter(|i| narrow.contains(i)).count();
+        let wide_hits = negatives.filter(|i| wide.contains(i)).count();
+        // About 2900 vs 11 expected
+        assert!(narrow_hits > 2000, "{narrow_hits}");
+        assert!(wide_hits * 50 < narrow_hits, "{wide_hits} vs {narrow_hits}");
+    }
+
+    #[test]
+    fn insert_lookup_delete_until_full() {
+        let mut cf = CuckooFilter16::<Murmur3Hasher>::new(1024).unwrap();
+        let mut i = 0u64;
+        while cf.insert(&i).is_ok() {
+            i += 1;
+        }
+        assert!(cf.is_full());
+        assert!(i as usize > cf.capacity() * 9 / 10);
+        assert!((0..=i).all(|i| cf.contains(&i)));
+        assert_eq!(cf.insert(&(i + 1)), Err(CuckooFilterError::OutOfSpace));
+        for i in 0..=i {
+            assert!(cf.delete(&i).is_ok());
+        }
+        assert!(!cf.is_full());
+        assert!(cf.data.iter().flatten().all(|&slot| slot == 0));
+        // Placement agrees with the byte filter, and the alternate bucket relation undoes itself
+        let (candidate_1, candidate_2, fingerprint) = cf.buckets_from_item(&7u64);
+        let byte_filter = CuckooFilter::<Murmur3Hasher>::new(1024, false).unwrap();
+        assert_eq!(byte_filter.peek_buckets(&7u64).0, candidate_1);
+        assert_eq!(cf.alternate_bucket(candidate_2, fingerprint), candidate_1);
+    }
+}
@@ -0,0 +1,45 @@
+//! Exercises the public API from a `#![no_std]` crate, using only `core` and `alloc`
+//!
+//! The unit tests lean on `std` (`HashSet`, `println!`, `rand`), so they can't tell us whether the library itself stays `no_std`. This crate can: if a public method starts requiring `std`, it stops compiling here.
+
+#![no_std]
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::hash::Hasher;
+use cuckoo_filter::{murmur3_x86_64bit, CuckooFilter, CuckooFilterError, Murmur3Hasher};
+
+#[test]
+fn no_std_insert_lookup_delete() {
+    let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    let item = String::from("the cat says meow");
+    assert!(filter.insert(&item).is_ok());
+    assert!(filter.contains(&item));
+    assert!(filter.delete(&item).is_ok());
+    assert!(!filter.contains(&item));
+    assert_eq!(
+        filter.delete(&item),
+        Err(CuckooFilterError::ItemDoesNotExist)
+    );
+}
+
+#[test]
+fn no_std_stateless() {
+    let mut filter = CuckooFilter::<Murmur3Hasher>::new(128, false).unwrap();
+    let items: Vec<[u8; 4]> = (0u32..32).map(|i| i.to_le_bytes()).collect();
+    for item in items.iter() {
+        assert!(filter.insert_stateless(item, murmur3_x86_64bit).is_ok());
+    }
+    for item in items.iter() {
+        assert!(filter.contains_stateless(item, murmur3_x86_64bit));
+        assert!(filter.delete_stateless(item, murmur3_x86_64bit).is_ok());
+    }
+}
+
+#[test]
+fn no_std_hasher() {
+    let mut hasher = Murmur3Hasher::new();
+    hasher.write(b"meow");
+    assert_eq!(hasher.finish(), murmur3_x86_64bit(b"meow"));
+}